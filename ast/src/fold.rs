@@ -0,0 +1,357 @@
+use crate::*;
+
+/// Rebuilds a tree from its parts, allowing any node to be replaced by
+/// overriding the corresponding `fold_*` method. The default methods rebuild
+/// each node unchanged from its folded children.
+pub trait Fold: Sized {
+    fn fold_geode(&mut self, geode: Geode) -> Geode {
+        walk::geode(self, geode)
+    }
+
+    fn fold_module(&mut self, module: Module) -> Module {
+        walk::module(self, module)
+    }
+
+    fn fold_item(&mut self, item: Item) -> Item {
+        walk::item(self, item)
+    }
+
+    fn fold_attribute(&mut self, attribute: Attribute) -> Attribute {
+        walk::attribute(self, attribute)
+    }
+
+    fn fold_function(&mut self, function: Function) -> Function {
+        walk::function(self, function)
+    }
+
+    fn fold_identifier(&mut self, identifier: Identifier) -> Identifier {
+        identifier
+    }
+
+    fn fold_struct(&mut self, strukt: Struct) -> Struct {
+        walk::structure(self, strukt)
+    }
+
+    fn fold_struct_member(&mut self, struct_member: StructMember) -> StructMember {
+        walk::struct_member(self, struct_member)
+    }
+
+    fn fold_path(&mut self, path: Path) -> Path {
+        walk::path(self, path)
+    }
+
+    fn fold_type(&mut self, ty: Type) -> Type {
+        walk::ty(self, ty)
+    }
+
+    fn fold_function_parameter(&mut self, parameter: FunctionParameter) -> FunctionParameter {
+        walk::function_parameter(self, parameter)
+    }
+
+    fn fold_block(&mut self, block: Block) -> Block {
+        walk::block(self, block)
+    }
+
+    fn fold_node(&mut self, node: AstNode) -> AstNode {
+        walk::node(self, node)
+    }
+
+    fn fold_statement(&mut self, statement: Statement) -> Statement {
+        walk::statement(self, statement)
+    }
+
+    fn fold_variable_binding(&mut self, binding: VariableBinding) -> VariableBinding {
+        walk::variable_binding(self, binding)
+    }
+
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        walk::expression(self, expression)
+    }
+
+    fn fold_if_expr(&mut self, if_expr: IfExpr) -> IfExpr {
+        walk::if_expr(self, if_expr)
+    }
+
+    fn fold_struct_expr(&mut self, struct_expr: StructExpr) -> StructExpr {
+        walk::struct_expr(self, struct_expr)
+    }
+
+    fn fold_struct_expr_member(&mut self, member: StructExprMember) -> StructExprMember {
+        walk::struct_expr_member(self, member)
+    }
+
+    fn fold_use(&mut self, usage: Use) -> Use {
+        walk::usage(self, usage)
+    }
+
+    fn fold_use_tree(&mut self, tree: UseTree) -> UseTree {
+        walk::use_tree(self, tree)
+    }
+
+    fn fold_const(&mut self, constant: Const) -> Const {
+        walk::constant(self, constant)
+    }
+
+    fn fold_static(&mut self, statik: Static) -> Static {
+        walk::statik(self, statik)
+    }
+
+    fn fold_type_alias(&mut self, alias: TypeAlias) -> TypeAlias {
+        walk::type_alias(self, alias)
+    }
+}
+
+pub mod walk {
+    use super::*;
+
+    macro_rules! list {
+        ($f:ident, $f_method:ident, $list:expr) => {
+            $list.into_iter().map(|item| $f.$f_method(item)).collect()
+        };
+    }
+
+    pub fn geode<F: Fold>(folder: &mut F, geode: Geode) -> Geode {
+        Geode { module: folder.fold_module(geode.module), span: geode.span }
+    }
+
+    pub fn module<F: Fold>(folder: &mut F, module: Module) -> Module {
+        Module {
+            attributes: list!(folder, fold_attribute, module.attributes),
+            visibility: module.visibility,
+            name: folder.fold_identifier(module.name),
+            items: list!(folder, fold_item, module.items),
+            inline: module.inline,
+            file: module.file,
+            span: module.span,
+        }
+    }
+
+    pub fn item<F: Fold>(folder: &mut F, item: Item) -> Item {
+        match item {
+            Item::Function(f) => Item::Function(folder.fold_function(f)),
+            Item::Module(m) => Item::Module(folder.fold_module(m)),
+            Item::Struct(s) => Item::Struct(folder.fold_struct(s)),
+            Item::Use(u) => Item::Use(folder.fold_use(u)),
+            Item::Const(c) => Item::Const(folder.fold_const(c)),
+            Item::Static(s) => Item::Static(folder.fold_static(s)),
+            Item::TypeAlias(t) => Item::TypeAlias(folder.fold_type_alias(t)),
+        }
+    }
+
+    pub fn attribute<F: Fold>(folder: &mut F, attribute: Attribute) -> Attribute {
+        let args = attribute.args.map(|args| {
+            args.into_iter()
+                .map(|arg| match arg {
+                    AttributeArg::Path(path) => AttributeArg::Path(folder.fold_path(path)),
+                    arg @ AttributeArg::Integer(..) => arg,
+                })
+                .collect()
+        });
+
+        Attribute { style: attribute.style, name: folder.fold_identifier(attribute.name), args, span: attribute.span }
+    }
+
+    pub fn node<F: Fold>(folder: &mut F, node: AstNode) -> AstNode {
+        match node {
+            AstNode::Expression(e) => AstNode::Expression(folder.fold_expression(e)),
+            AstNode::Item(i) => AstNode::Item(folder.fold_item(i)),
+            AstNode::Statement(s) => AstNode::Statement(folder.fold_statement(s)),
+        }
+    }
+
+    pub fn structure<F: Fold>(folder: &mut F, strukt: Struct) -> Struct {
+        Struct {
+            attributes: list!(folder, fold_attribute, strukt.attributes),
+            visibility: strukt.visibility,
+            name: folder.fold_identifier(strukt.name),
+            members: list!(folder, fold_struct_member, strukt.members),
+            span: strukt.span,
+        }
+    }
+
+    pub fn struct_member<F: Fold>(folder: &mut F, struct_member: StructMember) -> StructMember {
+        StructMember {
+            attributes: list!(folder, fold_attribute, struct_member.attributes),
+            visibility: struct_member.visibility,
+            name: folder.fold_identifier(struct_member.name),
+            ty: folder.fold_type(struct_member.ty),
+            span: struct_member.span,
+        }
+    }
+
+    pub fn path<F: Fold>(folder: &mut F, path: Path) -> Path {
+        Path { segments: list!(folder, fold_identifier, path.segments), span: path.span }
+    }
+
+    pub fn ty<F: Fold>(folder: &mut F, ty: Type) -> Type {
+        let kind = match ty.kind {
+            kind @ TypeKind::Integer | kind @ TypeKind::Bool | kind @ TypeKind::Never => kind,
+            TypeKind::Named(path) => TypeKind::Named(folder.fold_path(path)),
+            TypeKind::Box(ty) => TypeKind::Box(Box::new(folder.fold_type(*ty))),
+        };
+
+        Type { kind, span: ty.span }
+    }
+
+    pub fn function<F: Fold>(folder: &mut F, function: Function) -> Function {
+        Function {
+            attributes: list!(folder, fold_attribute, function.attributes),
+            visibility: function.visibility,
+            name: folder.fold_identifier(function.name),
+            parameters: list!(folder, fold_function_parameter, function.parameters),
+            return_ty: function.return_ty.map(|ty| folder.fold_type(ty)),
+            body: folder.fold_block(function.body),
+            span: function.span,
+        }
+    }
+
+    pub fn function_parameter<F: Fold>(folder: &mut F, parameter: FunctionParameter) -> FunctionParameter {
+        FunctionParameter {
+            name: folder.fold_identifier(parameter.name),
+            ty: folder.fold_type(parameter.ty),
+            span: parameter.span,
+        }
+    }
+
+    pub fn block<F: Fold>(folder: &mut F, block: Block) -> Block {
+        Block {
+            items: list!(folder, fold_item, block.items),
+            statements: list!(folder, fold_statement, block.statements),
+            return_expr: block.return_expr.map(|expr| folder.fold_expression(expr)),
+            span: block.span,
+        }
+    }
+
+    pub fn statement<F: Fold>(folder: &mut F, statement: Statement) -> Statement {
+        let kind = match statement.kind {
+            StatementKind::VariableBinding(binding) => {
+                StatementKind::VariableBinding(folder.fold_variable_binding(binding))
+            }
+            StatementKind::Expression(expr) => StatementKind::Expression(folder.fold_expression(expr)),
+        };
+
+        Statement { kind, span: statement.span }
+    }
+
+    pub fn variable_binding<F: Fold>(folder: &mut F, binding: VariableBinding) -> VariableBinding {
+        VariableBinding {
+            mutable: binding.mutable,
+            name: folder.fold_identifier(binding.name),
+            ty: binding.ty.map(|ty| folder.fold_type(ty)),
+            value: folder.fold_expression(binding.value),
+            span: binding.span,
+        }
+    }
+
+    pub fn expression<F: Fold>(folder: &mut F, expression: Expression) -> Expression {
+        let kind = match expression.kind {
+            ExpressionKind::Assignment(place, value) => ExpressionKind::Assignment(
+                Box::new(folder.fold_expression(*place)),
+                Box::new(folder.fold_expression(*value)),
+            ),
+            ExpressionKind::BinaryOperation(lhs, op, rhs) => ExpressionKind::BinaryOperation(
+                Box::new(folder.fold_expression(*lhs)),
+                op,
+                Box::new(folder.fold_expression(*rhs)),
+            ),
+            ExpressionKind::Block(block) => ExpressionKind::Block(Box::new(folder.fold_block(*block))),
+            ExpressionKind::FieldAccess(base, field) => {
+                ExpressionKind::FieldAccess(Box::new(folder.fold_expression(*base)), folder.fold_identifier(field))
+            }
+            ExpressionKind::FnCall(callee, args) => {
+                ExpressionKind::FnCall(Box::new(folder.fold_expression(*callee)), list!(folder, fold_expression, args))
+            }
+            ExpressionKind::If(if_expr) => ExpressionKind::If(Box::new(folder.fold_if_expr(*if_expr))),
+            ExpressionKind::Path(path) => ExpressionKind::Path(folder.fold_path(path)),
+            ExpressionKind::Struct(struct_expr) => {
+                ExpressionKind::Struct(Box::new(folder.fold_struct_expr(*struct_expr)))
+            }
+            ExpressionKind::Unary(op, expr) => ExpressionKind::Unary(op, Box::new(folder.fold_expression(*expr))),
+            kind @ ExpressionKind::Boolean(_) | kind @ ExpressionKind::Integer(_) | kind @ ExpressionKind::Unit => kind,
+        };
+
+        Expression { attributes: expression.attributes, kind, span: expression.span }
+    }
+
+    pub fn if_expr<F: Fold>(folder: &mut F, if_expr: IfExpr) -> IfExpr {
+        let ifs = if_expr
+            .ifs
+            .into_iter()
+            .map(|branch| If {
+                condition: folder.fold_expression(branch.condition),
+                body: folder.fold_block(branch.body),
+                span: branch.span,
+            })
+            .collect();
+
+        IfExpr { ifs, r#else: if_expr.r#else.map(|block| folder.fold_block(block)), span: if_expr.span }
+    }
+
+    pub fn struct_expr<F: Fold>(folder: &mut F, struct_expr: StructExpr) -> StructExpr {
+        StructExpr {
+            name: folder.fold_path(struct_expr.name),
+            members: list!(folder, fold_struct_expr_member, struct_expr.members),
+            base: struct_expr.base.map(|base| Box::new(folder.fold_expression(*base))),
+            span: struct_expr.span,
+        }
+    }
+
+    pub fn struct_expr_member<F: Fold>(folder: &mut F, member: StructExprMember) -> StructExprMember {
+        StructExprMember {
+            name: folder.fold_identifier(member.name),
+            expression: folder.fold_expression(member.expression),
+            span: member.span,
+        }
+    }
+
+    pub fn constant<F: Fold>(folder: &mut F, constant: Const) -> Const {
+        Const {
+            attributes: list!(folder, fold_attribute, constant.attributes),
+            visibility: constant.visibility,
+            name: folder.fold_identifier(constant.name),
+            ty: folder.fold_type(constant.ty),
+            value: folder.fold_expression(constant.value),
+            span: constant.span,
+        }
+    }
+
+    pub fn statik<F: Fold>(folder: &mut F, statik: Static) -> Static {
+        Static {
+            attributes: list!(folder, fold_attribute, statik.attributes),
+            visibility: statik.visibility,
+            mutable: statik.mutable,
+            name: folder.fold_identifier(statik.name),
+            ty: folder.fold_type(statik.ty),
+            value: folder.fold_expression(statik.value),
+            span: statik.span,
+        }
+    }
+
+    pub fn type_alias<F: Fold>(folder: &mut F, alias: TypeAlias) -> TypeAlias {
+        TypeAlias {
+            attributes: list!(folder, fold_attribute, alias.attributes),
+            visibility: alias.visibility,
+            name: folder.fold_identifier(alias.name),
+            ty: folder.fold_type(alias.ty),
+            span: alias.span,
+        }
+    }
+
+    pub fn usage<F: Fold>(folder: &mut F, usage: Use) -> Use {
+        Use {
+            attributes: list!(folder, fold_attribute, usage.attributes),
+            tree: folder.fold_use_tree(usage.tree),
+            span: usage.span,
+        }
+    }
+
+    pub fn use_tree<F: Fold>(folder: &mut F, tree: UseTree) -> UseTree {
+        let kind = match tree.kind {
+            UseTreeKind::Simple(rename) => UseTreeKind::Simple(rename.map(|rename| folder.fold_identifier(rename))),
+            UseTreeKind::Glob => UseTreeKind::Glob,
+            UseTreeKind::Nested(trees) => UseTreeKind::Nested(list!(folder, fold_use_tree, trees)),
+        };
+
+        UseTree { prefix: folder.fold_path(tree.prefix), kind, span: tree.span }
+    }
+}
@@ -1,7 +1,11 @@
+pub mod fold;
 pub mod visit;
+pub mod visit_mut;
 
-use codespan::Span;
+use codespan::{FileId, Span};
+pub use fold::Fold;
 pub use visit::Visitor;
+pub use visit_mut::VisitorMut;
 
 #[derive(Clone, Debug)]
 pub struct Geode {
@@ -11,8 +15,16 @@ pub struct Geode {
 
 #[derive(Clone, Debug)]
 pub struct Module {
+    pub attributes: Vec<Attribute>,
+    pub visibility: Visibility,
     pub name: Identifier,
     pub items: Vec<Item>,
+    /// Whether the module was declared as `module foo { ... }` rather than
+    /// `module foo;`
+    pub inline: bool,
+    /// The file the module's items were parsed from, if they were loaded from
+    /// a file other than the one containing the module declaration
+    pub file: Option<FileId>,
     pub span: Span,
 }
 
@@ -29,6 +41,9 @@ pub enum Item {
     Struct(Struct),
     Module(Module),
     Use(Use),
+    Const(Const),
+    Static(Static),
+    TypeAlias(TypeAlias),
 }
 
 impl Item {
@@ -38,12 +53,62 @@ impl Item {
             Item::Struct(s) => s.span,
             Item::Module(m) => m.span,
             Item::Use(u) => u.span,
+            Item::Const(c) => c.span,
+            Item::Static(s) => s.span,
+            Item::TypeAlias(t) => t.span,
         }
     }
+
+    pub fn attributes(&self) -> &[Attribute] {
+        match self {
+            Item::Function(f) => &f.attributes,
+            Item::Struct(s) => &s.attributes,
+            Item::Module(m) => &m.attributes,
+            Item::Use(u) => &u.attributes,
+            Item::Const(c) => &c.attributes,
+            Item::Static(s) => &s.attributes,
+            Item::TypeAlias(t) => &t.attributes,
+        }
+    }
+}
+
+/// `const NAME: Type = value;`
+#[derive(Clone, Debug)]
+pub struct Const {
+    pub attributes: Vec<Attribute>,
+    pub visibility: Visibility,
+    pub name: Identifier,
+    pub ty: Type,
+    pub value: Expression,
+    pub span: Span,
+}
+
+/// `type Name = Type;`
+#[derive(Clone, Debug)]
+pub struct TypeAlias {
+    pub attributes: Vec<Attribute>,
+    pub visibility: Visibility,
+    pub name: Identifier,
+    pub ty: Type,
+    pub span: Span,
+}
+
+/// `static mut NAME: Type = value;`
+#[derive(Clone, Debug)]
+pub struct Static {
+    pub attributes: Vec<Attribute>,
+    pub visibility: Visibility,
+    pub mutable: bool,
+    pub name: Identifier,
+    pub ty: Type,
+    pub value: Expression,
+    pub span: Span,
 }
 
 #[derive(Clone, Debug)]
 pub struct Function {
+    pub attributes: Vec<Attribute>,
+    pub visibility: Visibility,
     pub name: Identifier,
     pub parameters: Vec<FunctionParameter>,
     pub return_ty: Option<Type>,
@@ -68,6 +133,8 @@ pub struct Block {
 
 #[derive(Clone, Debug)]
 pub struct Struct {
+    pub attributes: Vec<Attribute>,
+    pub visibility: Visibility,
     pub name: Identifier,
     pub members: Vec<StructMember>,
     pub span: Span,
@@ -75,17 +142,99 @@ pub struct Struct {
 
 #[derive(Clone, Debug)]
 pub struct StructMember {
+    pub attributes: Vec<Attribute>,
+    pub visibility: Visibility,
     pub name: Identifier,
     pub ty: Type,
     pub span: Span,
 }
 
+#[derive(Clone, Copy, Debug)]
+pub struct Visibility {
+    pub kind: VisibilityKind,
+    pub span: Span,
+}
+
+impl Visibility {
+    /// The implicit visibility of an item without a `pub` qualifier, with an
+    /// empty span located at `start`
+    pub fn private(start: Span) -> Self {
+        Self { kind: VisibilityKind::Private, span: Span::new(start.start(), start.start()) }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VisibilityKind {
+    /// `pub`
+    Public,
+    /// `pub(geode)`
+    Geode,
+    /// No visibility qualifier
+    Private,
+}
+
+impl std::fmt::Display for VisibilityKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VisibilityKind::Public => write!(f, "pub"),
+            VisibilityKind::Geode => write!(f, "pub(geode)"),
+            VisibilityKind::Private => Ok(()),
+        }
+    }
+}
+
+/// `#[name]` or `#[name(args)]`, or `#![name]` or `#![name(args)]` for
+/// attributes applying to the enclosing module
+#[derive(Clone, Debug)]
+pub struct Attribute {
+    pub style: AttributeStyle,
+    pub name: Identifier,
+    /// `None` for `#[name]`, as opposed to `#[name()]`
+    pub args: Option<Vec<AttributeArg>>,
+    pub span: Span,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributeStyle {
+    /// `#[...]`
+    Outer,
+    /// `#![...]`
+    Inner,
+}
+
+#[derive(Clone, Debug)]
+pub enum AttributeArg {
+    Path(Path),
+    Integer(i128, Span),
+}
+
 #[derive(Clone, Debug)]
 pub struct Use {
-    pub path: Path,
+    pub attributes: Vec<Attribute>,
+    pub tree: UseTree,
+    pub span: Span,
+}
+
+/// An import tree, e.g. the `a::b::{c, d as e}` in `use a::b::{c, d as e};`
+#[derive(Clone, Debug)]
+pub struct UseTree {
+    /// The path leading up to the tree, which may be empty in the case of
+    /// `use {a, b};` or `use *;`
+    pub prefix: Path,
+    pub kind: UseTreeKind,
     pub span: Span,
 }
 
+#[derive(Clone, Debug)]
+pub enum UseTreeKind {
+    /// `a::b` or `a::b as c`
+    Simple(Option<Identifier>),
+    /// `a::b::*`
+    Glob,
+    /// `a::b::{c, d}`
+    Nested(Vec<UseTree>),
+}
+
 #[derive(Clone, Debug)]
 pub struct Statement {
     pub kind: StatementKind,
@@ -109,6 +258,9 @@ pub struct VariableBinding {
 
 #[derive(Debug, Clone)]
 pub struct Expression {
+    /// Outer attributes, which apply to the expression following them along
+    /// with any field accesses and calls on it, e.g. `#[allow(dead_code)] f()`
+    pub attributes: Vec<Attribute>,
     pub kind: ExpressionKind,
     pub span: Span,
 }
@@ -147,6 +299,7 @@ pub struct If {
 pub struct StructExpr {
     pub name: Path,
     pub members: Vec<StructExprMember>,
+    pub base: Option<Box<Expression>>,
     pub span: Span,
 }
 
@@ -181,24 +334,15 @@ pub enum BinOp {
 
 impl BinOp {
     pub fn is_comparison_op(self) -> bool {
-        match self {
-            BinOp::Equal => true,
-            _ => false,
-        }
+        matches!(self, BinOp::Equal)
     }
 
     pub fn is_arith_op(self) -> bool {
-        match self {
-            BinOp::Add | BinOp::Subtract | BinOp::Multiply | BinOp::Divide => true,
-            _ => false,
-        }
+        matches!(self, BinOp::Add | BinOp::Subtract | BinOp::Multiply | BinOp::Divide)
     }
 
     pub fn is_logic_op(self) -> bool {
-        match self {
-            BinOp::LogicalAnd => true,
-            _ => false,
-        }
+        matches!(self, BinOp::LogicalAnd)
     }
 }
 
@@ -218,12 +362,18 @@ impl std::fmt::Display for BinOp {
 #[derive(Debug, Clone, Copy)]
 pub enum UnaryOp {
     Minus,
+    /// Moves a value onto the heap, `box x`
+    Box,
+    /// Reads the value behind a box, `*x`
+    Deref,
 }
 
 impl std::fmt::Display for UnaryOp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             UnaryOp::Minus => write!(f, "-"),
+            UnaryOp::Box => write!(f, "box "),
+            UnaryOp::Deref => write!(f, "*"),
         }
     }
 }
@@ -239,6 +389,9 @@ pub enum TypeKind {
     Bool,
     Integer,
     Named(Path),
+    Never,
+    /// A value on the heap, `Box<T>`
+    Box(Box<Type>),
 }
 
 #[derive(Clone, Debug)]
@@ -1,6 +1,10 @@
 use crate::*;
 
 pub trait Visitor: Sized {
+    fn visit_geode(&mut self, geode: &Geode) {
+        walk::geode(self, geode);
+    }
+
     fn visit_module(&mut self, module: &Module) {
         walk::module(self, module);
     }
@@ -9,6 +13,10 @@ pub trait Visitor: Sized {
         walk::item(self, item);
     }
 
+    fn visit_attribute(&mut self, attribute: &Attribute) {
+        walk::attribute(self, attribute);
+    }
+
     fn visit_function(&mut self, function: &Function) {
         walk::function(self, function);
     }
@@ -33,28 +41,60 @@ pub trait Visitor: Sized {
         walk::ty(self, ty);
     }
 
-    fn visit_function_parameter(&mut self, _: &FunctionParameter) {
-        todo!()
+    fn visit_function_parameter(&mut self, parameter: &FunctionParameter) {
+        walk::function_parameter(self, parameter);
     }
 
-    fn visit_block(&mut self, _: &Block) {
-        todo!()
+    fn visit_block(&mut self, block: &Block) {
+        walk::block(self, block);
     }
 
     fn visit_node(&mut self, node: &AstNode) {
         walk::node(self, node)
     }
 
-    fn visit_statement(&mut self, _: &Statement) {
-        todo!()
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk::statement(self, statement);
+    }
+
+    fn visit_variable_binding(&mut self, binding: &VariableBinding) {
+        walk::variable_binding(self, binding);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk::expression(self, expression);
+    }
+
+    fn visit_if_expr(&mut self, if_expr: &IfExpr) {
+        walk::if_expr(self, if_expr);
+    }
+
+    fn visit_struct_expr(&mut self, struct_expr: &StructExpr) {
+        walk::struct_expr(self, struct_expr);
+    }
+
+    fn visit_struct_expr_member(&mut self, member: &StructExprMember) {
+        walk::struct_expr_member(self, member);
+    }
+
+    fn visit_use(&mut self, usage: &Use) {
+        walk::usage(self, usage);
+    }
+
+    fn visit_use_tree(&mut self, tree: &UseTree) {
+        walk::use_tree(self, tree);
+    }
+
+    fn visit_const(&mut self, constant: &Const) {
+        walk::constant(self, constant);
     }
 
-    fn visit_expression(&mut self, _: &Expression) {
-        todo!()
+    fn visit_static(&mut self, statik: &Static) {
+        walk::statik(self, statik);
     }
 
-    fn visit_use(&mut self, _: &Use) {
-        todo!()
+    fn visit_type_alias(&mut self, alias: &TypeAlias) {
+        walk::type_alias(self, alias);
     }
 }
 
@@ -73,7 +113,13 @@ pub mod walk {
 
     pub use _list as list;
 
+    pub fn geode<V: Visitor>(visitor: &mut V, geode: &Geode) {
+        visitor.visit_module(&geode.module);
+    }
+
     pub fn module<V: Visitor>(visitor: &mut V, module: &Module) {
+        list!(visitor, visit_attribute, &module.attributes);
+        visitor.visit_identifier(&module.name);
         list!(visitor, visit_item, &module.items);
     }
 
@@ -83,6 +129,20 @@ pub mod walk {
             Item::Module(m) => visitor.visit_module(m),
             Item::Struct(s) => visitor.visit_struct(s),
             Item::Use(u) => visitor.visit_use(u),
+            Item::Const(c) => visitor.visit_const(c),
+            Item::Static(s) => visitor.visit_static(s),
+            Item::TypeAlias(t) => visitor.visit_type_alias(t),
+        }
+    }
+
+    pub fn attribute<V: Visitor>(visitor: &mut V, attribute: &Attribute) {
+        visitor.visit_identifier(&attribute.name);
+
+        for arg in attribute.args.iter().flatten() {
+            match arg {
+                AttributeArg::Path(path) => visitor.visit_path(path),
+                AttributeArg::Integer(..) => {}
+            }
         }
     }
 
@@ -95,10 +155,13 @@ pub mod walk {
     }
 
     pub fn structure<V: Visitor>(visitor: &mut V, strukt: &Struct) {
+        list!(visitor, visit_attribute, &strukt.attributes);
+        visitor.visit_identifier(&strukt.name);
         list!(visitor, visit_struct_member, &strukt.members);
     }
 
     pub fn struct_member<V: Visitor>(visitor: &mut V, struct_member: &StructMember) {
+        list!(visitor, visit_attribute, &struct_member.attributes);
         visitor.visit_identifier(&struct_member.name);
         visitor.visit_type(&struct_member.ty);
     }
@@ -109,12 +172,15 @@ pub mod walk {
 
     pub fn ty<V: Visitor>(visitor: &mut V, ty: &Type) {
         match &ty.kind {
-            TypeKind::Integer | TypeKind::Bool => todo!("hmm"),
+            TypeKind::Integer | TypeKind::Bool | TypeKind::Never => {}
             TypeKind::Named(path) => visitor.visit_path(path),
+            TypeKind::Box(ty) => visitor.visit_type(ty),
         }
     }
 
     pub fn function<V: Visitor>(visitor: &mut V, function: &Function) {
+        list!(visitor, visit_attribute, &function.attributes);
+        visitor.visit_identifier(&function.name);
         list!(visitor, visit_function_parameter, &function.parameters);
         if let Some(ty) = &function.return_ty {
             visitor.visit_type(ty);
@@ -122,7 +188,118 @@ pub mod walk {
         visitor.visit_block(&function.body);
     }
 
+    pub fn function_parameter<V: Visitor>(visitor: &mut V, parameter: &FunctionParameter) {
+        visitor.visit_identifier(&parameter.name);
+        visitor.visit_type(&parameter.ty);
+    }
+
+    pub fn block<V: Visitor>(visitor: &mut V, block: &Block) {
+        list!(visitor, visit_item, &block.items);
+        list!(visitor, visit_statement, &block.statements);
+        if let Some(expr) = &block.return_expr {
+            visitor.visit_expression(expr);
+        }
+    }
+
+    pub fn statement<V: Visitor>(visitor: &mut V, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::VariableBinding(binding) => visitor.visit_variable_binding(binding),
+            StatementKind::Expression(expr) => visitor.visit_expression(expr),
+        }
+    }
+
+    pub fn variable_binding<V: Visitor>(visitor: &mut V, binding: &VariableBinding) {
+        visitor.visit_identifier(&binding.name);
+        if let Some(ty) = &binding.ty {
+            visitor.visit_type(ty);
+        }
+        visitor.visit_expression(&binding.value);
+    }
+
+    pub fn expression<V: Visitor>(visitor: &mut V, expression: &Expression) {
+        match &expression.kind {
+            ExpressionKind::Assignment(place, value) => {
+                visitor.visit_expression(place);
+                visitor.visit_expression(value);
+            }
+            ExpressionKind::BinaryOperation(lhs, _, rhs) => {
+                visitor.visit_expression(lhs);
+                visitor.visit_expression(rhs);
+            }
+            ExpressionKind::Block(block) => visitor.visit_block(block),
+            ExpressionKind::FieldAccess(base, field) => {
+                visitor.visit_expression(base);
+                visitor.visit_identifier(field);
+            }
+            ExpressionKind::FnCall(callee, args) => {
+                visitor.visit_expression(callee);
+                list!(visitor, visit_expression, args);
+            }
+            ExpressionKind::If(if_expr) => visitor.visit_if_expr(if_expr),
+            ExpressionKind::Path(path) => visitor.visit_path(path),
+            ExpressionKind::Struct(struct_expr) => visitor.visit_struct_expr(struct_expr),
+            ExpressionKind::Unary(_, expr) => visitor.visit_expression(expr),
+            ExpressionKind::Boolean(_) | ExpressionKind::Integer(_) | ExpressionKind::Unit => {}
+        }
+    }
+
+    pub fn if_expr<V: Visitor>(visitor: &mut V, if_expr: &IfExpr) {
+        for branch in &if_expr.ifs {
+            visitor.visit_expression(&branch.condition);
+            visitor.visit_block(&branch.body);
+        }
+
+        if let Some(block) = &if_expr.r#else {
+            visitor.visit_block(block);
+        }
+    }
+
+    pub fn struct_expr<V: Visitor>(visitor: &mut V, struct_expr: &StructExpr) {
+        visitor.visit_path(&struct_expr.name);
+        list!(visitor, visit_struct_expr_member, &struct_expr.members);
+
+        if let Some(base) = &struct_expr.base {
+            visitor.visit_expression(base);
+        }
+    }
+
+    pub fn struct_expr_member<V: Visitor>(visitor: &mut V, member: &StructExprMember) {
+        visitor.visit_identifier(&member.name);
+        visitor.visit_expression(&member.expression);
+    }
+
+    pub fn constant<V: Visitor>(visitor: &mut V, constant: &Const) {
+        list!(visitor, visit_attribute, &constant.attributes);
+        visitor.visit_identifier(&constant.name);
+        visitor.visit_type(&constant.ty);
+        visitor.visit_expression(&constant.value);
+    }
+
+    pub fn statik<V: Visitor>(visitor: &mut V, statik: &Static) {
+        list!(visitor, visit_attribute, &statik.attributes);
+        visitor.visit_identifier(&statik.name);
+        visitor.visit_type(&statik.ty);
+        visitor.visit_expression(&statik.value);
+    }
+
+    pub fn type_alias<V: Visitor>(visitor: &mut V, alias: &TypeAlias) {
+        list!(visitor, visit_attribute, &alias.attributes);
+        visitor.visit_identifier(&alias.name);
+        visitor.visit_type(&alias.ty);
+    }
+
     pub fn usage<V: Visitor>(visitor: &mut V, usage: &Use) {
-        visitor.visit_path(&usage.path);
+        list!(visitor, visit_attribute, &usage.attributes);
+        visitor.visit_use_tree(&usage.tree);
+    }
+
+    pub fn use_tree<V: Visitor>(visitor: &mut V, tree: &UseTree) {
+        visitor.visit_path(&tree.prefix);
+
+        match &tree.kind {
+            UseTreeKind::Simple(Some(rename)) => visitor.visit_identifier(rename),
+            UseTreeKind::Simple(None) | UseTreeKind::Glob => {}
+            UseTreeKind::Nested(trees) => list!(visitor, visit_use_tree, trees),
+        }
     }
 }
@@ -0,0 +1,297 @@
+use crate::*;
+
+/// Like [`Visitor`], but with mutable access to each node so that the tree can
+/// be rewritten in place
+pub trait VisitorMut: Sized {
+    fn visit_geode(&mut self, geode: &mut Geode) {
+        walk::geode(self, geode);
+    }
+
+    fn visit_module(&mut self, module: &mut Module) {
+        walk::module(self, module);
+    }
+
+    fn visit_item(&mut self, item: &mut Item) {
+        walk::item(self, item);
+    }
+
+    fn visit_attribute(&mut self, attribute: &mut Attribute) {
+        walk::attribute(self, attribute);
+    }
+
+    fn visit_function(&mut self, function: &mut Function) {
+        walk::function(self, function);
+    }
+
+    fn visit_identifier(&mut self, _: &mut Identifier) {
+        // Nothing else to do
+    }
+
+    fn visit_struct(&mut self, strukt: &mut Struct) {
+        walk::structure(self, strukt);
+    }
+
+    fn visit_struct_member(&mut self, struct_member: &mut StructMember) {
+        walk::struct_member(self, struct_member);
+    }
+
+    fn visit_path(&mut self, path: &mut Path) {
+        walk::path(self, path);
+    }
+
+    fn visit_type(&mut self, ty: &mut Type) {
+        walk::ty(self, ty);
+    }
+
+    fn visit_function_parameter(&mut self, parameter: &mut FunctionParameter) {
+        walk::function_parameter(self, parameter);
+    }
+
+    fn visit_block(&mut self, block: &mut Block) {
+        walk::block(self, block);
+    }
+
+    fn visit_node(&mut self, node: &mut AstNode) {
+        walk::node(self, node)
+    }
+
+    fn visit_statement(&mut self, statement: &mut Statement) {
+        walk::statement(self, statement);
+    }
+
+    fn visit_variable_binding(&mut self, binding: &mut VariableBinding) {
+        walk::variable_binding(self, binding);
+    }
+
+    fn visit_expression(&mut self, expression: &mut Expression) {
+        walk::expression(self, expression);
+    }
+
+    fn visit_if_expr(&mut self, if_expr: &mut IfExpr) {
+        walk::if_expr(self, if_expr);
+    }
+
+    fn visit_struct_expr(&mut self, struct_expr: &mut StructExpr) {
+        walk::struct_expr(self, struct_expr);
+    }
+
+    fn visit_struct_expr_member(&mut self, member: &mut StructExprMember) {
+        walk::struct_expr_member(self, member);
+    }
+
+    fn visit_use(&mut self, usage: &mut Use) {
+        walk::usage(self, usage);
+    }
+
+    fn visit_use_tree(&mut self, tree: &mut UseTree) {
+        walk::use_tree(self, tree);
+    }
+
+    fn visit_const(&mut self, constant: &mut Const) {
+        walk::constant(self, constant);
+    }
+
+    fn visit_static(&mut self, statik: &mut Static) {
+        walk::statik(self, statik);
+    }
+
+    fn visit_type_alias(&mut self, alias: &mut TypeAlias) {
+        walk::type_alias(self, alias);
+    }
+}
+
+pub mod walk {
+    use super::*;
+
+    use crate::visit::walk::list;
+
+    pub fn geode<V: VisitorMut>(visitor: &mut V, geode: &mut Geode) {
+        visitor.visit_module(&mut geode.module);
+    }
+
+    pub fn module<V: VisitorMut>(visitor: &mut V, module: &mut Module) {
+        list!(visitor, visit_attribute, &mut module.attributes);
+        visitor.visit_identifier(&mut module.name);
+        list!(visitor, visit_item, &mut module.items);
+    }
+
+    pub fn item<V: VisitorMut>(visitor: &mut V, item: &mut Item) {
+        match item {
+            Item::Function(f) => visitor.visit_function(f),
+            Item::Module(m) => visitor.visit_module(m),
+            Item::Struct(s) => visitor.visit_struct(s),
+            Item::Use(u) => visitor.visit_use(u),
+            Item::Const(c) => visitor.visit_const(c),
+            Item::Static(s) => visitor.visit_static(s),
+            Item::TypeAlias(t) => visitor.visit_type_alias(t),
+        }
+    }
+
+    pub fn attribute<V: VisitorMut>(visitor: &mut V, attribute: &mut Attribute) {
+        visitor.visit_identifier(&mut attribute.name);
+
+        for arg in attribute.args.iter_mut().flatten() {
+            match arg {
+                AttributeArg::Path(path) => visitor.visit_path(path),
+                AttributeArg::Integer(..) => {}
+            }
+        }
+    }
+
+    pub fn node<V: VisitorMut>(visitor: &mut V, node: &mut AstNode) {
+        match node {
+            AstNode::Expression(e) => visitor.visit_expression(e),
+            AstNode::Item(i) => visitor.visit_item(i),
+            AstNode::Statement(s) => visitor.visit_statement(s),
+        }
+    }
+
+    pub fn structure<V: VisitorMut>(visitor: &mut V, strukt: &mut Struct) {
+        list!(visitor, visit_attribute, &mut strukt.attributes);
+        visitor.visit_identifier(&mut strukt.name);
+        list!(visitor, visit_struct_member, &mut strukt.members);
+    }
+
+    pub fn struct_member<V: VisitorMut>(visitor: &mut V, struct_member: &mut StructMember) {
+        list!(visitor, visit_attribute, &mut struct_member.attributes);
+        visitor.visit_identifier(&mut struct_member.name);
+        visitor.visit_type(&mut struct_member.ty);
+    }
+
+    pub fn path<V: VisitorMut>(visitor: &mut V, path: &mut Path) {
+        list!(visitor, visit_identifier, &mut path.segments);
+    }
+
+    pub fn ty<V: VisitorMut>(visitor: &mut V, ty: &mut Type) {
+        match &mut ty.kind {
+            TypeKind::Integer | TypeKind::Bool | TypeKind::Never => {}
+            TypeKind::Named(path) => visitor.visit_path(path),
+            TypeKind::Box(ty) => visitor.visit_type(ty),
+        }
+    }
+
+    pub fn function<V: VisitorMut>(visitor: &mut V, function: &mut Function) {
+        list!(visitor, visit_attribute, &mut function.attributes);
+        visitor.visit_identifier(&mut function.name);
+        list!(visitor, visit_function_parameter, &mut function.parameters);
+        if let Some(ty) = &mut function.return_ty {
+            visitor.visit_type(ty);
+        }
+        visitor.visit_block(&mut function.body);
+    }
+
+    pub fn function_parameter<V: VisitorMut>(visitor: &mut V, parameter: &mut FunctionParameter) {
+        visitor.visit_identifier(&mut parameter.name);
+        visitor.visit_type(&mut parameter.ty);
+    }
+
+    pub fn block<V: VisitorMut>(visitor: &mut V, block: &mut Block) {
+        list!(visitor, visit_item, &mut block.items);
+        list!(visitor, visit_statement, &mut block.statements);
+        if let Some(expr) = &mut block.return_expr {
+            visitor.visit_expression(expr);
+        }
+    }
+
+    pub fn statement<V: VisitorMut>(visitor: &mut V, statement: &mut Statement) {
+        match &mut statement.kind {
+            StatementKind::VariableBinding(binding) => visitor.visit_variable_binding(binding),
+            StatementKind::Expression(expr) => visitor.visit_expression(expr),
+        }
+    }
+
+    pub fn variable_binding<V: VisitorMut>(visitor: &mut V, binding: &mut VariableBinding) {
+        visitor.visit_identifier(&mut binding.name);
+        if let Some(ty) = &mut binding.ty {
+            visitor.visit_type(ty);
+        }
+        visitor.visit_expression(&mut binding.value);
+    }
+
+    pub fn expression<V: VisitorMut>(visitor: &mut V, expression: &mut Expression) {
+        match &mut expression.kind {
+            ExpressionKind::Assignment(place, value) => {
+                visitor.visit_expression(place);
+                visitor.visit_expression(value);
+            }
+            ExpressionKind::BinaryOperation(lhs, _, rhs) => {
+                visitor.visit_expression(lhs);
+                visitor.visit_expression(rhs);
+            }
+            ExpressionKind::Block(block) => visitor.visit_block(block),
+            ExpressionKind::FieldAccess(base, field) => {
+                visitor.visit_expression(base);
+                visitor.visit_identifier(field);
+            }
+            ExpressionKind::FnCall(callee, args) => {
+                visitor.visit_expression(callee);
+                list!(visitor, visit_expression, args);
+            }
+            ExpressionKind::If(if_expr) => visitor.visit_if_expr(if_expr),
+            ExpressionKind::Path(path) => visitor.visit_path(path),
+            ExpressionKind::Struct(struct_expr) => visitor.visit_struct_expr(struct_expr),
+            ExpressionKind::Unary(_, expr) => visitor.visit_expression(expr),
+            ExpressionKind::Boolean(_) | ExpressionKind::Integer(_) | ExpressionKind::Unit => {}
+        }
+    }
+
+    pub fn if_expr<V: VisitorMut>(visitor: &mut V, if_expr: &mut IfExpr) {
+        for branch in &mut if_expr.ifs {
+            visitor.visit_expression(&mut branch.condition);
+            visitor.visit_block(&mut branch.body);
+        }
+
+        if let Some(block) = &mut if_expr.r#else {
+            visitor.visit_block(block);
+        }
+    }
+
+    pub fn struct_expr<V: VisitorMut>(visitor: &mut V, struct_expr: &mut StructExpr) {
+        visitor.visit_path(&mut struct_expr.name);
+        list!(visitor, visit_struct_expr_member, &mut struct_expr.members);
+
+        if let Some(base) = &mut struct_expr.base {
+            visitor.visit_expression(base);
+        }
+    }
+
+    pub fn struct_expr_member<V: VisitorMut>(visitor: &mut V, member: &mut StructExprMember) {
+        visitor.visit_identifier(&mut member.name);
+        visitor.visit_expression(&mut member.expression);
+    }
+
+    pub fn constant<V: VisitorMut>(visitor: &mut V, constant: &mut Const) {
+        list!(visitor, visit_attribute, &mut constant.attributes);
+        visitor.visit_identifier(&mut constant.name);
+        visitor.visit_type(&mut constant.ty);
+        visitor.visit_expression(&mut constant.value);
+    }
+
+    pub fn statik<V: VisitorMut>(visitor: &mut V, statik: &mut Static) {
+        list!(visitor, visit_attribute, &mut statik.attributes);
+        visitor.visit_identifier(&mut statik.name);
+        visitor.visit_type(&mut statik.ty);
+        visitor.visit_expression(&mut statik.value);
+    }
+
+    pub fn type_alias<V: VisitorMut>(visitor: &mut V, alias: &mut TypeAlias) {
+        list!(visitor, visit_attribute, &mut alias.attributes);
+        visitor.visit_identifier(&mut alias.name);
+        visitor.visit_type(&mut alias.ty);
+    }
+
+    pub fn usage<V: VisitorMut>(visitor: &mut V, usage: &mut Use) {
+        list!(visitor, visit_attribute, &mut usage.attributes);
+        visitor.visit_use_tree(&mut usage.tree);
+    }
+
+    pub fn use_tree<V: VisitorMut>(visitor: &mut V, tree: &mut UseTree) {
+        visitor.visit_path(&mut tree.prefix);
+
+        match &mut tree.kind {
+            UseTreeKind::Simple(Some(rename)) => visitor.visit_identifier(rename),
+            UseTreeKind::Simple(None) | UseTreeKind::Glob => {}
+            UseTreeKind::Nested(trees) => list!(visitor, visit_use_tree, trees),
+        }
+    }
+}
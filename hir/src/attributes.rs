@@ -0,0 +1,295 @@
+use crate::{
+    visit::{walk, Visitor},
+    *,
+};
+use std::collections::HashMap;
+
+/// The kinds of item an attribute can be placed on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributeTarget {
+    Module,
+    Function,
+    Struct,
+    StructMember,
+    Use,
+    Const,
+    Static,
+    TypeAlias,
+    Expression,
+}
+
+impl AttributeTarget {
+    pub fn of(item: &ItemKind) -> Self {
+        match item {
+            ItemKind::Module(_) => AttributeTarget::Module,
+            ItemKind::Function(_) => AttributeTarget::Function,
+            ItemKind::Struct(_) => AttributeTarget::Struct,
+            ItemKind::Use(_) => AttributeTarget::Use,
+            ItemKind::Const(_) => AttributeTarget::Const,
+            ItemKind::Static(_) => AttributeTarget::Static,
+            ItemKind::TypeAlias(_) => AttributeTarget::TypeAlias,
+        }
+    }
+}
+
+impl Display for AttributeTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AttributeTarget::Module => write!(f, "modules"),
+            AttributeTarget::Function => write!(f, "functions"),
+            AttributeTarget::Struct => write!(f, "structs"),
+            AttributeTarget::StructMember => write!(f, "struct fields"),
+            AttributeTarget::Use => write!(f, "imports"),
+            AttributeTarget::Const => write!(f, "constants"),
+            AttributeTarget::Static => write!(f, "statics"),
+            AttributeTarget::TypeAlias => write!(f, "type aliases"),
+            AttributeTarget::Expression => write!(f, "expressions"),
+        }
+    }
+}
+
+/// Whether an attribute takes a parenthesized argument list
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributeArgs {
+    /// `#[name]`
+    None,
+    /// `#[name(args)]`
+    Required,
+    /// Either `#[name]` or `#[name(args)]`
+    Optional,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AttributeSpec {
+    /// The targets the attribute is valid on, or every target if empty
+    pub targets: &'static [AttributeTarget],
+    pub args: AttributeArgs,
+}
+
+/// The set of attributes the compiler knows about. Unknown attributes are
+/// warned about rather than rejected so that they can be introduced without
+/// breaking existing code.
+#[derive(Clone, Debug)]
+pub struct AttributeRegistry {
+    known: HashMap<String, AttributeSpec>,
+}
+
+impl AttributeRegistry {
+    pub fn new() -> Self {
+        use AttributeTarget::*;
+
+        let mut registry = Self { known: HashMap::new() };
+        registry.register("test", AttributeSpec { targets: &[Function], args: AttributeArgs::None });
+        registry.register("inline", AttributeSpec { targets: &[Function], args: AttributeArgs::Optional });
+        registry.register("derive", AttributeSpec { targets: &[Struct], args: AttributeArgs::Required });
+
+        for lint_level in &["allow", "warn", "deny"] {
+            registry.register(lint_level, AttributeSpec { targets: &[], args: AttributeArgs::Required });
+        }
+
+        registry
+    }
+
+    pub fn register(&mut self, name: &str, spec: AttributeSpec) {
+        self.known.insert(name.to_string(), spec);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AttributeSpec> {
+        self.known.get(name)
+    }
+
+    /// Validates the attributes on `item` and any items nested within it.
+    /// `file` is the file the item was parsed from, which is overridden by
+    /// modules loaded from their own file.
+    pub fn validate(&self, item: &Item, file: FileId) -> Vec<AttributeDiagnostic> {
+        let mut validator = Validator { registry: self, file, diagnostics: Vec::new() };
+        validator.visit_item(item);
+
+        validator.diagnostics
+    }
+
+    /// Validates the attributes on the expressions within `statement`, and on
+    /// any items declared in them
+    pub fn validate_statement(&self, statement: &Statement, file: FileId) -> Vec<AttributeDiagnostic> {
+        let mut validator = Validator { registry: self, file, diagnostics: Vec::new() };
+        validator.visit_statement(statement);
+
+        validator.diagnostics
+    }
+
+    /// Validates the attributes on `expression` and the expressions within
+    /// it, and on any items declared in them
+    pub fn validate_expression(&self, expression: &Expression, file: FileId) -> Vec<AttributeDiagnostic> {
+        let mut validator = Validator { registry: self, file, diagnostics: Vec::new() };
+        validator.visit_expression(expression);
+
+        validator.diagnostics
+    }
+
+    fn validate_attributes(
+        &self,
+        attributes: &[Attribute],
+        target: AttributeTarget,
+        file: FileId,
+        diagnostics: &mut Vec<AttributeDiagnostic>,
+    ) {
+        for attribute in attributes {
+            let name = attribute.name.string();
+            let kind = match self.get(&name) {
+                None => Some(AttributeDiagnosticKind::Unknown(name)),
+                Some(spec) if !spec.targets.is_empty() && !spec.targets.contains(&target) => {
+                    Some(AttributeDiagnosticKind::InvalidTarget { name, target })
+                }
+                Some(spec) => match (spec.args, &attribute.args) {
+                    (AttributeArgs::None, Some(_)) => Some(AttributeDiagnosticKind::UnexpectedArgs(name)),
+                    (AttributeArgs::Required, None) => Some(AttributeDiagnosticKind::MissingArgs(name)),
+                    _ => None,
+                },
+            };
+
+            if let Some(kind) = kind {
+                diagnostics.push(AttributeDiagnostic { kind, file, span: attribute.span });
+            }
+        }
+    }
+}
+
+struct Validator<'a> {
+    registry: &'a AttributeRegistry,
+    file: FileId,
+    diagnostics: Vec<AttributeDiagnostic>,
+}
+
+impl Visitor for Validator<'_> {
+    fn visit_item(&mut self, item: &Item) {
+        let target = AttributeTarget::of(&item.kind);
+        self.registry.validate_attributes(item.attributes(), target, self.file, &mut self.diagnostics);
+
+        walk::item(self, item);
+    }
+
+    fn visit_module(&mut self, module: &Module) {
+        let parent = self.file;
+        self.file = module.file.unwrap_or(parent);
+        walk::module(self, module);
+        self.file = parent;
+    }
+
+    fn visit_struct_member(&mut self, member: &StructMember) {
+        let target = AttributeTarget::StructMember;
+        self.registry.validate_attributes(&member.attributes, target, self.file, &mut self.diagnostics);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        let target = AttributeTarget::Expression;
+        self.registry.validate_attributes(&expression.attributes, target, self.file, &mut self.diagnostics);
+
+        walk::expression(self, expression);
+    }
+}
+
+impl Default for AttributeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AttributeDiagnostic {
+    pub kind: AttributeDiagnosticKind,
+    pub file: FileId,
+    pub span: Span,
+}
+
+impl AttributeDiagnostic {
+    /// Unknown attributes are only warned about, everything else is an error
+    pub fn is_error(&self) -> bool {
+        !matches!(self.kind, AttributeDiagnosticKind::Unknown(_))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum AttributeDiagnosticKind {
+    Unknown(String),
+    InvalidTarget { name: String, target: AttributeTarget },
+    MissingArgs(String),
+    UnexpectedArgs(String),
+}
+
+impl Display for AttributeDiagnosticKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AttributeDiagnosticKind::Unknown(name) => write!(f, "Unknown attribute `{}`", name),
+            AttributeDiagnosticKind::InvalidTarget { name, target } => {
+                write!(f, "Attribute `{}` can't be used on {}", name, target)
+            }
+            AttributeDiagnosticKind::MissingArgs(name) => {
+                write!(f, "Attribute `{}` requires arguments, e.g. `#[{}(...)]`", name, name)
+            }
+            AttributeDiagnosticKind::UnexpectedArgs(name) => {
+                write!(f, "Attribute `{}` doesn't take arguments, e.g. `#[{}]`", name, name)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codespan::Files;
+
+    fn messages(diagnostics: Vec<AttributeDiagnostic>) -> Vec<String> {
+        diagnostics.into_iter().map(|diagnostic| diagnostic.kind.to_string()).collect()
+    }
+
+    fn names(attributes: &[Attribute]) -> Vec<String> {
+        attributes.iter().map(|attribute| attribute.name.string()).collect()
+    }
+
+    #[test]
+    fn expression_attributes_apply_to_postfix_expressions() {
+        let expression = Expression::convert(&parser::Parser::new("#[a] #[b] f().x + 1").expression().unwrap());
+        assert!(expression.attributes.is_empty());
+
+        match &expression.kind {
+            ExpressionKind::BinaryOperation(lhs, ..) => {
+                assert_eq!(names(&lhs.attributes), ["a", "b"]);
+                assert!(matches!(lhs.kind, ExpressionKind::FieldAccess(..)));
+            }
+            kind => panic!("expected a binary operation, got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn attributes_in_blocks_go_to_what_follows_them() {
+        let source = "{ #[test] fn g() {} #[a] g() }";
+        let block = match Expression::convert(&parser::Parser::new(source).expression().unwrap()).kind {
+            ExpressionKind::Block(block) => block,
+            kind => panic!("expected a block, got {:?}", kind),
+        };
+
+        assert_eq!(names(block.items[0].attributes()), ["test"]);
+        assert_eq!(names(&block.return_expr.attributes), ["a"]);
+    }
+
+    #[test]
+    fn expression_attributes_are_validated() {
+        let registry = AttributeRegistry::new();
+        let mut files = Files::new();
+
+        let source = "fn f() -> Int { let x = #[allow(unused)] 1; #[inline] #[frobnicate] { x } }";
+        let item = Item::convert(&parser::Parser::new(source).item().unwrap());
+        let found = messages(registry.validate(&item, files.add("item", source.to_string())));
+        assert_eq!(found, ["Attribute `inline` can't be used on expressions", "Unknown attribute `frobnicate`"]);
+
+        let source = "let y = #[derive(Eq)] 1;";
+        let statement = Statement::convert(&parser::Parser::new(source).statement().unwrap());
+        let found = messages(registry.validate_statement(&statement, files.add("statement", source.to_string())));
+        assert_eq!(found, ["Attribute `derive` can't be used on expressions"]);
+
+        let source = "#[test] f()";
+        let expression = Expression::convert(&parser::Parser::new(source).expression().unwrap());
+        let found = messages(registry.validate_expression(&expression, files.add("expression", source.to_string())));
+        assert_eq!(found, ["Attribute `test` can't be used on expressions"]);
+    }
+}
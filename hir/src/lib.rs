@@ -1,8 +1,13 @@
+pub mod attributes;
+pub mod resolve;
+pub mod snapshot;
 mod ty;
 pub mod visit;
+pub mod visit_mut;
 
-pub use ast::{BinOp, UnaryOp};
-use codespan::Span;
+pub use ast::{AttributeStyle, BinOp, UnaryOp, Visibility, VisibilityKind};
+use codespan::{FileId, Span};
+pub use resolve::{DefId, ExprId, LocalId, Res};
 use std::{
     cell::RefCell,
     fmt::{self, Display, Formatter},
@@ -16,16 +21,22 @@ std::thread_local! {
 
 #[derive(Clone, Debug)]
 pub struct Module {
+    pub attributes: Vec<Attribute>,
+    pub visibility: Visibility,
     pub name: Identifier,
     pub items: Vec<Item>,
+    pub file: Option<FileId>,
     pub span: Span,
 }
 
 impl Module {
     pub fn convert(module: &ast::Module) -> Self {
         Self {
+            attributes: module.attributes.iter().map(Attribute::convert).collect(),
+            visibility: module.visibility,
             name: Identifier::convert(&module.name),
             items: module.items.iter().map(Item::convert).collect(),
+            file: module.file,
             span: module.span,
         }
     }
@@ -34,12 +45,22 @@ impl Module {
 #[derive(Clone, Debug)]
 pub struct Item {
     pub kind: ItemKind,
+    /// Assigned during name resolution, `None` for `use` items
+    pub def: Option<DefId>,
     pub span: Span,
 }
 
 impl Item {
     pub fn convert(item: &ast::Item) -> Self {
-        Self { kind: ItemKind::convert(&item), span: item.span() }
+        Self { kind: ItemKind::convert(item), def: None, span: item.span() }
+    }
+
+    pub fn def_id(&self) -> DefId {
+        self.def.expect("items are assigned a `DefId` during name resolution")
+    }
+
+    pub fn attributes(&self) -> &[Attribute] {
+        self.kind.attributes()
     }
 }
 
@@ -49,6 +70,9 @@ pub enum ItemKind {
     Function(Function),
     Struct(Struct),
     Use(Use),
+    Const(Const),
+    Static(Static),
+    TypeAlias(TypeAlias),
 }
 
 impl ItemKind {
@@ -58,25 +82,249 @@ impl ItemKind {
             ast::Item::Module(f) => ItemKind::Module(Module::convert(f)),
             ast::Item::Struct(f) => ItemKind::Struct(Struct::convert(f)),
             ast::Item::Use(u) => ItemKind::Use(Use::convert(u)),
+            ast::Item::Const(c) => ItemKind::Const(Const::convert(c)),
+            ast::Item::Static(s) => ItemKind::Static(Static::convert(s)),
+            ast::Item::TypeAlias(t) => ItemKind::TypeAlias(TypeAlias::convert(t)),
+        }
+    }
+
+    pub fn attributes(&self) -> &[Attribute] {
+        match self {
+            ItemKind::Module(m) => &m.attributes,
+            ItemKind::Function(f) => &f.attributes,
+            ItemKind::Struct(s) => &s.attributes,
+            ItemKind::Use(u) => &u.attributes,
+            ItemKind::Const(c) => &c.attributes,
+            ItemKind::Static(s) => &s.attributes,
+            ItemKind::TypeAlias(t) => &t.attributes,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TypeAlias {
+    pub attributes: Vec<Attribute>,
+    pub visibility: Visibility,
+    pub name: Identifier,
+    pub ty: Type,
+}
+
+impl TypeAlias {
+    pub fn convert(alias: &ast::TypeAlias) -> Self {
+        Self {
+            attributes: alias.attributes.iter().map(Attribute::convert).collect(),
+            visibility: alias.visibility,
+            name: Identifier::convert(&alias.name),
+            ty: Type::convert(&alias.ty),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Const {
+    pub attributes: Vec<Attribute>,
+    pub visibility: Visibility,
+    pub name: Identifier,
+    pub ty: Type,
+    pub value: Expression,
+}
+
+impl Const {
+    pub fn convert(constant: &ast::Const) -> Self {
+        Self {
+            attributes: constant.attributes.iter().map(Attribute::convert).collect(),
+            visibility: constant.visibility,
+            name: Identifier::convert(&constant.name),
+            ty: Type::convert(&constant.ty),
+            value: Expression::convert(&constant.value),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Static {
+    pub attributes: Vec<Attribute>,
+    pub visibility: Visibility,
+    pub mutable: bool,
+    pub name: Identifier,
+    pub ty: Type,
+    pub value: Expression,
+}
+
+impl Static {
+    pub fn convert(statik: &ast::Static) -> Self {
+        Self {
+            attributes: statik.attributes.iter().map(Attribute::convert).collect(),
+            visibility: statik.visibility,
+            mutable: statik.mutable,
+            name: Identifier::convert(&statik.name),
+            ty: Type::convert(&statik.ty),
+            value: Expression::convert(&statik.value),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Attribute {
+    pub style: AttributeStyle,
+    pub name: Identifier,
+    pub args: Option<Vec<AttributeArg>>,
+    pub span: Span,
+}
+
+impl Attribute {
+    pub fn convert(attribute: &ast::Attribute) -> Self {
+        Self {
+            style: attribute.style,
+            name: Identifier::convert(&attribute.name),
+            args: attribute.args.as_ref().map(|args| args.iter().map(AttributeArg::convert).collect()),
+            span: attribute.span,
+        }
+    }
+}
+
+impl Display for Attribute {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.style {
+            AttributeStyle::Outer => write!(f, "#[{}", self.name)?,
+            AttributeStyle::Inner => write!(f, "#![{}", self.name)?,
+        }
+
+        if let Some(args) = &self.args {
+            write!(f, "(")?;
+            for (idx, arg) in args.iter().enumerate() {
+                match arg {
+                    AttributeArg::Path(path, _) => write!(f, "{}", path)?,
+                    AttributeArg::Integer(n, _) => write!(f, "{}", n)?,
+                }
+
+                if idx != args.len() - 1 {
+                    write!(f, ", ")?;
+                }
+            }
+            write!(f, ")")?;
+        }
+
+        write!(f, "]")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum AttributeArg {
+    Path(Path, Span),
+    Integer(i128, Span),
+}
+
+impl AttributeArg {
+    pub fn convert(arg: &ast::AttributeArg) -> Self {
+        match arg {
+            ast::AttributeArg::Path(path) => AttributeArg::Path(Path::convert(path), path.span),
+            ast::AttributeArg::Integer(n, span) => AttributeArg::Integer(*n, *span),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            AttributeArg::Path(_, span) => *span,
+            AttributeArg::Integer(_, span) => *span,
         }
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct Use {
-    pub path: Path,
+    pub attributes: Vec<Attribute>,
+    pub tree: UseTree,
     pub span: Span,
 }
 
 impl Use {
     pub fn convert(usage: &ast::Use) -> Self {
-        Self { path: Path::convert(&usage.path), span: usage.span }
+        Self {
+            attributes: usage.attributes.iter().map(Attribute::convert).collect(),
+            tree: UseTree::convert(&usage.tree),
+            span: usage.span,
+        }
     }
+
+    /// Flattens the use tree into the individual imports it describes
+    pub fn imports(&self) -> Vec<Import> {
+        let mut imports = Vec::new();
+        self.tree.flatten(&Path::new(), &mut imports);
+        imports
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct UseTree {
+    pub prefix: Path,
+    pub kind: UseTreeKind,
+    pub span: Span,
 }
 
-#[derive(Clone, Hash, PartialEq, Eq, Debug, Default)]
+impl UseTree {
+    pub fn convert(tree: &ast::UseTree) -> Self {
+        let kind = match &tree.kind {
+            ast::UseTreeKind::Simple(rename) => UseTreeKind::Simple(rename.as_ref().map(Identifier::convert)),
+            ast::UseTreeKind::Glob => UseTreeKind::Glob,
+            ast::UseTreeKind::Nested(trees) => UseTreeKind::Nested(trees.iter().map(UseTree::convert).collect()),
+        };
+
+        Self { prefix: Path::convert(&tree.prefix), kind, span: tree.span }
+    }
+
+    fn flatten(&self, parent: &Path, imports: &mut Vec<Import>) {
+        let mut path = parent.join(&self.prefix);
+
+        match &self.kind {
+            UseTreeKind::Simple(rename) => {
+                // `a::b::{self}` imports `a::b` itself
+                if path.segments.len() > 1 && path.last().string() == "self" {
+                    path.pop();
+                }
+
+                let name = rename.unwrap_or_else(|| path.last());
+                imports.push(Import { path, kind: ImportKind::Named(name), span: self.span });
+            }
+            UseTreeKind::Glob => imports.push(Import { path, kind: ImportKind::Glob, span: self.span }),
+            UseTreeKind::Nested(trees) => {
+                for tree in trees {
+                    tree.flatten(&path, imports);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum UseTreeKind {
+    Simple(Option<Identifier>),
+    Glob,
+    Nested(Vec<UseTree>),
+}
+
+/// A single import described by a use tree
+#[derive(Clone, Debug)]
+pub struct Import {
+    pub path: Path,
+    pub kind: ImportKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ImportKind {
+    /// Binds the imported path to the given name
+    Named(Identifier),
+    /// Imports everything under the path
+    Glob,
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct Path {
     pub segments: Vec<Identifier>,
+    /// What the path refers to, filled in during name resolution. Not
+    /// considered for equality, so paths can still be used as keys.
+    pub res: Option<Res>,
 }
 
 impl std::fmt::Display for Path {
@@ -101,7 +349,7 @@ impl Path {
     }
 
     pub fn from_identifier(ident: Identifier) -> Self {
-        Self { segments: vec![ident] }
+        Self { segments: vec![ident], res: None }
     }
 
     pub fn last(&self) -> Identifier {
@@ -112,11 +360,19 @@ impl Path {
         self.segments.first().cloned().unwrap()
     }
 
+    /// The span covering every segment of the path
+    pub fn span(&self) -> Span {
+        match (self.segments.first(), self.segments.last()) {
+            (Some(first), Some(last)) => first.span.merge(last.span),
+            _ => Span::default(),
+        }
+    }
+
     pub fn with_ident(&self, ident: Identifier) -> Self {
         let mut segments = self.segments.clone();
         segments.push(ident);
 
-        Self { segments }
+        Self { segments, res: None }
     }
 
     pub fn is_identifier(&self) -> Option<Identifier> {
@@ -131,7 +387,21 @@ impl Path {
     }
 
     pub fn convert(ast: &ast::Path) -> Self {
-        Self { segments: ast.segments.iter().map(|ident| Identifier::convert(ident)).collect() }
+        Self { segments: ast.segments.iter().map(Identifier::convert).collect(), res: None }
+    }
+
+    /// Resolves a leading `geode` segment relative to the root module `geode`
+    /// and a leading `self` or `super` segment relative to `scope`, returning
+    /// `None` if the path isn't rooted
+    pub fn absolute(&self, scope: &Path, geode: &Path) -> Option<Self> {
+        let rest = Self { segments: self.segments.iter().skip(1).copied().collect(), res: None };
+
+        match self.segments.first().map(|s| s.string()).as_deref() {
+            Some("geode") => Some(geode.join(&rest)),
+            Some("self") => Some(scope.join(&rest)),
+            Some("super") => Some(scope.join(self).canonicalize()),
+            _ => None,
+        }
     }
 
     pub fn canonicalize(&self) -> Self {
@@ -145,7 +415,17 @@ impl Path {
             }
         }
 
-        Self { segments }
+        Self { segments, res: None }
+    }
+
+    pub fn parent(&self) -> Self {
+        let mut parent = self.clone();
+        parent.pop();
+        parent
+    }
+
+    pub fn starts_with(&self, prefix: &Self) -> bool {
+        self.segments.starts_with(&prefix.segments)
     }
 
     pub fn join(&self, other: &Self) -> Self {
@@ -155,8 +435,27 @@ impl Path {
                 segs.extend_from_slice(&other.segments);
                 segs
             },
+            res: None,
         }
     }
+
+    pub fn resolution(&self) -> Res {
+        self.res.expect("paths are resolved before they're used")
+    }
+}
+
+impl PartialEq for Path {
+    fn eq(&self, other: &Self) -> bool {
+        self.segments == other.segments
+    }
+}
+
+impl Eq for Path {}
+
+impl std::hash::Hash for Path {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.segments.hash(state);
+    }
 }
 
 #[derive(Clone, Copy, Eq, Debug)]
@@ -230,6 +529,7 @@ impl StatementKind {
 
 #[derive(Clone, Debug)]
 pub struct Local {
+    pub id: Option<LocalId>,
     pub name: Identifier,
     pub mutable: bool,
     pub ty: Type,
@@ -245,22 +545,33 @@ impl Local {
         let value = Expression::convert(&vb.value);
         let span = vb.span;
 
-        Self { name, mutable, ty, value, span }
+        Self { id: None, name, mutable, ty, value, span }
+    }
+
+    pub fn local_id(&self) -> LocalId {
+        self.id.expect("locals are assigned a `LocalId` during name resolution")
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct Expression {
+    pub id: Option<ExprId>,
+    pub attributes: Vec<Attribute>,
     pub kind: ExpressionKind,
     pub span: Span,
 }
 
 impl Expression {
     pub fn convert(expr: &ast::Expression) -> Self {
+        let attributes = expr.attributes.iter().map(Attribute::convert).collect();
         let kind = ExpressionKind::convert(&expr.kind);
         let span = expr.span;
 
-        Self { kind, span }
+        Self { id: None, attributes, kind, span }
+    }
+
+    pub fn expr_id(&self) -> ExprId {
+        self.id.expect("expressions are assigned an `ExprId` during name resolution")
     }
 }
 
@@ -287,11 +598,11 @@ impl ExpressionKind {
                 ExpressionKind::Assignment(Box::new(Expression::convert(lhs)), Box::new(Expression::convert(rhs)))
             }
             ast::ExpressionKind::BinaryOperation(e1, op, e2) => ExpressionKind::BinaryOperation(
-                Box::new(Expression::convert(&e1)),
+                Box::new(Expression::convert(e1)),
                 *op,
-                Box::new(Expression::convert(&e2)),
+                Box::new(Expression::convert(e2)),
             ),
-            ast::ExpressionKind::Block(b) => ExpressionKind::Block(Box::new(Block::convert(&b))),
+            ast::ExpressionKind::Block(b) => ExpressionKind::Block(Box::new(Block::convert(b))),
             ast::ExpressionKind::Boolean(b) => ExpressionKind::Boolean(*b),
             ast::ExpressionKind::FieldAccess(e, ident) => {
                 ExpressionKind::FieldAccess(Box::new(Expression::convert(e)), Identifier::convert(ident))
@@ -301,11 +612,11 @@ impl ExpressionKind {
             ast::ExpressionKind::Struct(s) => ExpressionKind::Struct(StructExpr::convert(s)),
             ast::ExpressionKind::Unit => ExpressionKind::Unit,
             ast::ExpressionKind::FnCall(lhs, args) => ExpressionKind::FnCall(
-                Box::new(Expression::convert(&lhs)),
+                Box::new(Expression::convert(lhs)),
                 args.iter().map(Expression::convert).collect(),
             ),
-            ast::ExpressionKind::Unary(op, expr) => ExpressionKind::Unary(*op, Box::new(Expression::convert(&expr))),
-            ast::ExpressionKind::If(if_expr) => ExpressionKind::If(Box::new(IfExpr::convert(&if_expr))),
+            ast::ExpressionKind::Unary(op, expr) => ExpressionKind::Unary(*op, Box::new(Expression::convert(expr))),
+            ast::ExpressionKind::If(if_expr) => ExpressionKind::If(Box::new(IfExpr::convert(if_expr))),
         }
     }
 }
@@ -322,9 +633,15 @@ impl IfExpr {
         Self {
             ifs: if_expr.ifs.iter().map(If::convert).collect(),
             r#else: if_expr.r#else.as_ref().map(Block::convert).unwrap_or_else(|| Block {
+                scope: None,
                 items: Vec::new(),
                 statements: Vec::new(),
-                return_expr: Expression { kind: ExpressionKind::Unit, span: Span::new(0, 0) },
+                return_expr: Expression {
+                    id: None,
+                    attributes: Vec::new(),
+                    kind: ExpressionKind::Unit,
+                    span: Span::new(0, 0),
+                },
             }),
             span: if_expr.span,
         }
@@ -348,6 +665,8 @@ impl If {
 pub struct StructExpr {
     pub name: Path,
     pub members: Vec<StructExprMember>,
+    /// The struct to take the remaining fields from, as in `Point { x: 1, ..other }`
+    pub base: Option<Box<Expression>>,
     pub span: Span,
 }
 
@@ -355,9 +674,10 @@ impl StructExpr {
     pub fn convert(struct_expr: &ast::StructExpr) -> Self {
         let name = Path::convert(&struct_expr.name);
         let members = struct_expr.members.iter().map(StructExprMember::convert).collect();
+        let base = struct_expr.base.as_ref().map(|base| Box::new(Expression::convert(base)));
         let span = struct_expr.span;
 
-        Self { name, members, span }
+        Self { name, members, base, span }
     }
 }
 
@@ -380,6 +700,8 @@ impl StructExprMember {
 
 #[derive(Clone, Debug)]
 pub struct Block {
+    /// The scope introduced by the block, assigned during name resolution
+    pub scope: Option<DefId>,
     pub items: Vec<Item>,
     pub statements: Vec<Statement>,
     pub return_expr: Expression,
@@ -388,18 +710,27 @@ pub struct Block {
 impl Block {
     pub fn convert(block: &ast::Block) -> Self {
         Self {
+            scope: None,
             items: block.items.iter().map(Item::convert).collect(),
             statements: block.statements.iter().map(Statement::convert).collect(),
             return_expr: block.return_expr.as_ref().map(Expression::convert).unwrap_or_else(|| Expression {
+                id: None,
+                attributes: Vec::new(),
                 kind: ExpressionKind::Unit,
                 span: block.statements.last().map(|s| s.span).unwrap_or(block.span),
             }),
         }
     }
+
+    pub fn scope_id(&self) -> DefId {
+        self.scope.expect("blocks are assigned a `DefId` during name resolution")
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct Function {
+    pub attributes: Vec<Attribute>,
+    pub visibility: Visibility,
     pub name: Identifier,
     pub parameters: Vec<FunctionParameter>,
     pub return_type: Type,
@@ -409,6 +740,8 @@ pub struct Function {
 impl Function {
     pub fn convert(f: &ast::Function) -> Self {
         Self {
+            attributes: f.attributes.iter().map(Attribute::convert).collect(),
+            visibility: f.visibility,
             name: Identifier::convert(&f.name),
             parameters: f.parameters.iter().map(FunctionParameter::convert).collect(),
             return_type: f.return_ty.as_ref().map(Type::convert).unwrap_or_else(|| Type {
@@ -423,6 +756,7 @@ impl Function {
 
 #[derive(Clone, Debug)]
 pub struct FunctionParameter {
+    pub id: Option<LocalId>,
     pub name: Identifier,
     pub ty: Type,
     pub span: Span,
@@ -430,12 +764,18 @@ pub struct FunctionParameter {
 
 impl FunctionParameter {
     pub fn convert(fp: &ast::FunctionParameter) -> Self {
-        Self { name: Identifier::convert(&fp.name), ty: Type::convert(&fp.ty), span: fp.span }
+        Self { id: None, name: Identifier::convert(&fp.name), ty: Type::convert(&fp.ty), span: fp.span }
+    }
+
+    pub fn local_id(&self) -> LocalId {
+        self.id.expect("parameters are assigned a `LocalId` during name resolution")
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct Struct {
+    pub attributes: Vec<Attribute>,
+    pub visibility: Visibility,
     pub name: Identifier,
     pub members: Vec<StructMember>,
     pub span: Span,
@@ -444,6 +784,8 @@ pub struct Struct {
 impl Struct {
     pub fn convert(strukt: &ast::Struct) -> Self {
         Self {
+            attributes: strukt.attributes.iter().map(Attribute::convert).collect(),
+            visibility: strukt.visibility,
             name: Identifier::convert(&strukt.name),
             members: strukt.members.iter().map(StructMember::convert).collect(),
             span: strukt.span,
@@ -453,7 +795,13 @@ impl Struct {
 
 impl Display for Struct {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        writeln!(f, "struct {} {{", self.name)?;
+        for attribute in &self.attributes {
+            writeln!(f, "{}", attribute)?;
+        }
+        match self.visibility.kind {
+            VisibilityKind::Private => writeln!(f, "struct {} {{", self.name)?,
+            kind => writeln!(f, "{} struct {} {{", kind, self.name)?,
+        }
         for member in &self.members {
             writeln!(f, "    {}", member)?;
         }
@@ -463,6 +811,8 @@ impl Display for Struct {
 
 #[derive(Clone, Debug)]
 pub struct StructMember {
+    pub attributes: Vec<Attribute>,
+    pub visibility: Visibility,
     pub name: Identifier,
     pub ty: Type,
     pub span: Span,
@@ -470,12 +820,24 @@ pub struct StructMember {
 
 impl StructMember {
     pub fn convert(member: &ast::StructMember) -> Self {
-        Self { name: Identifier::convert(&member.name), ty: Type::convert(&member.ty), span: member.span }
+        Self {
+            attributes: member.attributes.iter().map(Attribute::convert).collect(),
+            visibility: member.visibility,
+            name: Identifier::convert(&member.name),
+            ty: Type::convert(&member.ty),
+            span: member.span,
+        }
     }
 }
 
 impl Display for StructMember {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}: {}", self.name, self.ty.kind)
+        for attribute in &self.attributes {
+            write!(f, "{} ", attribute)?;
+        }
+        match self.visibility.kind {
+            VisibilityKind::Private => write!(f, "{}: {}", self.name, self.ty.kind),
+            kind => write!(f, "{} {}: {}", kind, self.name, self.ty.kind),
+        }
     }
 }
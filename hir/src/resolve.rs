@@ -0,0 +1,1085 @@
+//! Name resolution, which assigns every item a [`DefId`], every local a
+//! [`LocalId`] and every expression an [`ExprId`] and records what each path
+//! refers to, so that later stages never have to look names up themselves.
+
+use crate::{
+    snapshot::{MapSnapshot, SnapshotMap, SnapshotSet, SnapshotVec, VecSnapshot},
+    visit::{self, Visitor},
+    visit_mut::{self, VisitorMut},
+    *,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Identifies an item, or the scope introduced by a block
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DefId(pub usize);
+
+/// Identifies a local binding or function parameter
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LocalId(pub usize);
+
+/// Identifies an expression, so that later stages can record information
+/// about it without changing the HIR
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ExprId(pub usize);
+
+/// What a path refers to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Res {
+    Def(DefId),
+    Local(LocalId),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DefKind {
+    Module,
+    Function,
+    Struct,
+    Const,
+    Static {
+        mutable: bool,
+    },
+    TypeAlias,
+    /// The unnamed scope of a block, which can contain items of its own
+    Block,
+}
+
+impl DefKind {
+    pub fn is_value(self) -> bool {
+        matches!(self, DefKind::Function | DefKind::Const | DefKind::Static { .. })
+    }
+
+    pub fn is_type(self) -> bool {
+        matches!(self, DefKind::Struct | DefKind::TypeAlias)
+    }
+}
+
+impl Display for DefKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DefKind::Module => write!(f, "module"),
+            DefKind::Function => write!(f, "function"),
+            DefKind::Struct => write!(f, "struct"),
+            DefKind::Const => write!(f, "constant"),
+            DefKind::Static { .. } => write!(f, "static"),
+            DefKind::TypeAlias => write!(f, "type alias"),
+            DefKind::Block => write!(f, "block"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Definition {
+    pub kind: DefKind,
+    /// The full path of the definition. Blocks are named by their position
+    /// within the enclosing scope, e.g. `f::{0}`, so they can't be referred
+    /// to by the user.
+    pub path: Path,
+    pub visibility: VisibilityKind,
+    /// The file the definition was written in
+    pub file: Option<FileId>,
+}
+
+impl Definition {
+    /// The name of the definition, which points at where it was defined
+    pub fn name(&self) -> Identifier {
+        self.path.last()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LocalInfo {
+    pub name: Identifier,
+    pub mutable: bool,
+    /// The file the local was bound in
+    pub file: Option<FileId>,
+}
+
+/// What was defined more than once
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateKind {
+    /// Two items with the same name in the same scope
+    Item,
+    /// Two members with the same name in a struct definition
+    Field,
+    /// Two parameters with the same name in a function signature
+    Parameter,
+    /// The same field given twice in a struct literal
+    StructExprField,
+    /// An item with the same name as an earlier import in the same scope
+    ItemAfterImport,
+    /// An import of a name that an item in the same scope already has
+    ImportAfterItem,
+}
+
+#[derive(Clone, Debug)]
+pub enum ResolveErrorKind {
+    ConflictingImport {
+        name: Identifier,
+        first: Path,
+        second: Path,
+    },
+    /// `first` and `second` point at the names of the two definitions, the
+    /// first of which is in `first_file`
+    Duplicate {
+        kind: DuplicateKind,
+        name: Identifier,
+        first_file: Option<FileId>,
+        first: Span,
+        second: Span,
+    },
+    NotAType(Path, DefKind),
+    NotAValue(Path, DefKind),
+    Private(Identifier),
+    UndeclaredDependency {
+        geode: Identifier,
+        dependency: Identifier,
+    },
+    UnknownIdentifier(Identifier),
+    UnknownImport(Path),
+    UnknownPath(Path),
+    UnknownType(Path),
+}
+
+impl Display for ResolveErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveErrorKind::ConflictingImport { name, first, second } => {
+                write!(f, "`{}` is imported more than once, as both `{}` and `{}`", name, first, second)
+            }
+            ResolveErrorKind::Duplicate { kind, name, .. } => match kind {
+                DuplicateKind::Item => write!(f, "`{}` is defined more than once in the same scope", name),
+                DuplicateKind::Field => write!(f, "Field `{}` is declared more than once", name),
+                DuplicateKind::Parameter => write!(f, "Parameter `{}` is declared more than once", name),
+                DuplicateKind::StructExprField => write!(f, "Field `{}` is specified more than once", name),
+                DuplicateKind::ItemAfterImport | DuplicateKind::ImportAfterItem => {
+                    write!(f, "`{}` is both imported and defined in the same scope", name)
+                }
+            },
+            ResolveErrorKind::NotAType(path, kind) => write!(f, "Expected a type, found {} `{}`", kind, path),
+            ResolveErrorKind::NotAValue(path, kind) => write!(f, "Expected a value, found {} `{}`", kind, path),
+            ResolveErrorKind::Private(ident) => write!(f, "`{}` is private", ident),
+            ResolveErrorKind::UndeclaredDependency { geode, dependency } => {
+                write!(f, "Geode `{}` doesn't declare a dependency on `{}`", geode, dependency)
+            }
+            ResolveErrorKind::UnknownIdentifier(ident) => write!(f, "Unknown identifier `{}`", ident),
+            ResolveErrorKind::UnknownImport(path) => write!(f, "Unknown import `{}`", path),
+            ResolveErrorKind::UnknownPath(path) => write!(f, "Unknown path `{}`", path),
+            ResolveErrorKind::UnknownType(path) => write!(f, "Unknown type: `{}`", path),
+        }
+    }
+}
+
+/// An error found during name resolution, along with where it was found
+#[derive(Clone, Debug)]
+pub struct ResolveError {
+    pub kind: ResolveErrorKind,
+    pub file: Option<FileId>,
+    pub span: Span,
+}
+
+impl ResolveError {
+    pub fn new(kind: ResolveErrorKind, file: Option<FileId>, span: Span) -> Self {
+        Self { kind, file, span }
+    }
+}
+
+impl Display for ResolveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ResolveError>;
+
+/// The imports of a single module or block
+#[derive(Clone, Debug, Default)]
+struct Imports {
+    named: HashMap<Identifier, Path>,
+    /// The file each named import was declared in, and the span of the name
+    /// it binds
+    named_at: HashMap<Identifier, (Option<FileId>, Span)>,
+    /// Paths imported with `use path::*;`
+    globs: Vec<Path>,
+}
+
+/// An import that a path was resolved through, identified by the module or
+/// block it was declared in
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum UsedImport {
+    Named(Path, Identifier),
+    Glob(Path, Path),
+}
+
+/// Keeps track of every definition seen so far, so that items defined by
+/// earlier inputs at the REPL stay visible to later ones
+#[derive(Clone, Debug, Default)]
+pub struct Resolver {
+    defs: SnapshotVec<Definition>,
+    paths: SnapshotMap<Path, DefId>,
+    /// Imports keyed by the path of the module or block they're declared in
+    imports: SnapshotMap<Path, Imports>,
+    /// The imports that some path has been resolved through
+    used_imports: SnapshotSet<UsedImport>,
+    /// Loaded geodes and the names of the geodes they depend on
+    geodes: HashMap<Identifier, Vec<Identifier>>,
+    locals: SnapshotVec<LocalInfo>,
+    /// Locals bound at the top level of the REPL, which stay in scope for
+    /// later inputs
+    top_level: SnapshotMap<Identifier, LocalId>,
+    block_count: usize,
+    expr_count: usize,
+}
+
+/// What a [`Resolver`] looked like when a snapshot of it was taken
+#[derive(Clone, Copy, Debug)]
+pub struct Snapshot {
+    defs: VecSnapshot,
+    paths: MapSnapshot,
+    imports: MapSnapshot,
+    used_imports: MapSnapshot,
+    locals: VecSnapshot,
+    top_level: MapSnapshot,
+    block_count: usize,
+    expr_count: usize,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn def(&self, id: DefId) -> &Definition {
+        &self.defs[id.0]
+    }
+
+    pub fn local(&self, id: LocalId) -> &LocalInfo {
+        &self.locals[id.0]
+    }
+
+    /// Looks up the definition with the full path `path`
+    pub fn lookup(&self, path: &Path) -> Option<DefId> {
+        self.paths.get(path).copied()
+    }
+
+    /// Returns the local bound to `name` at the top level of the REPL
+    pub fn top_level_local(&self, name: Identifier) -> Option<LocalId> {
+        self.top_level.get(&name).copied()
+    }
+
+    /// Resolves `item` and everything nested within it, defined at the root
+    /// of `file`
+    pub fn resolve_item(&mut self, item: &mut Item, file: FileId) -> Result<()> {
+        let mut collector = Collector::new(self, file);
+        collector.visit_item(item);
+        collector.finish()?;
+
+        self.collect_imports(file, |importer| importer.visit_item(item))?;
+
+        let mut resolver = PathResolver::new(self, file, Vec::new());
+        resolver.visit_item(item);
+        resolver.finish()
+    }
+
+    /// Resolves a statement entered at the top level, which may bind a local
+    /// that later inputs can refer to
+    pub fn resolve_statement(&mut self, statement: &mut Statement, file: FileId) -> Result<()> {
+        let mut collector = Collector::new(self, file);
+        collector.visit_statement(statement);
+        collector.finish()?;
+
+        self.collect_imports(file, |importer| importer.visit_statement(statement))?;
+
+        let top_level = HashMap::clone(&self.top_level);
+        let mut resolver = PathResolver::new(self, file, vec![top_level]);
+        resolver.visit_statement(statement);
+
+        let top_level = resolver.ribs.pop().unwrap();
+        resolver.finish()?;
+        for (name, id) in top_level {
+            if self.top_level.get(&name) != Some(&id) {
+                self.top_level.insert(name, id);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolves an expression entered at the top level
+    pub fn resolve_expression(&mut self, expression: &mut Expression, file: FileId) -> Result<()> {
+        let mut collector = Collector::new(self, file);
+        collector.visit_expression(expression);
+        collector.finish()?;
+
+        self.collect_imports(file, |importer| importer.visit_expression(expression))?;
+
+        let top_level = HashMap::clone(&self.top_level);
+        let mut resolver = PathResolver::new(self, file, vec![top_level]);
+        resolver.visit_expression(expression);
+        resolver.finish()
+    }
+
+    fn collect_imports(&mut self, file: FileId, f: impl FnOnce(&mut Importer<'_>)) -> Result<()> {
+        let mut importer = Importer { resolver: self, scope: Path::new(), file: Some(file), error: None };
+        f(&mut importer);
+
+        match importer.error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Starts recording the definitions, imports and locals added next, so
+    /// that they can be undone
+    pub fn snapshot(&mut self) -> Snapshot {
+        Snapshot {
+            defs: self.defs.snapshot(),
+            paths: self.paths.snapshot(),
+            imports: self.imports.snapshot(),
+            used_imports: self.used_imports.snapshot(),
+            locals: self.locals.snapshot(),
+            top_level: self.top_level.snapshot(),
+            block_count: self.block_count,
+            expr_count: self.expr_count,
+        }
+    }
+
+    /// Undoes everything resolved since `snapshot` was taken
+    pub fn rollback_to(&mut self, snapshot: Snapshot) {
+        self.defs.rollback_to(snapshot.defs);
+        self.paths.rollback_to(snapshot.paths);
+        self.imports.rollback_to(snapshot.imports);
+        self.used_imports.rollback_to(snapshot.used_imports);
+        self.locals.rollback_to(snapshot.locals);
+        self.top_level.rollback_to(snapshot.top_level);
+        self.block_count = snapshot.block_count;
+        self.expr_count = snapshot.expr_count;
+    }
+
+    /// Keeps everything resolved since `snapshot` was taken
+    pub fn commit(&mut self, snapshot: Snapshot) {
+        self.defs.commit(snapshot.defs);
+        self.paths.commit(snapshot.paths);
+        self.imports.commit(snapshot.imports);
+        self.used_imports.commit(snapshot.used_imports);
+        self.locals.commit(snapshot.locals);
+        self.top_level.commit(snapshot.top_level);
+    }
+
+    /// Registers the geode `name`, whose root module is the module of the same
+    /// name, and the geodes it's allowed to refer to
+    pub fn register_geode(&mut self, name: Identifier, dependencies: Vec<Identifier>) {
+        self.geodes.insert(name, dependencies);
+    }
+
+    /// Returns the path of the root module of the geode containing `path`.
+    /// Anything outside of a registered geode, such as items defined at the
+    /// REPL, belongs to the unnamed geode rooted at the empty path.
+    pub fn geode_of(&self, path: &Path) -> Path {
+        match path.segments.first() {
+            Some(first) if self.geodes.contains_key(first) => Path::from_identifier(*first),
+            _ => Path::new(),
+        }
+    }
+
+    /// Ensures every segment of the fully resolved `path` is visible from the
+    /// path `from`. Private items are only visible from within the module or
+    /// block they were declared in, `pub(geode)` items are only visible from
+    /// within their geode, and a geode can only refer to other geodes it
+    /// declared as dependencies.
+    pub fn check_visibility(&self, from: &Path, path: &Path) -> std::result::Result<(), ResolveErrorKind> {
+        let from_geode = self.geode_of(from);
+        let path_geode = self.geode_of(path);
+
+        if let (Some(&geode), Some(&dependency)) = (from_geode.segments.first(), path_geode.segments.first()) {
+            if geode != dependency && !self.geodes[&geode].contains(&dependency) {
+                return Err(ResolveErrorKind::UndeclaredDependency { geode, dependency });
+            }
+        }
+
+        let mut prefix = Path::new();
+
+        for &segment in &path.segments {
+            let parent = prefix.clone();
+            prefix = prefix.with_ident(segment);
+
+            match self.lookup(&prefix).map(|id| self.def(id).visibility) {
+                Some(VisibilityKind::Private) if !from.starts_with(&parent) => {
+                    return Err(ResolveErrorKind::Private(segment));
+                }
+                Some(VisibilityKind::Geode) if from_geode != path_geode => {
+                    return Err(ResolveErrorKind::Private(segment))
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolves `path` to the item it refers to from `scope`, trying in order
+    /// rooted paths, the items and then the named imports of enclosing blocks
+    /// and the enclosing module, the named imports of outer modules, and then
+    /// the glob imports of each of those scopes. The root module's items can
+    /// be named from anywhere, and come before its glob imports.
+    pub fn resolve_path(&self, scope: &Path, path: &Path) -> Option<DefId> {
+        self.resolve_path_through(scope, path).map(|(id, _)| id)
+    }
+
+    /// Whether any path has been resolved through `import`, which was
+    /// declared in `scope`
+    pub fn is_import_used(&self, scope: &Path, import: &Import) -> bool {
+        let used = match import.kind {
+            ImportKind::Named(name) => UsedImport::Named(scope.clone(), name),
+            ImportKind::Glob => UsedImport::Glob(scope.clone(), self.resolve_import_path(scope, &import.path)),
+        };
+
+        self.used_imports.contains(&used)
+    }
+
+    /// Resolves `path` like [`Resolver::resolve_path`], also returning the
+    /// import it was resolved through, if any
+    fn resolve_path_through(&self, scope: &Path, path: &Path) -> Option<(DefId, Option<UsedImport>)> {
+        let module = self.enclosing_module(scope);
+
+        if let Some(absolute) = path.absolute(&module, &self.geode_of(scope)) {
+            return Some((self.lookup(&absolute)?, None));
+        }
+
+        let rest = Path { segments: path.segments[1..].to_vec(), res: None };
+        let mut current = scope.clone();
+
+        loop {
+            let is_block = self.lookup(&current).map(|id| self.def(id).kind) == Some(DefKind::Block);
+            if is_block || current == module {
+                if let Some(id) = self.lookup(&current.join(path)) {
+                    return Some((id, None));
+                }
+            }
+
+            if let Some(base) = self.imports.get(&current).and_then(|imports| imports.named.get(&path.first())) {
+                let import = UsedImport::Named(current.clone(), path.first());
+                return Some((self.lookup(&base.join(&rest))?, Some(import)));
+            }
+
+            if current.pop().is_none() {
+                break;
+            }
+        }
+
+        let mut current = scope.clone();
+
+        loop {
+            if current.segments.is_empty() {
+                if let Some(id) = self.lookup(path) {
+                    return Some((id, None));
+                }
+            }
+
+            for glob in self.imports.get(&current).map(|imports| &imports.globs[..]).unwrap_or_default() {
+                if let Some(id) = self.lookup(&glob.join(path)) {
+                    return Some((id, Some(UsedImport::Glob(current.clone(), glob.clone()))));
+                }
+            }
+
+            if current.pop().is_none() {
+                break;
+            }
+        }
+
+        None
+    }
+
+    /// Returns the path of the innermost module containing `path`, which is
+    /// the root module if no module contains it
+    pub fn enclosing_module(&self, path: &Path) -> Path {
+        let mut module = path.clone();
+
+        while !module.segments.is_empty() && self.lookup(&module).map(|id| self.def(id).kind) != Some(DefKind::Module) {
+            module.pop();
+        }
+
+        module
+    }
+
+    /// The items defined directly within the module, block or other item at
+    /// `path`, other than blocks, which can't be named. Items that have since
+    /// been redefined are left out.
+    pub fn children<'a>(&'a self, path: &'a Path) -> impl Iterator<Item = DefId> + 'a {
+        let children = self.defs.iter().enumerate().filter(move |&(id, def)| {
+            def.kind != DefKind::Block
+                && def.path.segments.len() == path.segments.len() + 1
+                && def.path.starts_with(path)
+                && self.lookup(&def.path) == Some(DefId(id))
+        });
+
+        children.map(|(id, _)| DefId(id))
+    }
+
+    /// The items that can be referred to by a single identifier from `scope`,
+    /// looked for in the same order as [`Resolver::resolve_path`] does, so
+    /// names that are shadowed only refer to what they'd resolve to. Items
+    /// that aren't visible from `scope` are left out.
+    pub fn names_in_scope(&self, scope: &Path) -> Vec<(Identifier, DefId)> {
+        let module = self.enclosing_module(scope);
+        let mut names = Vec::new();
+        let mut seen = HashSet::new();
+        let mut add = |name: Identifier, id: DefId| {
+            if seen.insert(name) && self.check_visibility(scope, &self.def(id).path).is_ok() {
+                names.push((name, id));
+            }
+        };
+
+        let mut current = scope.clone();
+        loop {
+            let is_block = self.lookup(&current).map(|id| self.def(id).kind) == Some(DefKind::Block);
+            if is_block || current == module {
+                for id in self.children(&current) {
+                    add(self.def(id).name(), id);
+                }
+            }
+
+            for (&name, base) in self.imports.get(&current).map(|imports| &imports.named).into_iter().flatten() {
+                if let Some(id) = self.lookup(base) {
+                    add(name, id);
+                }
+            }
+
+            if current.pop().is_none() {
+                break;
+            }
+        }
+
+        let mut current = scope.clone();
+        loop {
+            if current.segments.is_empty() {
+                for id in self.children(&current) {
+                    add(self.def(id).name(), id);
+                }
+            }
+
+            for glob in self.imports.get(&current).map(|imports| &imports.globs[..]).unwrap_or_default() {
+                for id in self.children(glob) {
+                    add(self.def(id).name(), id);
+                }
+            }
+
+            if current.pop().is_none() {
+                break;
+            }
+        }
+
+        names
+    }
+
+    /// Resolves the path of an import from `scope`, which is either rooted,
+    /// relative to the enclosing module, or relative to the root module
+    fn resolve_import_path(&self, scope: &Path, path: &Path) -> Path {
+        let module = self.enclosing_module(scope);
+
+        if let Some(absolute) = path.absolute(&module, &self.geode_of(scope)) {
+            return absolute;
+        }
+
+        let relative = module.join(path);
+        match !module.segments.is_empty() && self.lookup(&relative).is_some() {
+            true => relative,
+            false => path.clone(),
+        }
+    }
+
+    /// Validates the imports of `usage`, which is in `file`, and adds them to
+    /// those of `scope`
+    fn collect_use(&mut self, scope: &Path, usage: &Use, file: Option<FileId>) -> Result<()> {
+        for import in usage.imports() {
+            self.collect_import(scope, &import, file).map_err(|kind| ResolveError::new(kind, file, import.span))?;
+        }
+
+        Ok(())
+    }
+
+    fn collect_import(
+        &mut self,
+        scope: &Path,
+        import: &Import,
+        file: Option<FileId>,
+    ) -> std::result::Result<(), ResolveErrorKind> {
+        let path = self.resolve_import_path(scope, &import.path);
+
+        match import.kind {
+            ImportKind::Named(name) => {
+                if self.lookup(&path).is_none() {
+                    return Err(ResolveErrorKind::UnknownImport(path));
+                }
+
+                self.check_visibility(scope, &path)?;
+
+                // Items take precedence over imports, so the import could
+                // never be used
+                if let Some(item) = self.lookup(&scope.with_ident(name)) {
+                    let item = self.def(item);
+                    return Err(ResolveErrorKind::Duplicate {
+                        kind: DuplicateKind::ImportAfterItem,
+                        name,
+                        first_file: item.file,
+                        first: item.name().span,
+                        second: name.span,
+                    });
+                }
+
+                let imports = self.imports.entry_mut(scope.clone());
+                match imports.named.get(&name) {
+                    Some(existing) if *existing != path => {
+                        return Err(ResolveErrorKind::ConflictingImport {
+                            name,
+                            first: existing.clone(),
+                            second: path,
+                        });
+                    }
+                    _ => {
+                        imports.named.insert(name, path);
+                        imports.named_at.insert(name, (file, name.span));
+                    }
+                }
+            }
+            ImportKind::Glob => {
+                if self.lookup(&path).map(|id| self.def(id).kind) != Some(DefKind::Module) {
+                    return Err(ResolveErrorKind::UnknownImport(path));
+                }
+
+                self.check_visibility(scope, &path)?;
+
+                let imports = self.imports.entry_mut(scope.clone());
+                if !imports.globs.contains(&path) {
+                    imports.globs.push(path);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn define(&mut self, kind: DefKind, path: Path, visibility: VisibilityKind, file: Option<FileId>) -> DefId {
+        let id = DefId(self.defs.push(Definition { kind, path: path.clone(), visibility, file }));
+        self.paths.insert(path, id);
+
+        id
+    }
+
+    fn new_local(&mut self, name: Identifier, mutable: bool, file: Option<FileId>) -> LocalId {
+        LocalId(self.locals.push(LocalInfo { name, mutable, file }))
+    }
+}
+
+/// Assigns a `DefId` to every item and block, so that paths can refer to
+/// items regardless of where they're defined, and rejects anything that's
+/// defined more than once
+struct Collector<'a> {
+    resolver: &'a mut Resolver,
+    scope: Path,
+    /// The file being collected from, which changes when entering a module
+    /// loaded from its own file
+    file: Option<FileId>,
+    /// Where each item seen so far was named. Items can be redefined by later
+    /// inputs, but not within the same one.
+    defined: HashMap<Path, Span>,
+    error: Option<ResolveError>,
+}
+
+impl<'a> Collector<'a> {
+    fn new(resolver: &'a mut Resolver, file: FileId) -> Self {
+        Self { resolver, scope: Path::new(), file: Some(file), defined: HashMap::new(), error: None }
+    }
+
+    fn finish(self) -> Result<()> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    fn report(&mut self, kind: DuplicateKind, name: Identifier, first_file: Option<FileId>, first: Span) {
+        if self.error.is_none() {
+            let kind = ResolveErrorKind::Duplicate { kind, name, first_file, first, second: name.span };
+            self.error = Some(ResolveError::new(kind, self.file, name.span));
+        }
+    }
+
+    fn check_unique(&mut self, kind: DuplicateKind, names: impl IntoIterator<Item = Identifier>) {
+        let mut seen = HashMap::new();
+
+        for name in names {
+            match seen.get(&name) {
+                Some(&first) => self.report(kind, name, self.file, first),
+                None => {
+                    seen.insert(name, name.span);
+                }
+            }
+        }
+    }
+}
+
+impl VisitorMut for Collector<'_> {
+    fn visit_item(&mut self, item: &mut Item) {
+        let (kind, name, visibility) = match &item.kind {
+            ItemKind::Module(m) => (DefKind::Module, m.name, m.visibility.kind),
+            ItemKind::Function(f) => (DefKind::Function, f.name, f.visibility.kind),
+            ItemKind::Struct(s) => (DefKind::Struct, s.name, s.visibility.kind),
+            ItemKind::Const(c) => (DefKind::Const, c.name, c.visibility.kind),
+            ItemKind::Static(s) => (DefKind::Static { mutable: s.mutable }, s.name, s.visibility.kind),
+            ItemKind::TypeAlias(t) => (DefKind::TypeAlias, t.name, t.visibility.kind),
+            ItemKind::Use(_) => return,
+        };
+
+        let path = self.scope.with_ident(name);
+        match self.defined.get(&path) {
+            Some(&first) => self.report(DuplicateKind::Item, name, self.file, first),
+            None => {
+                self.defined.insert(path.clone(), name.span);
+            }
+        }
+
+        // Imports from earlier inputs, since those of this one haven't been
+        // collected yet
+        let import = self.resolver.imports.get(&self.scope).and_then(|imports| imports.named_at.get(&name));
+        if let Some(&(file, first)) = import {
+            self.report(DuplicateKind::ItemAfterImport, name, file, first);
+        }
+
+        item.def = Some(self.resolver.define(kind, path.clone(), visibility, self.file));
+
+        match &item.kind {
+            ItemKind::Module(module) => {
+                // A redefined module starts over with no imports
+                self.resolver.imports.remove(&path);
+
+                let file = module.file.or(self.file);
+                let parent_file = std::mem::replace(&mut self.file, file);
+                let parent = std::mem::replace(&mut self.scope, path);
+                visit_mut::walk::item(self, item);
+                self.scope = parent;
+                self.file = parent_file;
+            }
+            ItemKind::Function(_) => {
+                let parent = std::mem::replace(&mut self.scope, path);
+                visit_mut::walk::item(self, item);
+                self.scope = parent;
+            }
+            _ => visit_mut::walk::item(self, item),
+        }
+    }
+
+    fn visit_block(&mut self, block: &mut Block) {
+        let path = self.scope.with_ident(Identifier::new(&format!("{{{}}}", self.resolver.block_count)));
+        self.resolver.block_count += 1;
+        block.scope = Some(self.resolver.define(DefKind::Block, path.clone(), VisibilityKind::Public, self.file));
+
+        let parent = std::mem::replace(&mut self.scope, path);
+        visit_mut::walk::block(self, block);
+        self.scope = parent;
+    }
+
+    fn visit_struct(&mut self, strukt: &mut Struct) {
+        self.check_unique(DuplicateKind::Field, strukt.members.iter().map(|member| member.name));
+    }
+
+    fn visit_function(&mut self, function: &mut Function) {
+        self.check_unique(DuplicateKind::Parameter, function.parameters.iter().map(|param| param.name));
+        visit_mut::walk::function(self, function);
+    }
+
+    fn visit_struct_expr(&mut self, struct_expr: &mut StructExpr) {
+        self.check_unique(DuplicateKind::StructExprField, struct_expr.members.iter().map(|member| member.name));
+        visit_mut::walk::struct_expr(self, struct_expr);
+    }
+}
+
+/// Collects the imports of every module and block
+struct Importer<'a> {
+    resolver: &'a mut Resolver,
+    scope: Path,
+    file: Option<FileId>,
+    error: Option<ResolveError>,
+}
+
+impl Visitor for Importer<'_> {
+    fn visit_item(&mut self, item: &Item) {
+        match &item.kind {
+            ItemKind::Module(_) | ItemKind::Function(_) => {
+                let def = self.resolver.def(item.def_id());
+                let path = def.path.clone();
+                let file = match &item.kind {
+                    ItemKind::Module(module) => module.file.or(def.file),
+                    _ => def.file,
+                };
+
+                let parent_file = std::mem::replace(&mut self.file, file);
+                let parent = std::mem::replace(&mut self.scope, path);
+                visit::walk::item(self, item);
+                self.scope = parent;
+                self.file = parent_file;
+            }
+            ItemKind::Use(usage) => {
+                if self.error.is_none() {
+                    self.error = self.resolver.collect_use(&self.scope, usage, self.file).err();
+                }
+            }
+            _ => visit::walk::item(self, item),
+        }
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        let path = self.resolver.def(block.scope_id()).path.clone();
+        let parent = std::mem::replace(&mut self.scope, path);
+        visit::walk::block(self, block);
+        self.scope = parent;
+    }
+}
+
+/// Resolves every path to the item or local it refers to
+struct PathResolver<'a> {
+    resolver: &'a mut Resolver,
+    scope: Path,
+    file: Option<FileId>,
+    /// The locals in scope, one set per enclosing block
+    ribs: Vec<HashMap<Identifier, LocalId>>,
+    error: Option<ResolveError>,
+}
+
+impl<'a> PathResolver<'a> {
+    fn new(resolver: &'a mut Resolver, file: FileId, ribs: Vec<HashMap<Identifier, LocalId>>) -> Self {
+        Self { resolver, scope: Path::new(), file: Some(file), ribs, error: None }
+    }
+
+    fn finish(self) -> Result<()> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    fn report(&mut self, kind: ResolveErrorKind, span: Span) {
+        if self.error.is_none() {
+            self.error = Some(ResolveError::new(kind, self.file, span));
+        }
+    }
+
+    fn bind(&mut self, name: Identifier, mutable: bool) -> LocalId {
+        let id = self.resolver.new_local(name, mutable, self.file);
+        if let Some(rib) = self.ribs.last_mut() {
+            rib.insert(name, id);
+        }
+
+        id
+    }
+
+    fn resolve_def(&mut self, path: &Path) -> Option<(DefId, DefKind)> {
+        let (id, import) = self.resolver.resolve_path_through(&self.scope, path)?;
+        if let Some(import) = import {
+            self.resolver.used_imports.insert(import);
+        }
+        let def = self.resolver.def(id);
+        let kind = def.kind;
+
+        if let Err(error) = self.resolver.check_visibility(&self.scope, &def.path) {
+            self.report(error, path.span());
+        }
+
+        Some((id, kind))
+    }
+
+    fn resolve_value(&mut self, path: &mut Path) {
+        if let Some(ident) = path.is_identifier() {
+            if let Some(&id) = self.ribs.iter().rev().find_map(|rib| rib.get(&ident)) {
+                path.res = Some(Res::Local(id));
+                return;
+            }
+        }
+
+        match self.resolve_def(path) {
+            Some((id, kind)) if kind.is_value() => path.res = Some(Res::Def(id)),
+            Some((_, kind)) => self.report(ResolveErrorKind::NotAValue(path.clone(), kind), path.span()),
+            None => match path.is_identifier() {
+                Some(ident) => self.report(ResolveErrorKind::UnknownIdentifier(ident), ident.span),
+                None => self.report(ResolveErrorKind::UnknownPath(path.clone()), path.span()),
+            },
+        }
+    }
+
+    fn resolve_type(&mut self, path: &mut Path) {
+        match self.resolve_def(path) {
+            Some((id, kind)) if kind.is_type() => path.res = Some(Res::Def(id)),
+            Some((_, kind)) => self.report(ResolveErrorKind::NotAType(path.clone(), kind), path.span()),
+            None => self.report(ResolveErrorKind::UnknownType(path.clone()), path.span()),
+        }
+    }
+}
+
+impl VisitorMut for PathResolver<'_> {
+    fn visit_item(&mut self, item: &mut Item) {
+        let (scope, file, ribs) = match &item.kind {
+            // Items can't refer to the locals around them
+            ItemKind::Module(module) => {
+                let def = self.resolver.def(item.def_id());
+                (def.path.clone(), module.file.or(def.file), Vec::new())
+            }
+            ItemKind::Function(_) => (self.resolver.def(item.def_id()).path.clone(), self.file, vec![HashMap::new()]),
+            ItemKind::Use(_) => return,
+            _ => (self.scope.clone(), self.file, Vec::new()),
+        };
+
+        let parent_scope = std::mem::replace(&mut self.scope, scope);
+        let parent_file = std::mem::replace(&mut self.file, file);
+        let parent_ribs = std::mem::replace(&mut self.ribs, ribs);
+        visit_mut::walk::item(self, item);
+        self.scope = parent_scope;
+        self.file = parent_file;
+        self.ribs = parent_ribs;
+    }
+
+    fn visit_attribute(&mut self, _: &mut Attribute) {
+        // Attribute arguments aren't resolved
+    }
+
+    fn visit_function_parameter(&mut self, parameter: &mut FunctionParameter) {
+        self.visit_type(&mut parameter.ty);
+        parameter.id = Some(self.bind(parameter.name, false));
+    }
+
+    fn visit_type(&mut self, ty: &mut Type) {
+        match &mut ty.kind {
+            TypeKind::Path(path) => self.resolve_type(path),
+            _ => visit_mut::walk::ty(self, ty),
+        }
+    }
+
+    fn visit_block(&mut self, block: &mut Block) {
+        let scope = self.resolver.def(block.scope_id()).path.clone();
+        let parent = std::mem::replace(&mut self.scope, scope);
+        self.ribs.push(HashMap::new());
+
+        visit_mut::walk::block(self, block);
+
+        self.ribs.pop();
+        self.scope = parent;
+    }
+
+    fn visit_local(&mut self, local: &mut Local) {
+        self.visit_type(&mut local.ty);
+        self.visit_expression(&mut local.value);
+        local.id = Some(self.bind(local.name, local.mutable));
+    }
+
+    fn visit_expression(&mut self, expression: &mut Expression) {
+        expression.id = Some(ExprId(self.resolver.expr_count));
+        self.resolver.expr_count += 1;
+
+        match &mut expression.kind {
+            ExpressionKind::Path(path) => self.resolve_value(path),
+            _ => visit_mut::walk::expression(self, expression),
+        }
+    }
+
+    fn visit_struct_expr(&mut self, struct_expr: &mut StructExpr) {
+        self.resolve_type(&mut struct_expr.name);
+        for member in &mut struct_expr.members {
+            self.visit_struct_expr_member(member);
+        }
+
+        if let Some(base) = &mut struct_expr.base {
+            self.visit_expression(base);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codespan::Files;
+
+    /// Resolves inputs one at a time, the way the REPL does
+    struct Session {
+        resolver: Resolver,
+        files: Files<String>,
+    }
+
+    /// The paths of the items that the paths within the visited code refer
+    /// to, in order
+    struct Resolutions<'a> {
+        resolver: &'a Resolver,
+        paths: Vec<String>,
+    }
+
+    impl Visitor for Resolutions<'_> {
+        fn visit_path(&mut self, path: &Path) {
+            if let Some(Res::Def(def)) = path.res {
+                self.paths.push(self.resolver.def(def).path.to_string());
+            }
+        }
+    }
+
+    impl Session {
+        fn new() -> Self {
+            Self { resolver: Resolver::new(), files: Files::new() }
+        }
+
+        /// Resolves the item `source`, returning what the paths within it
+        /// refer to
+        fn item(&mut self, source: &str) -> Result<Vec<String>> {
+            let file = self.files.add("item", source.to_string());
+            let mut item = Item::convert(&parser::Parser::new(source).item().unwrap());
+            self.resolver.resolve_item(&mut item, file)?;
+
+            let mut resolutions = Resolutions { resolver: &self.resolver, paths: Vec::new() };
+            resolutions.visit_item(&item);
+            Ok(resolutions.paths)
+        }
+
+        fn duplicate(&mut self, source: &str) -> Option<DuplicateKind> {
+            match self.item(source).map_err(|error| error.kind) {
+                Err(ResolveErrorKind::Duplicate { kind, .. }) => Some(kind),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn inner_scopes_shadow_outer_ones() {
+        let mut session = Session::new();
+        session.item("module a { pub fn f() {} }").unwrap();
+
+        let paths = session.item("module m { fn f() {} pub fn g() { fn f() {} f() } }").unwrap();
+        assert_eq!(paths.len(), 1);
+        assert!(paths[0].starts_with("m::g::") && paths[0] != "m::f", "resolved to `{}`", paths[0]);
+
+        let paths = session.item("module n { fn f() {} pub fn g() { use super::a::f; f() } }").unwrap();
+        assert_eq!(paths, ["a::f"]);
+    }
+
+    #[test]
+    fn named_imports_and_items_shadow_glob_imports() {
+        let mut session = Session::new();
+        session.item("module a { pub fn f() {} pub fn g() {} }").unwrap();
+        session.item("module b { pub fn f() {} }").unwrap();
+
+        let paths = session.item("module m { use super::a::*; use super::b::f; pub fn h() { f(); g() } }").unwrap();
+        assert_eq!(paths, ["b::f", "a::g"]);
+
+        let paths = session.item("module n { use super::a::*; fn f() {} pub fn h() { f() } }").unwrap();
+        assert_eq!(paths, ["n::f"]);
+    }
+
+    #[test]
+    fn imports_and_items_of_the_same_name_conflict() {
+        let mut session = Session::new();
+        session.item("module a { pub fn f() {} }").unwrap();
+
+        let kind = session.duplicate("module m { fn f() {} use super::a::f; }");
+        assert_eq!(kind, Some(DuplicateKind::ImportAfterItem));
+
+        // Items are collected before imports within an input, so an item can
+        // only follow an import from an earlier one
+        session.item("use a::f;").unwrap();
+        assert_eq!(session.duplicate("fn f() {}"), Some(DuplicateKind::ItemAfterImport));
+    }
+}
@@ -0,0 +1,302 @@
+//! Collections that can undo the changes made to them since a snapshot was
+//! taken. An input at the REPL that fails has everything it defined thrown
+//! away, and undoing only what it changed keeps that cheap however much has
+//! been defined before it.
+
+use std::{
+    collections::{hash_map::ValuesMut, HashMap},
+    hash::Hash,
+    ops::Deref,
+};
+
+/// A `Vec` whose elements can only be pushed or replaced, so that it can be
+/// rolled back to its length and contents when a snapshot was taken
+#[derive(Clone, Debug)]
+pub struct SnapshotVec<T> {
+    values: Vec<T>,
+    /// The elements replaced since the oldest open snapshot was taken, along
+    /// with what they were before, oldest first
+    undo_log: Vec<(usize, T)>,
+    /// How many snapshots are open
+    open: usize,
+}
+
+/// What a [`SnapshotVec`] looked like when a snapshot of it was taken
+#[derive(Clone, Copy, Debug)]
+pub struct VecSnapshot {
+    len: usize,
+    undo_len: usize,
+}
+
+impl<T> SnapshotVec<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `value`, returning its index
+    pub fn push(&mut self, value: T) -> usize {
+        self.values.push(value);
+        self.values.len() - 1
+    }
+
+    /// Replaces the element at `index` with `value`
+    pub fn set(&mut self, index: usize, value: T) {
+        let old = std::mem::replace(&mut self.values[index], value);
+        if self.open > 0 {
+            self.undo_log.push((index, old));
+        }
+    }
+
+    pub fn snapshot(&mut self) -> VecSnapshot {
+        self.open += 1;
+        VecSnapshot { len: self.values.len(), undo_len: self.undo_log.len() }
+    }
+
+    /// Undoes every change made since `snapshot` was taken
+    pub fn rollback_to(&mut self, snapshot: VecSnapshot) {
+        while self.undo_log.len() > snapshot.undo_len {
+            let (index, old) = self.undo_log.pop().unwrap();
+            self.values[index] = old;
+        }
+
+        self.values.truncate(snapshot.len);
+        self.close();
+    }
+
+    /// Keeps the changes made since `snapshot` was taken
+    pub fn commit(&mut self, _: VecSnapshot) {
+        self.close();
+    }
+
+    fn close(&mut self) {
+        self.open -= 1;
+        if self.open == 0 {
+            self.undo_log.clear();
+        }
+    }
+}
+
+impl<T> Default for SnapshotVec<T> {
+    fn default() -> Self {
+        Self { values: Vec::new(), undo_log: Vec::new(), open: 0 }
+    }
+}
+
+impl<T> From<Vec<T>> for SnapshotVec<T> {
+    fn from(values: Vec<T>) -> Self {
+        Self { values, undo_log: Vec::new(), open: 0 }
+    }
+}
+
+impl<T> Deref for SnapshotVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.values
+    }
+}
+
+/// A `HashMap` that can be rolled back to its contents when a snapshot was
+/// taken
+#[derive(Clone, Debug)]
+pub struct SnapshotMap<K, V> {
+    map: HashMap<K, V>,
+    /// The keys changed since the oldest open snapshot was taken, along with
+    /// what they mapped to before, oldest first
+    undo_log: Vec<(K, Option<V>)>,
+    /// How many snapshots are open
+    open: usize,
+}
+
+/// What a [`SnapshotMap`] or [`SnapshotSet`] looked like when a snapshot of
+/// it was taken
+#[derive(Clone, Copy, Debug)]
+pub struct MapSnapshot {
+    undo_len: usize,
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> SnapshotMap<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let old = self.map.insert(key.clone(), value);
+        self.log(key, &old);
+
+        old
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let old = self.map.remove(key);
+        if old.is_some() {
+            self.log(key.clone(), &old);
+        }
+
+        old
+    }
+
+    /// The value of `key` to be changed in place, inserting the default value
+    /// if there isn't one
+    pub fn entry_mut(&mut self, key: K) -> &mut V
+    where
+        V: Default,
+    {
+        let old = self.map.get(&key).cloned();
+        self.log(key.clone(), &old);
+
+        self.map.entry(key).or_default()
+    }
+
+    /// The values to be changed in place. Changes made through this can't be
+    /// undone, so it mustn't be used while a snapshot is open.
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        debug_assert_eq!(self.open, 0, "values can only be changed in place outside of snapshots");
+        self.map.values_mut()
+    }
+
+    fn log(&mut self, key: K, old: &Option<V>) {
+        if self.open > 0 {
+            self.undo_log.push((key, old.clone()));
+        }
+    }
+
+    pub fn snapshot(&mut self) -> MapSnapshot {
+        self.open += 1;
+        MapSnapshot { undo_len: self.undo_log.len() }
+    }
+
+    /// Undoes every change made since `snapshot` was taken
+    pub fn rollback_to(&mut self, snapshot: MapSnapshot) {
+        while self.undo_log.len() > snapshot.undo_len {
+            match self.undo_log.pop().unwrap() {
+                (key, Some(old)) => self.map.insert(key, old),
+                (key, None) => self.map.remove(&key),
+            };
+        }
+
+        self.close();
+    }
+
+    /// Keeps the changes made since `snapshot` was taken
+    pub fn commit(&mut self, _: MapSnapshot) {
+        self.close();
+    }
+
+    fn close(&mut self) {
+        self.open -= 1;
+        if self.open == 0 {
+            self.undo_log.clear();
+        }
+    }
+}
+
+impl<K, V> Default for SnapshotMap<K, V> {
+    fn default() -> Self {
+        Self { map: HashMap::new(), undo_log: Vec::new(), open: 0 }
+    }
+}
+
+impl<K, V> Deref for SnapshotMap<K, V> {
+    type Target = HashMap<K, V>;
+
+    fn deref(&self) -> &HashMap<K, V> {
+        &self.map
+    }
+}
+
+/// A `HashSet` that can be rolled back to its contents when a snapshot was
+/// taken
+#[derive(Clone, Debug)]
+pub struct SnapshotSet<T>(SnapshotMap<T, ()>);
+
+impl<T: Clone + Eq + Hash> SnapshotSet<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `value`, returning whether it wasn't already in the set
+    pub fn insert(&mut self, value: T) -> bool {
+        self.0.insert(value, ()).is_none()
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        self.0.contains_key(value)
+    }
+
+    pub fn snapshot(&mut self) -> MapSnapshot {
+        self.0.snapshot()
+    }
+
+    /// Undoes every insertion made since `snapshot` was taken
+    pub fn rollback_to(&mut self, snapshot: MapSnapshot) {
+        self.0.rollback_to(snapshot)
+    }
+
+    /// Keeps the insertions made since `snapshot` was taken
+    pub fn commit(&mut self, snapshot: MapSnapshot) {
+        self.0.commit(snapshot)
+    }
+}
+
+impl<T> Default for SnapshotSet<T> {
+    fn default() -> Self {
+        Self(SnapshotMap::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolling_back_a_vec_undoes_pushes_and_replacements() {
+        let mut vec = SnapshotVec::from(vec![1, 2]);
+
+        let snapshot = vec.snapshot();
+        vec.set(0, 10);
+        vec.push(3);
+        vec.set(2, 30);
+        vec.rollback_to(snapshot);
+        assert_eq!(&vec[..], &[1, 2]);
+
+        let snapshot = vec.snapshot();
+        vec.set(1, 20);
+        vec.commit(snapshot);
+        assert_eq!(&vec[..], &[1, 20]);
+    }
+
+    #[test]
+    fn rolling_back_a_map_restores_what_keys_mapped_to() {
+        let mut map = SnapshotMap::new();
+        map.insert("a", 1);
+        map.insert("b", 2);
+
+        let snapshot = map.snapshot();
+        map.insert("a", 10);
+        map.insert("a", 100);
+        map.remove(&"b");
+        map.insert("c", 3);
+        *map.entry_mut("d") += 4;
+        map.rollback_to(snapshot);
+
+        let mut entries: Vec<_> = map.iter().map(|(&key, &value)| (key, value)).collect();
+        entries.sort();
+        assert_eq!(entries, [("a", 1), ("b", 2)]);
+    }
+
+    #[test]
+    fn nested_snapshots_roll_back_separately() {
+        let mut set = SnapshotSet::new();
+
+        let outer = set.snapshot();
+        set.insert(1);
+        let inner = set.snapshot();
+        set.insert(2);
+        set.rollback_to(inner);
+        assert!(set.contains(&1) && !set.contains(&2));
+
+        set.rollback_to(outer);
+        assert!(!set.contains(&1));
+    }
+}
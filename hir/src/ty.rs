@@ -28,6 +28,8 @@ pub enum TypeKind {
     Path(Path),
     Unit,
     Infer,
+    Never,
+    Box(Box<Type>),
 }
 
 impl TypeKind {
@@ -36,6 +38,8 @@ impl TypeKind {
             ast::TypeKind::Bool => TypeKind::Bool,
             ast::TypeKind::Integer => TypeKind::Integer,
             ast::TypeKind::Named(path) => TypeKind::Path(Path::convert(path)),
+            ast::TypeKind::Never => TypeKind::Never,
+            ast::TypeKind::Box(ty) => TypeKind::Box(Box::new(Type::convert(ty))),
         }
     }
 }
@@ -48,6 +52,8 @@ impl Display for TypeKind {
             TypeKind::Path(p) => write!(f, "{}", p),
             TypeKind::Unit => write!(f, "unit"),
             TypeKind::Infer => write!(f, "_"),
+            TypeKind::Never => write!(f, "!"),
+            TypeKind::Box(ty) => write!(f, "Box<{}>", ty.kind),
         }
     }
 }
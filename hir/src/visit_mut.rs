@@ -0,0 +1,271 @@
+use crate::*;
+
+/// Like [`Visitor`](crate::visit::Visitor), but with mutable access to each
+/// node so that the tree can be rewritten in place
+pub trait VisitorMut: Sized {
+    fn visit_module(&mut self, module: &mut Module) {
+        walk::module(self, module);
+    }
+
+    fn visit_item(&mut self, item: &mut Item) {
+        walk::item(self, item);
+    }
+
+    fn visit_attribute(&mut self, attribute: &mut Attribute) {
+        walk::attribute(self, attribute);
+    }
+
+    fn visit_function(&mut self, function: &mut Function) {
+        walk::function(self, function);
+    }
+
+    fn visit_identifier(&mut self, _: &mut Identifier) {
+        // Nothing else to do
+    }
+
+    fn visit_struct(&mut self, strukt: &mut Struct) {
+        walk::structure(self, strukt);
+    }
+
+    fn visit_struct_member(&mut self, struct_member: &mut StructMember) {
+        walk::struct_member(self, struct_member);
+    }
+
+    fn visit_path(&mut self, path: &mut Path) {
+        walk::path(self, path);
+    }
+
+    fn visit_type(&mut self, ty: &mut Type) {
+        walk::ty(self, ty);
+    }
+
+    fn visit_function_parameter(&mut self, parameter: &mut FunctionParameter) {
+        walk::function_parameter(self, parameter);
+    }
+
+    fn visit_block(&mut self, block: &mut Block) {
+        walk::block(self, block);
+    }
+
+    fn visit_statement(&mut self, statement: &mut Statement) {
+        walk::statement(self, statement);
+    }
+
+    fn visit_local(&mut self, local: &mut Local) {
+        walk::local(self, local);
+    }
+
+    fn visit_expression(&mut self, expression: &mut Expression) {
+        walk::expression(self, expression);
+    }
+
+    fn visit_if_expr(&mut self, if_expr: &mut IfExpr) {
+        walk::if_expr(self, if_expr);
+    }
+
+    fn visit_struct_expr(&mut self, struct_expr: &mut StructExpr) {
+        walk::struct_expr(self, struct_expr);
+    }
+
+    fn visit_struct_expr_member(&mut self, member: &mut StructExprMember) {
+        walk::struct_expr_member(self, member);
+    }
+
+    fn visit_use(&mut self, usage: &mut Use) {
+        walk::usage(self, usage);
+    }
+
+    fn visit_use_tree(&mut self, tree: &mut UseTree) {
+        walk::use_tree(self, tree);
+    }
+
+    fn visit_const(&mut self, constant: &mut Const) {
+        walk::constant(self, constant);
+    }
+
+    fn visit_static(&mut self, statik: &mut Static) {
+        walk::statik(self, statik);
+    }
+
+    fn visit_type_alias(&mut self, alias: &mut TypeAlias) {
+        walk::type_alias(self, alias);
+    }
+}
+
+pub mod walk {
+    use super::*;
+
+    use crate::visit::walk::list;
+
+    pub fn module<V: VisitorMut>(visitor: &mut V, module: &mut Module) {
+        list!(visitor, visit_attribute, &mut module.attributes);
+        visitor.visit_identifier(&mut module.name);
+        list!(visitor, visit_item, &mut module.items);
+    }
+
+    pub fn item<V: VisitorMut>(visitor: &mut V, item: &mut Item) {
+        match &mut item.kind {
+            ItemKind::Function(f) => visitor.visit_function(f),
+            ItemKind::Module(m) => visitor.visit_module(m),
+            ItemKind::Struct(s) => visitor.visit_struct(s),
+            ItemKind::Use(u) => visitor.visit_use(u),
+            ItemKind::Const(c) => visitor.visit_const(c),
+            ItemKind::Static(s) => visitor.visit_static(s),
+            ItemKind::TypeAlias(t) => visitor.visit_type_alias(t),
+        }
+    }
+
+    pub fn attribute<V: VisitorMut>(visitor: &mut V, attribute: &mut Attribute) {
+        visitor.visit_identifier(&mut attribute.name);
+
+        for arg in attribute.args.iter_mut().flatten() {
+            match arg {
+                AttributeArg::Path(path, _) => visitor.visit_path(path),
+                AttributeArg::Integer(..) => {}
+            }
+        }
+    }
+
+    pub fn structure<V: VisitorMut>(visitor: &mut V, strukt: &mut Struct) {
+        list!(visitor, visit_attribute, &mut strukt.attributes);
+        visitor.visit_identifier(&mut strukt.name);
+        list!(visitor, visit_struct_member, &mut strukt.members);
+    }
+
+    pub fn struct_member<V: VisitorMut>(visitor: &mut V, struct_member: &mut StructMember) {
+        list!(visitor, visit_attribute, &mut struct_member.attributes);
+        visitor.visit_identifier(&mut struct_member.name);
+        visitor.visit_type(&mut struct_member.ty);
+    }
+
+    pub fn path<V: VisitorMut>(visitor: &mut V, path: &mut Path) {
+        list!(visitor, visit_identifier, &mut path.segments);
+    }
+
+    pub fn ty<V: VisitorMut>(visitor: &mut V, ty: &mut Type) {
+        match &mut ty.kind {
+            TypeKind::Integer | TypeKind::Bool | TypeKind::Unit | TypeKind::Infer | TypeKind::Never => {}
+            TypeKind::Path(path) => visitor.visit_path(path),
+            TypeKind::Box(ty) => visitor.visit_type(ty),
+        }
+    }
+
+    pub fn function<V: VisitorMut>(visitor: &mut V, function: &mut Function) {
+        list!(visitor, visit_attribute, &mut function.attributes);
+        visitor.visit_identifier(&mut function.name);
+        list!(visitor, visit_function_parameter, &mut function.parameters);
+        visitor.visit_type(&mut function.return_type);
+        visitor.visit_block(&mut function.body);
+    }
+
+    pub fn function_parameter<V: VisitorMut>(visitor: &mut V, parameter: &mut FunctionParameter) {
+        visitor.visit_identifier(&mut parameter.name);
+        visitor.visit_type(&mut parameter.ty);
+    }
+
+    pub fn block<V: VisitorMut>(visitor: &mut V, block: &mut Block) {
+        list!(visitor, visit_item, &mut block.items);
+        list!(visitor, visit_statement, &mut block.statements);
+        visitor.visit_expression(&mut block.return_expr);
+    }
+
+    pub fn statement<V: VisitorMut>(visitor: &mut V, statement: &mut Statement) {
+        match &mut statement.kind {
+            StatementKind::Local(local) => visitor.visit_local(local),
+            StatementKind::Expression(expr) => visitor.visit_expression(expr),
+        }
+    }
+
+    pub fn local<V: VisitorMut>(visitor: &mut V, local: &mut Local) {
+        visitor.visit_identifier(&mut local.name);
+        visitor.visit_type(&mut local.ty);
+        visitor.visit_expression(&mut local.value);
+    }
+
+    pub fn expression<V: VisitorMut>(visitor: &mut V, expression: &mut Expression) {
+        list!(visitor, visit_attribute, &mut expression.attributes);
+
+        match &mut expression.kind {
+            ExpressionKind::Assignment(place, value) => {
+                visitor.visit_expression(place);
+                visitor.visit_expression(value);
+            }
+            ExpressionKind::BinaryOperation(lhs, _, rhs) => {
+                visitor.visit_expression(lhs);
+                visitor.visit_expression(rhs);
+            }
+            ExpressionKind::Block(block) => visitor.visit_block(block),
+            ExpressionKind::FieldAccess(base, field) => {
+                visitor.visit_expression(base);
+                visitor.visit_identifier(field);
+            }
+            ExpressionKind::FnCall(callee, args) => {
+                visitor.visit_expression(callee);
+                list!(visitor, visit_expression, args);
+            }
+            ExpressionKind::If(if_expr) => visitor.visit_if_expr(if_expr),
+            ExpressionKind::Path(path) => visitor.visit_path(path),
+            ExpressionKind::Struct(struct_expr) => visitor.visit_struct_expr(struct_expr),
+            ExpressionKind::Unary(_, expr) => visitor.visit_expression(expr),
+            ExpressionKind::Boolean(_) | ExpressionKind::Integer(_) | ExpressionKind::Unit => {}
+        }
+    }
+
+    pub fn if_expr<V: VisitorMut>(visitor: &mut V, if_expr: &mut IfExpr) {
+        for branch in &mut if_expr.ifs {
+            visitor.visit_expression(&mut branch.condition);
+            visitor.visit_block(&mut branch.body);
+        }
+
+        visitor.visit_block(&mut if_expr.r#else);
+    }
+
+    pub fn struct_expr<V: VisitorMut>(visitor: &mut V, struct_expr: &mut StructExpr) {
+        visitor.visit_path(&mut struct_expr.name);
+        list!(visitor, visit_struct_expr_member, &mut struct_expr.members);
+
+        if let Some(base) = &mut struct_expr.base {
+            visitor.visit_expression(base);
+        }
+    }
+
+    pub fn struct_expr_member<V: VisitorMut>(visitor: &mut V, member: &mut StructExprMember) {
+        visitor.visit_identifier(&mut member.name);
+        visitor.visit_expression(&mut member.expression);
+    }
+
+    pub fn constant<V: VisitorMut>(visitor: &mut V, constant: &mut Const) {
+        list!(visitor, visit_attribute, &mut constant.attributes);
+        visitor.visit_identifier(&mut constant.name);
+        visitor.visit_type(&mut constant.ty);
+        visitor.visit_expression(&mut constant.value);
+    }
+
+    pub fn statik<V: VisitorMut>(visitor: &mut V, statik: &mut Static) {
+        list!(visitor, visit_attribute, &mut statik.attributes);
+        visitor.visit_identifier(&mut statik.name);
+        visitor.visit_type(&mut statik.ty);
+        visitor.visit_expression(&mut statik.value);
+    }
+
+    pub fn type_alias<V: VisitorMut>(visitor: &mut V, alias: &mut TypeAlias) {
+        list!(visitor, visit_attribute, &mut alias.attributes);
+        visitor.visit_identifier(&mut alias.name);
+        visitor.visit_type(&mut alias.ty);
+    }
+
+    pub fn usage<V: VisitorMut>(visitor: &mut V, usage: &mut Use) {
+        list!(visitor, visit_attribute, &mut usage.attributes);
+        visitor.visit_use_tree(&mut usage.tree);
+    }
+
+    pub fn use_tree<V: VisitorMut>(visitor: &mut V, tree: &mut UseTree) {
+        visitor.visit_path(&mut tree.prefix);
+
+        match &mut tree.kind {
+            UseTreeKind::Simple(Some(rename)) => visitor.visit_identifier(rename),
+            UseTreeKind::Simple(None) | UseTreeKind::Glob => {}
+            UseTreeKind::Nested(trees) => list!(visitor, visit_use_tree, trees),
+        }
+    }
+}
@@ -0,0 +1,396 @@
+//! The lints checked by default
+
+use crate::{unused, Level, Lint, LintContext, LintPass};
+use hir::{
+    BinOp, Expression, ExpressionKind, FunctionParameter, Identifier, ImportKind, Item, ItemKind, Local, LocalId, Path,
+    StructMember,
+};
+
+pub(crate) static PASSES: &[&dyn LintPass] = &[
+    &NonSnakeCase,
+    &NonCamelCaseTypes,
+    &SelfComparison,
+    &ConstantCondition,
+    &RedundantBlock,
+    &IdentifierSuffix,
+    &DeadCode,
+    &UnusedImports,
+    &UnusedMut,
+    &UnusedVariables,
+    &UnknownLints,
+];
+
+/// Names that set the level of several lints at once
+pub(crate) static GROUPS: &[(&str, &[&Lint])] =
+    &[("unused", &[&DEAD_CODE, &UNUSED_IMPORTS, &UNUSED_MUT, &UNUSED_VARIABLES])];
+
+/// The suffixes identifiers are allowed to end in, longest first
+const SUFFIXES: &[&str] = &["?!", "?", "!", "\u{2048}"];
+
+/// `name` without any suffix, along with the suffix
+fn split_suffix(name: &str) -> (&str, Option<&'static str>) {
+    for &suffix in SUFFIXES {
+        if let Some(stem) = name.strip_suffix(suffix) {
+            return (stem, Some(suffix));
+        }
+    }
+
+    (name, None)
+}
+
+/// The name declared by `item`, if it declares one
+fn item_name(item: &Item) -> Option<Identifier> {
+    match &item.kind {
+        ItemKind::Module(m) => Some(m.name),
+        ItemKind::Function(f) => Some(f.name),
+        ItemKind::Struct(s) => Some(s.name),
+        ItemKind::Use(_) => None,
+        ItemKind::Const(c) => Some(c.name),
+        ItemKind::Static(s) => Some(s.name),
+        ItemKind::TypeAlias(t) => Some(t.name),
+    }
+}
+
+static NON_SNAKE_CASE: Lint = Lint {
+    name: "non_snake_case",
+    description: "function names that aren't in snake case, e.g. `do_thing`",
+    default: Level::Warn,
+};
+
+struct NonSnakeCase;
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    let mut previous = None::<char>;
+
+    for c in name.chars() {
+        if c.is_uppercase() {
+            if previous.is_some_and(|p| p.is_lowercase() || p.is_numeric()) {
+                snake.push('_');
+            }
+
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+
+        previous = Some(c);
+    }
+
+    snake
+}
+
+impl LintPass for NonSnakeCase {
+    fn lint(&self) -> &'static Lint {
+        &NON_SNAKE_CASE
+    }
+
+    fn check_item(&self, cx: &mut LintContext, item: &Item) {
+        if let ItemKind::Function(function) = &item.kind {
+            let name = function.name.string();
+            let (stem, suffix) = split_suffix(&name);
+
+            if stem.chars().any(char::is_uppercase) {
+                let suggestion = format!("{}{}", to_snake_case(stem), suffix.unwrap_or(""));
+                let message = format!("Function `{}` should have a snake case name like `{}`", name, suggestion);
+                cx.emit(&NON_SNAKE_CASE, function.name.span, message);
+            }
+        }
+    }
+}
+
+static NON_CAMEL_CASE_TYPES: Lint = Lint {
+    name: "non_camel_case_types",
+    description: "struct names that aren't in camel case, e.g. `ThingDoer`",
+    default: Level::Warn,
+};
+
+struct NonCamelCaseTypes;
+
+fn to_camel_case(name: &str) -> String {
+    let mut camel = String::new();
+
+    for word in name.split('_').filter(|word| !word.is_empty()) {
+        let mut chars = word.chars();
+        camel.extend(chars.next().into_iter().flat_map(char::to_uppercase));
+        camel.extend(chars);
+    }
+
+    camel
+}
+
+impl LintPass for NonCamelCaseTypes {
+    fn lint(&self) -> &'static Lint {
+        &NON_CAMEL_CASE_TYPES
+    }
+
+    fn check_item(&self, cx: &mut LintContext, item: &Item) {
+        if let ItemKind::Struct(strukt) = &item.kind {
+            let name = strukt.name.string();
+            let (stem, suffix) = split_suffix(&name);
+            let stem = stem.trim_start_matches('_');
+
+            if stem.starts_with(char::is_lowercase) || stem.contains('_') {
+                let suggestion = format!("{}{}", to_camel_case(stem), suffix.unwrap_or(""));
+                let message = format!("Struct `{}` should have a camel case name like `{}`", name, suggestion);
+                cx.emit(&NON_CAMEL_CASE_TYPES, strukt.name.span, message);
+            }
+        }
+    }
+}
+
+static SELF_COMPARISON: Lint = Lint {
+    name: "self_comparison",
+    description: "comparisons of an expression with itself, e.g. `x == x`",
+    default: Level::Warn,
+};
+
+struct SelfComparison;
+
+/// Whether `lhs` and `rhs` always evaluate to the same value
+fn same(lhs: &Expression, rhs: &Expression) -> bool {
+    match (&lhs.kind, &rhs.kind) {
+        (ExpressionKind::Path(lhs), ExpressionKind::Path(rhs)) => same_path(lhs, rhs),
+        (ExpressionKind::FieldAccess(lhs, lhs_field), ExpressionKind::FieldAccess(rhs, rhs_field)) => {
+            lhs_field == rhs_field && same(lhs, rhs)
+        }
+        (ExpressionKind::Integer(lhs), ExpressionKind::Integer(rhs)) => lhs == rhs,
+        (ExpressionKind::Boolean(lhs), ExpressionKind::Boolean(rhs)) => lhs == rhs,
+        (ExpressionKind::Unit, ExpressionKind::Unit) => true,
+        _ => false,
+    }
+}
+
+fn same_path(lhs: &Path, rhs: &Path) -> bool {
+    lhs.res.is_some() && lhs.res == rhs.res
+}
+
+impl LintPass for SelfComparison {
+    fn lint(&self) -> &'static Lint {
+        &SELF_COMPARISON
+    }
+
+    fn check_expression(&self, cx: &mut LintContext, expression: &Expression) {
+        if let ExpressionKind::BinaryOperation(lhs, BinOp::Equal, rhs) = &expression.kind {
+            if same(lhs, rhs) {
+                cx.emit(&SELF_COMPARISON, expression.span, "Comparison of an expression with itself is always `true`");
+            }
+        }
+    }
+}
+
+static CONSTANT_CONDITION: Lint = Lint {
+    name: "constant_condition",
+    description: "`if` conditions that are `true` or `false`",
+    default: Level::Warn,
+};
+
+struct ConstantCondition;
+
+impl LintPass for ConstantCondition {
+    fn lint(&self) -> &'static Lint {
+        &CONSTANT_CONDITION
+    }
+
+    fn check_expression(&self, cx: &mut LintContext, expression: &Expression) {
+        if let ExpressionKind::If(if_expr) = &expression.kind {
+            for i in &if_expr.ifs {
+                if let ExpressionKind::Boolean(value) = i.condition.kind {
+                    let message = format!("Condition is always `{}`", value);
+                    cx.emit(&CONSTANT_CONDITION, i.condition.span, message);
+                }
+            }
+        }
+    }
+}
+
+static REDUNDANT_BLOCK: Lint = Lint {
+    name: "redundant_block",
+    description: "blocks that only contain an expression, e.g. `{ x }`",
+    default: Level::Warn,
+};
+
+struct RedundantBlock;
+
+impl LintPass for RedundantBlock {
+    fn lint(&self) -> &'static Lint {
+        &REDUNDANT_BLOCK
+    }
+
+    fn check_expression(&self, cx: &mut LintContext, expression: &Expression) {
+        if let ExpressionKind::Block(block) = &expression.kind {
+            if block.items.is_empty() && block.statements.is_empty() {
+                let message = match block.return_expr.kind {
+                    ExpressionKind::Unit => "Empty block, which is the same as `()`",
+                    _ => "Redundant block around an expression",
+                };
+
+                cx.emit(&REDUNDANT_BLOCK, expression.span, message);
+            }
+        }
+    }
+}
+
+static IDENTIFIER_SUFFIX: Lint =
+    Lint { name: "identifier_suffix", description: "names ending in `?`, `!`, `?!` or `⁈`", default: Level::Allow };
+
+struct IdentifierSuffix;
+
+impl IdentifierSuffix {
+    fn check(&self, cx: &mut LintContext, name: Identifier) {
+        let string = name.string();
+        if let (_, Some(suffix)) = split_suffix(&string) {
+            let message = format!("Identifier `{}` ends in `{}`", string, suffix);
+            cx.emit(&IDENTIFIER_SUFFIX, name.span, message);
+        }
+    }
+}
+
+impl LintPass for IdentifierSuffix {
+    fn lint(&self) -> &'static Lint {
+        &IDENTIFIER_SUFFIX
+    }
+
+    fn check_item(&self, cx: &mut LintContext, item: &Item) {
+        if let Some(name) = item_name(item) {
+            self.check(cx, name);
+        }
+    }
+
+    fn check_struct_member(&self, cx: &mut LintContext, member: &StructMember) {
+        self.check(cx, member.name);
+    }
+
+    fn check_function_parameter(&self, cx: &mut LintContext, parameter: &FunctionParameter) {
+        self.check(cx, parameter.name);
+    }
+
+    fn check_local(&self, cx: &mut LintContext, local: &Local) {
+        self.check(cx, local.name);
+    }
+}
+
+static DEAD_CODE: Lint =
+    Lint { name: "dead_code", description: "private functions that are never used", default: Level::Warn };
+
+struct DeadCode;
+
+impl LintPass for DeadCode {
+    fn lint(&self) -> &'static Lint {
+        &DEAD_CODE
+    }
+
+    fn check_item(&self, cx: &mut LintContext, item: &Item) {
+        if let ItemKind::Function(function) = &item.kind {
+            let def = cx.resolver().def(item.def_id());
+            if unused::can_be_dead(function, &def.path) && !cx.usage.referenced.contains(&item.def_id()) {
+                let message = format!("Function `{}` is never used", def.path);
+                cx.emit(&DEAD_CODE, function.name.span, message);
+            }
+        }
+    }
+}
+
+static UNUSED_IMPORTS: Lint =
+    Lint { name: "unused_imports", description: "imports that are never used", default: Level::Warn };
+
+struct UnusedImports;
+
+impl LintPass for UnusedImports {
+    fn lint(&self) -> &'static Lint {
+        &UNUSED_IMPORTS
+    }
+
+    fn check_item(&self, cx: &mut LintContext, item: &Item) {
+        // Imports at the top level of the REPL can still be used by later
+        // inputs
+        if let (ItemKind::Use(usage), false) = (&item.kind, cx.scope.segments.is_empty()) {
+            for import in usage.imports() {
+                if !cx.resolver().is_import_used(&cx.scope, &import) {
+                    let message = match import.kind {
+                        ImportKind::Glob => format!("Unused import `{}::*`", import.path),
+                        _ => format!("Unused import `{}`", import.path),
+                    };
+
+                    cx.emit(&UNUSED_IMPORTS, import.span, message);
+                }
+            }
+        }
+    }
+}
+
+static UNUSED_MUT: Lint =
+    Lint { name: "unused_mut", description: "`mut` on locals that are never assigned to", default: Level::Warn };
+
+struct UnusedMut;
+
+impl LintPass for UnusedMut {
+    fn lint(&self) -> &'static Lint {
+        &UNUSED_MUT
+    }
+
+    fn check_local(&self, cx: &mut LintContext, local: &Local) {
+        let id = local.local_id();
+        if local.mutable && !is_top_level(cx, local.name, id) && !cx.usage.assigned.contains(&id) {
+            let message = format!("Local `{}` is never assigned to, so doesn't need `mut`", local.name);
+            cx.emit(&UNUSED_MUT, local.name.span, message);
+        }
+    }
+}
+
+static UNUSED_VARIABLES: Lint = Lint {
+    name: "unused_variables",
+    description: "locals and parameters that are never used, unless their name starts with `_`",
+    default: Level::Warn,
+};
+
+struct UnusedVariables;
+
+/// Whether the local `id` called `name` is bound at the top level of the
+/// REPL, where later inputs can still use it
+fn is_top_level(cx: &LintContext, name: Identifier, id: LocalId) -> bool {
+    cx.resolver().top_level_local(name) == Some(id)
+}
+
+impl UnusedVariables {
+    fn is_unused(&self, cx: &LintContext, name: Identifier, id: LocalId) -> bool {
+        !name.string().starts_with('_') && !cx.usage.read.contains(&id)
+    }
+}
+
+impl LintPass for UnusedVariables {
+    fn lint(&self) -> &'static Lint {
+        &UNUSED_VARIABLES
+    }
+
+    fn check_function_parameter(&self, cx: &mut LintContext, parameter: &FunctionParameter) {
+        if self.is_unused(cx, parameter.name, parameter.local_id()) {
+            let message = format!("Unused parameter `{}`", parameter.name);
+            cx.emit(&UNUSED_VARIABLES, parameter.name.span, message);
+        }
+    }
+
+    fn check_local(&self, cx: &mut LintContext, local: &Local) {
+        let id = local.local_id();
+        if !is_top_level(cx, local.name, id) && self.is_unused(cx, local.name, id) {
+            let message = format!("Unused local `{}`", local.name);
+            cx.emit(&UNUSED_VARIABLES, local.name.span, message);
+        }
+    }
+}
+
+pub(crate) static UNKNOWN_LINTS: Lint = Lint {
+    name: "unknown_lints",
+    description: "names in `#[allow(...)]`, `#[warn(...)]` and `#[deny(...)]` that aren't lints",
+    default: Level::Warn,
+};
+
+/// Reported while the levels set by attributes are read, rather than by
+/// checking any node
+struct UnknownLints;
+
+impl LintPass for UnknownLints {
+    fn lint(&self) -> &'static Lint {
+        &UNKNOWN_LINTS
+    }
+}
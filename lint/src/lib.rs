@@ -0,0 +1,605 @@
+//! Style and correctness lints over resolved HIR. Each lint has a level that
+//! can be set for every input, for a geode, or for an item and everything
+//! within it with `#[allow(...)]`, `#[warn(...)]` and `#[deny(...)]`.
+
+mod builtin;
+mod unused;
+
+use codespan::{FileId, Span};
+use hir::visit::{walk, Visitor};
+use hir::{
+    resolve::Resolver, AttributeArg, Block, Expression, FunctionParameter, Item, ItemKind, Local, Module, Path,
+    Statement, StructMember,
+};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Level {
+    /// The level set by the attribute `name`, if it sets one
+    fn of_attribute(name: &str) -> Option<Self> {
+        name.parse().ok()
+    }
+}
+
+impl FromStr for Level {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(Level::Allow),
+            "warn" => Ok(Level::Warn),
+            "deny" => Ok(Level::Deny),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Allow => write!(f, "allow"),
+            Level::Warn => write!(f, "warn"),
+            Level::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Lint {
+    /// The name used to refer to the lint in attributes and configuration
+    pub name: &'static str,
+    pub description: &'static str,
+    pub default: Level,
+}
+
+/// The checks making up a lint. Each is called for the matching node of the
+/// HIR being linted, in the order a [`Visitor`] would visit them, and reports
+/// what it finds through the [`LintContext`].
+pub trait LintPass: Sync {
+    fn lint(&self) -> &'static Lint;
+
+    fn check_item(&self, _: &mut LintContext, _: &Item) {}
+
+    fn check_struct_member(&self, _: &mut LintContext, _: &StructMember) {}
+
+    fn check_function_parameter(&self, _: &mut LintContext, _: &FunctionParameter) {}
+
+    fn check_local(&self, _: &mut LintContext, _: &Local) {}
+
+    fn check_expression(&self, _: &mut LintContext, _: &Expression) {}
+}
+
+/// Where the level of a lint was set, with the name of the lint or group of
+/// lints as it was written there
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LevelSource {
+    /// The lint's default level
+    Default,
+    /// `--lint name=level`, which sets the level for every input
+    CommandLine(String),
+    /// The `[lints]` table in the manifest of the geode `geode`
+    Manifest { geode: String, name: String },
+    /// `#[level(name)]` on the item or member the lint found something in,
+    /// or on one that contains it
+    Attribute(String),
+}
+
+/// Something a lint found, at the level the lint was set to where it was
+/// found
+#[derive(Clone, Debug)]
+pub struct LintDiagnostic {
+    pub lint: &'static str,
+    pub level: Level,
+    /// Where `level` was set
+    pub source: LevelSource,
+    pub message: String,
+    pub file: Option<FileId>,
+    pub span: Span,
+}
+
+impl LintDiagnostic {
+    pub fn is_error(&self) -> bool {
+        self.level == Level::Deny
+    }
+}
+
+#[derive(Debug)]
+pub enum LintConfigError {
+    UnknownLint(String),
+    UnknownLevel(String),
+    /// A setting from the command line that isn't of the form `name=level`
+    Malformed(String),
+}
+
+impl Display for LintConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LintConfigError::UnknownLint(name) => write!(f, "Unknown lint `{}`", name),
+            LintConfigError::UnknownLevel(level) => {
+                write!(f, "Unknown lint level `{}`, expected one of `allow`, `warn` or `deny`", level)
+            }
+            LintConfigError::Malformed(setting) => {
+                write!(f, "Invalid lint setting `{}`, expected `name=level`, e.g. `non_snake_case=deny`", setting)
+            }
+        }
+    }
+}
+
+/// The lints the compiler knows about, and the levels they're set to outside
+/// of any item that sets them itself
+#[derive(Clone)]
+pub struct LintRegistry {
+    passes: Vec<&'static dyn LintPass>,
+    /// Levels set for every input, overriding each lint's default
+    levels: HashMap<&'static str, (Level, LevelSource)>,
+    /// Levels set for the geode whose root module has the given name
+    geode_levels: HashMap<String, HashMap<&'static str, (Level, LevelSource)>>,
+}
+
+impl LintRegistry {
+    pub fn new() -> Self {
+        let mut registry = Self { passes: Vec::new(), levels: HashMap::new(), geode_levels: HashMap::new() };
+        for &pass in builtin::PASSES {
+            registry.register(pass);
+        }
+
+        registry
+    }
+
+    pub fn register(&mut self, pass: &'static dyn LintPass) {
+        self.passes.push(pass);
+    }
+
+    /// Every known lint, in the order they were registered
+    pub fn lints(&self) -> impl Iterator<Item = &'static Lint> + '_ {
+        self.passes.iter().map(|pass| pass.lint())
+    }
+
+    /// The level of `lint` outside of any item or geode that sets it
+    pub fn level(&self, lint: &Lint) -> Level {
+        self.level_and_source(lint).0
+    }
+
+    fn level_and_source(&self, lint: &Lint) -> (Level, LevelSource) {
+        match self.levels.get(lint.name) {
+            Some(level) => level.clone(),
+            None => (lint.default, LevelSource::Default),
+        }
+    }
+
+    /// The lints called `name`, which is either the name of a lint or of a
+    /// group of them, such as `unused`
+    fn lints_named(&self, name: &str) -> Vec<&'static Lint> {
+        match builtin::GROUPS.iter().find(|(group, _)| *group == name) {
+            Some((_, lints)) => lints.to_vec(),
+            None => self.lints().filter(|lint| lint.name == name).collect(),
+        }
+    }
+
+    /// Sets the level of the lint or group of lints `name` for every input,
+    /// as `--lint` does
+    pub fn set_level(&mut self, name: &str, level: &str) -> Result<(), LintConfigError> {
+        let (lints, level) = self.parse_setting(name, level)?;
+        let source = LevelSource::CommandLine(name.to_string());
+        self.levels.extend(lints.into_iter().map(|lint| (lint.name, (level, source.clone()))));
+        Ok(())
+    }
+
+    /// Sets the level of a lint from a setting of the form `name=level`
+    pub fn apply_setting(&mut self, setting: &str) -> Result<(), LintConfigError> {
+        let mut parts = setting.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(name), Some(level)) => self.set_level(name.trim(), level.trim()),
+            _ => Err(LintConfigError::Malformed(setting.to_string())),
+        }
+    }
+
+    /// Sets the levels of lints within the geode `geode`, replacing any set
+    /// before
+    pub fn set_geode_levels<'a>(
+        &mut self,
+        geode: &str,
+        levels: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<(), LintConfigError> {
+        let mut parsed = HashMap::new();
+        for (name, level) in levels {
+            let (lints, level) = self.parse_setting(name, level)?;
+            let source = LevelSource::Manifest { geode: geode.to_string(), name: name.to_string() };
+            parsed.extend(lints.into_iter().map(|lint| (lint.name, (level, source.clone()))));
+        }
+
+        self.geode_levels.insert(geode.to_string(), parsed);
+        Ok(())
+    }
+
+    /// The lints named by `name` and the level to set them to
+    fn parse_setting(&self, name: &str, level: &str) -> Result<(Vec<&'static Lint>, Level), LintConfigError> {
+        let lints = self.lints_named(name);
+        if lints.is_empty() {
+            return Err(LintConfigError::UnknownLint(name.to_string()));
+        }
+
+        let level = level.parse().map_err(|_| LintConfigError::UnknownLevel(level.to_string()))?;
+        Ok((lints, level))
+    }
+
+    /// Lints `item` and everything within it, which was defined at the root
+    /// of `file` and resolved by `resolver`
+    pub fn check_item(&self, item: &Item, resolver: &Resolver, file: FileId) -> Vec<LintDiagnostic> {
+        let usage = unused::Usage::of(resolver, |collector| collector.visit_item(item));
+        self.check(resolver, usage, file, |linter| linter.visit_item(item))
+    }
+
+    /// Lints a statement entered at the top level of `file`
+    pub fn check_statement(&self, statement: &Statement, resolver: &Resolver, file: FileId) -> Vec<LintDiagnostic> {
+        let usage = unused::Usage::of(resolver, |collector| collector.visit_statement(statement));
+        self.check(resolver, usage, file, |linter| linter.visit_statement(statement))
+    }
+
+    /// Lints an expression entered at the top level of `file`
+    pub fn check_expression(&self, expression: &Expression, resolver: &Resolver, file: FileId) -> Vec<LintDiagnostic> {
+        let usage = unused::Usage::of(resolver, |collector| collector.visit_expression(expression));
+        self.check(resolver, usage, file, |linter| linter.visit_expression(expression))
+    }
+
+    fn check(
+        &self,
+        resolver: &Resolver,
+        usage: unused::Usage,
+        file: FileId,
+        f: impl FnOnce(&mut Linter<'_>),
+    ) -> Vec<LintDiagnostic> {
+        let cx = LintContext {
+            registry: self,
+            resolver,
+            usage,
+            scope: Path::new(),
+            levels: Vec::new(),
+            file: Some(file),
+            diagnostics: Vec::new(),
+        };
+        let mut linter = Linter { cx, depth: 0 };
+        f(&mut linter);
+
+        linter.cx.diagnostics
+    }
+}
+
+impl Default for LintRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where lints report what they find, which knows the level each lint is set
+/// to at the node being checked
+pub struct LintContext<'a> {
+    registry: &'a LintRegistry,
+    resolver: &'a Resolver,
+    usage: unused::Usage,
+    /// The module, function or block the node being checked is in
+    scope: Path,
+    /// Levels set by the geodes and items being visited, innermost last
+    levels: Vec<(&'static str, Level, LevelSource)>,
+    file: Option<FileId>,
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+impl LintContext<'_> {
+    pub fn resolver(&self) -> &Resolver {
+        self.resolver
+    }
+
+    pub fn level(&self, lint: &Lint) -> Level {
+        self.level_and_source(lint).0
+    }
+
+    fn level_and_source(&self, lint: &Lint) -> (Level, LevelSource) {
+        match self.levels.iter().rev().find(|(name, ..)| *name == lint.name) {
+            Some((_, level, source)) => (*level, source.clone()),
+            None => self.registry.level_and_source(lint),
+        }
+    }
+
+    /// Reports `message` about the code at `span` for `lint`, unless it's
+    /// allowed here
+    pub fn emit(&mut self, lint: &'static Lint, span: Span, message: impl Into<String>) {
+        let (level, source) = self.level_and_source(lint);
+        if level != Level::Allow {
+            let (message, file) = (message.into(), self.file);
+            self.diagnostics.push(LintDiagnostic { lint: lint.name, level, source, message, file, span });
+        }
+    }
+
+    /// Sets the levels given by the lint attributes in `attributes`, returning
+    /// how many levels were set before, to be restored afterwards
+    fn push_attributes(&mut self, attributes: &[hir::Attribute]) -> usize {
+        let outer = self.levels.len();
+
+        for attribute in attributes {
+            let level = match Level::of_attribute(&attribute.name.string()) {
+                Some(level) => level,
+                None => continue,
+            };
+
+            for arg in attribute.args.iter().flatten() {
+                let (path, span) = match arg {
+                    AttributeArg::Path(path, span) => (path, *span),
+                    AttributeArg::Integer(..) => continue,
+                };
+
+                let lints = path.is_identifier().map(|name| self.registry.lints_named(&name.string()));
+                match lints.unwrap_or_default() {
+                    lints if lints.is_empty() => {
+                        self.emit(&builtin::UNKNOWN_LINTS, span, format!("Unknown lint `{}`", path));
+                    }
+                    lints => {
+                        let source = LevelSource::Attribute(path.to_string());
+                        self.levels.extend(lints.into_iter().map(|lint| (lint.name, level, source.clone())));
+                    }
+                }
+            }
+        }
+
+        outer
+    }
+}
+
+/// Walks the HIR being linted, calling every lint pass on each node and
+/// keeping track of the levels and file in effect
+struct Linter<'a> {
+    cx: LintContext<'a>,
+    /// How many modules deep the item being visited is
+    depth: usize,
+}
+
+impl Visitor for Linter<'_> {
+    fn visit_item(&mut self, item: &Item) {
+        let outer = self.cx.levels.len();
+
+        // The root module of a geode takes the geode's levels
+        if let (ItemKind::Module(module), 0) = (&item.kind, self.depth) {
+            if let Some(levels) = self.cx.registry.geode_levels.get(&module.name.string()) {
+                self.cx.levels.extend(levels.iter().map(|(&name, (level, source))| (name, *level, source.clone())));
+            }
+        }
+
+        self.cx.push_attributes(item.attributes());
+        let registry = self.cx.registry;
+        for pass in &registry.passes {
+            pass.check_item(&mut self.cx, item);
+        }
+
+        match &item.kind {
+            ItemKind::Module(_) | ItemKind::Function(_) => {
+                let path = self.cx.resolver.def(item.def_id()).path.clone();
+                let scope = std::mem::replace(&mut self.cx.scope, path);
+                walk::item(self, item);
+                self.cx.scope = scope;
+            }
+            _ => walk::item(self, item),
+        }
+
+        self.cx.levels.truncate(outer);
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        let path = self.cx.resolver.def(block.scope_id()).path.clone();
+        let scope = std::mem::replace(&mut self.cx.scope, path);
+        walk::block(self, block);
+        self.cx.scope = scope;
+    }
+
+    fn visit_module(&mut self, module: &Module) {
+        let file = self.cx.file;
+        self.cx.file = module.file.or(file);
+        self.depth += 1;
+        walk::module(self, module);
+        self.depth -= 1;
+        self.cx.file = file;
+    }
+
+    fn visit_struct_member(&mut self, member: &StructMember) {
+        let outer = self.cx.push_attributes(&member.attributes);
+        let registry = self.cx.registry;
+        for pass in &registry.passes {
+            pass.check_struct_member(&mut self.cx, member);
+        }
+
+        walk::struct_member(self, member);
+        self.cx.levels.truncate(outer);
+    }
+
+    fn visit_function_parameter(&mut self, parameter: &FunctionParameter) {
+        let registry = self.cx.registry;
+        for pass in &registry.passes {
+            pass.check_function_parameter(&mut self.cx, parameter);
+        }
+
+        walk::function_parameter(self, parameter);
+    }
+
+    fn visit_local(&mut self, local: &Local) {
+        let registry = self.cx.registry;
+        for pass in &registry.passes {
+            pass.check_local(&mut self.cx, local);
+        }
+
+        walk::local(self, local);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        let outer = self.cx.push_attributes(&expression.attributes);
+        let registry = self.cx.registry;
+        for pass in &registry.passes {
+            pass.check_expression(&mut self.cx, expression);
+        }
+
+        walk::expression(self, expression);
+        self.cx.levels.truncate(outer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codespan::Files;
+
+    /// Lints inputs one at a time, the way the REPL does
+    struct Session {
+        registry: LintRegistry,
+        resolver: Resolver,
+        files: Files<String>,
+    }
+
+    impl Session {
+        fn new() -> Self {
+            Self { registry: LintRegistry::new(), resolver: Resolver::new(), files: Files::new() }
+        }
+
+        /// Lints the item `source`, returning what each lint found and the
+        /// level it was found at
+        fn item(&mut self, source: &str) -> Vec<(&'static str, Level)> {
+            let diagnostics = self.diagnostics(source);
+            diagnostics.iter().map(|diagnostic| (diagnostic.lint, diagnostic.level)).collect()
+        }
+
+        /// Lints the item `source`, returning where the level of each lint
+        /// that found something was set
+        fn sources(&mut self, source: &str) -> Vec<LevelSource> {
+            self.diagnostics(source).into_iter().map(|diagnostic| diagnostic.source).collect()
+        }
+
+        fn diagnostics(&mut self, source: &str) -> Vec<LintDiagnostic> {
+            let file = self.files.add("item", source.to_string());
+            let mut item = Item::convert(&parser::Parser::new(source).item().unwrap());
+            self.resolver.resolve_item(&mut item, file).unwrap();
+
+            self.registry.check_item(&item, &self.resolver, file)
+        }
+
+        fn statement(&mut self, source: &str) -> Vec<(&'static str, Level)> {
+            let file = self.files.add("statement", source.to_string());
+            let mut statement = Statement::convert(&parser::Parser::new(source).statement().unwrap());
+            self.resolver.resolve_statement(&mut statement, file).unwrap();
+
+            let diagnostics = self.registry.check_statement(&statement, &self.resolver, file);
+            diagnostics.iter().map(|diagnostic| (diagnostic.lint, diagnostic.level)).collect()
+        }
+    }
+
+    #[test]
+    fn unused_lints_are_registered() {
+        let registry = LintRegistry::new();
+        let names: Vec<_> = registry.lints().map(|lint| lint.name).collect();
+
+        for name in ["dead_code", "unused_imports", "unused_mut", "unused_variables"] {
+            assert!(names.contains(&name), "`{}` isn't registered", name);
+        }
+    }
+
+    #[test]
+    fn unused_lints_find_what_is_never_used() {
+        let mut session = Session::new();
+        let found = session.item(
+            "module m { fn helper() {} fn f(x: Int) -> Int { let mut y = 1; let _z = 2; let w = 3; w } pub fn g() -> Int { f(1) } }",
+        );
+
+        assert_eq!(
+            found,
+            [
+                ("dead_code", Level::Warn),
+                ("unused_variables", Level::Warn),
+                ("unused_mut", Level::Warn),
+                ("unused_variables", Level::Warn),
+            ]
+        );
+
+        assert_eq!(session.item("module n { use super::m::g; pub fn h() {} }"), [("unused_imports", Level::Warn)]);
+    }
+
+    #[test]
+    fn functions_only_used_by_dead_code_are_dead() {
+        let mut session = Session::new();
+        let found = session.item("module m { fn a() { b() } fn b() { a() } fn c() { c() } }");
+        assert_eq!(found, [("dead_code", Level::Warn); 3]);
+
+        let found = session.item("module n { pub fn f() { a() } fn a() { b() } fn b() { a() } }");
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn top_level_locals_can_be_used_later() {
+        let mut session = Session::new();
+        assert!(session.statement("let mut x = { let y = 1; 2 };").iter().eq(&[("unused_variables", Level::Warn)]));
+    }
+
+    #[test]
+    fn unused_lint_levels_are_configurable() {
+        let mut session = Session::new();
+        session.registry.set_level("dead_code", "deny").unwrap();
+        assert_eq!(session.item("module a { fn f() {} }"), [("dead_code", Level::Deny)]);
+
+        session.registry.set_level("unused", "allow").unwrap();
+        assert!(session.item("module b { fn f(x: Int) {} }").is_empty());
+
+        session.registry.set_geode_levels("c", vec![("unused_variables", "deny")]).unwrap();
+        assert_eq!(session.item("module c { pub fn f(x: Int) {} }"), [("unused_variables", Level::Deny)]);
+    }
+
+    #[test]
+    fn unknown_lints_in_attributes_are_reported() {
+        let mut session = Session::new();
+        assert_eq!(session.item("#[allow(dead_cod, unused)] fn f() {}"), [("unknown_lints", Level::Warn)]);
+        assert!(session.item("#[allow(unknown_lints, dead_cod)] fn g() {}").is_empty());
+    }
+
+    #[test]
+    fn attributes_set_unused_lint_levels() {
+        let mut session = Session::new();
+        assert_eq!(session.item("module a { #[deny(dead_code)] fn f() {} }"), [("dead_code", Level::Deny)]);
+        assert!(session.item("module b { #[allow(unused)] fn f(x: Int) { let mut y = 1; } }").is_empty());
+    }
+
+    #[test]
+    fn attributes_on_expressions_set_levels_within_them() {
+        let mut session = Session::new();
+        let found =
+            session.item("module a { pub fn f(x: Int) -> Int { #[allow(unused_variables)] { let y = 1; x } } }");
+        assert!(found.is_empty());
+
+        let found = session.item("module b { pub fn f(x: Int) -> Int { #[deny(self_comparison)] (x == x); x == x } }");
+        assert_eq!(found, [("self_comparison", Level::Deny), ("self_comparison", Level::Warn)]);
+
+        assert_eq!(
+            session.item("module c { pub fn f() { #[allow(dead_cod)] f() } }"),
+            [("unknown_lints", Level::Warn)]
+        );
+    }
+
+    #[test]
+    fn diagnostics_record_where_levels_were_set() {
+        let mut session = Session::new();
+        assert_eq!(session.sources("module a { fn f() {} }"), [LevelSource::Default]);
+
+        session.registry.set_level("unused", "deny").unwrap();
+        assert_eq!(session.sources("module b { fn f() {} }"), [LevelSource::CommandLine("unused".to_string())]);
+
+        session.registry.set_geode_levels("c", vec![("dead_code", "warn")]).unwrap();
+        let manifest = LevelSource::Manifest { geode: "c".to_string(), name: "dead_code".to_string() };
+        assert_eq!(session.sources("module c { fn f() {} }"), [manifest]);
+
+        let found = session.sources("module d { #![allow(unused)] #[warn(dead_code)] fn f() {} }");
+        assert_eq!(found, [LevelSource::Attribute("dead_code".to_string())]);
+    }
+}
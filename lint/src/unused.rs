@@ -0,0 +1,151 @@
+//! What's used within the input being linted, found before any lint pass
+//! runs, since a local or function can be used after it's declared. Nothing
+//! outside of the input can refer to what's declared within it, apart from
+//! the items and locals at the top level of the REPL, which the lints that
+//! use this leave alone.
+
+use hir::visit::{walk, Visitor};
+use hir::{
+    resolve::Resolver, DefId, Expression, ExpressionKind, Function, Item, ItemKind, LocalId, Path, Res, UnaryOp,
+    VisibilityKind,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Whether the function `function` at `path` is only used if something
+/// reachable refers to it. Public functions, functions at the top level of
+/// the REPL, which later inputs can still use, and tests are always used.
+pub(crate) fn can_be_dead(function: &Function, path: &Path) -> bool {
+    let is_test = function.attributes.iter().any(|attribute| attribute.name.string() == "test");
+    function.visibility.kind == VisibilityKind::Private && path.segments.len() > 1 && !is_test
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Usage {
+    /// Locals whose value is read
+    pub(crate) read: HashSet<LocalId>,
+    /// Locals that are assigned to, or have a field or what's in a box they
+    /// hold assigned to
+    pub(crate) assigned: HashSet<LocalId>,
+    /// Items referred to from code that's used, other than from within
+    /// themselves
+    pub(crate) referenced: HashSet<DefId>,
+}
+
+impl Usage {
+    /// Finds what's used within the code visited by `f`, which was resolved
+    /// by `resolver`
+    pub(crate) fn of(resolver: &Resolver, f: impl FnOnce(&mut Collector)) -> Self {
+        let mut collector = Collector {
+            resolver,
+            usage: Usage::default(),
+            functions: Vec::new(),
+            references: HashMap::new(),
+            roots: Vec::new(),
+        };
+        f(&mut collector);
+
+        // Functions that can be dead are only used if something used refers
+        // to them, so functions that only refer to each other aren't
+        let mut stack = collector.roots;
+        while let Some(def) = stack.pop() {
+            if collector.usage.referenced.insert(def) {
+                stack.extend(collector.references.remove(&def).into_iter().flatten());
+            }
+        }
+
+        collector.usage
+    }
+}
+
+pub(crate) struct Collector<'a> {
+    resolver: &'a Resolver,
+    usage: Usage,
+    /// The functions being visited, innermost last
+    functions: Vec<DefId>,
+    /// The items referred to from within each function that can be dead
+    references: HashMap<DefId, Vec<DefId>>,
+    /// The items referred to from code that's always used
+    roots: Vec<DefId>,
+}
+
+/// The local that the place `place` is part of, if it's in one, e.g. `s` for
+/// `(*s.b).c`
+fn place_local(place: &Expression) -> Option<LocalId> {
+    match &place.kind {
+        ExpressionKind::Path(path) => match path.resolution() {
+            Res::Local(id) => Some(id),
+            Res::Def(_) => None,
+        },
+        ExpressionKind::FieldAccess(place, _) | ExpressionKind::Unary(UnaryOp::Deref, place) => place_local(place),
+        _ => None,
+    }
+}
+
+impl Collector<'_> {
+    /// Records that `def` is referred to from the code being visited
+    fn refer(&mut self, def: DefId) {
+        match self.functions.last() {
+            // Functions don't use themselves by calling themselves
+            Some(&function) if function == def => {}
+            Some(&function) if self.references.contains_key(&function) => {
+                self.references.get_mut(&function).unwrap().push(def);
+            }
+            _ => self.roots.push(def),
+        }
+    }
+}
+
+impl Visitor for Collector<'_> {
+    fn visit_item(&mut self, item: &Item) {
+        match &item.kind {
+            ItemKind::Function(function) => {
+                if can_be_dead(function, &self.resolver.def(item.def_id()).path) {
+                    self.references.insert(item.def_id(), Vec::new());
+                }
+
+                self.functions.push(item.def_id());
+                walk::item(self, item);
+                self.functions.pop();
+            }
+            _ => walk::item(self, item),
+        }
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match &expression.kind {
+            // Assigning to a local doesn't use its value
+            ExpressionKind::Assignment(place, value) => match &place.kind {
+                ExpressionKind::Path(path) => {
+                    match path.resolution() {
+                        Res::Local(id) => {
+                            self.usage.assigned.insert(id);
+                        }
+                        Res::Def(def) => self.refer(def),
+                    }
+
+                    self.visit_expression(value);
+                }
+                _ => {
+                    // Assigning to a field or through a box needs the local
+                    // they're in to be `mut`
+                    if let Some(id) = place_local(place) {
+                        self.usage.assigned.insert(id);
+                    }
+
+                    walk::expression(self, expression);
+                }
+            },
+            _ => walk::expression(self, expression),
+        }
+    }
+
+    fn visit_path(&mut self, path: &Path) {
+        match path.res {
+            Some(Res::Local(id)) => {
+                self.usage.read.insert(id);
+            }
+            Some(Res::Def(def)) => self.refer(def),
+            None => {}
+        }
+    }
+}
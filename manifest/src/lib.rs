@@ -0,0 +1,496 @@
+use ast::{Geode, Identifier};
+use codespan::{Files, Span};
+use parser::{LoadError, Loader, Parser};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+pub const MANIFEST_FILE: &str = "Geode.toml";
+
+/// The contents of a `Geode.toml` file
+///
+/// ```toml
+/// [geode]
+/// name = "app"
+/// version = "0.1.0"
+/// root = "main.bm"
+///
+/// [dependencies]
+/// util = { path = "../util" }
+///
+/// [lints]
+/// non_snake_case = "deny"
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub geode: Package,
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, Dependency>,
+    /// The levels of lints within the geode, checked when the geode is
+    /// evaluated
+    #[serde(default)]
+    pub lints: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    /// The root file of the geode, relative to the manifest
+    #[serde(default = "Package::default_root")]
+    pub root: PathBuf,
+}
+
+impl Package {
+    fn default_root() -> PathBuf {
+        PathBuf::from("main").with_extension(parser::FILE_EXTENSION)
+    }
+}
+
+/// A dependency on another geode. Only local geodes are supported, so `path`
+/// is the directory containing the dependency's manifest, relative to the
+/// depending manifest.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dependency {
+    pub path: PathBuf,
+}
+
+impl Manifest {
+    pub fn from_file(path: &Path) -> Result<Self, ManifestError> {
+        let source =
+            std::fs::read_to_string(path).map_err(|error| ManifestError::Io { path: path.to_path_buf(), error })?;
+        let manifest: Manifest =
+            toml::from_str(&source).map_err(|error| ManifestError::Toml { path: path.to_path_buf(), error })?;
+
+        if !is_valid_name(&manifest.geode.name) {
+            return Err(ManifestError::InvalidName { path: path.to_path_buf(), name: manifest.geode.name });
+        }
+
+        if !is_valid_version(&manifest.geode.version) {
+            return Err(ManifestError::InvalidVersion { path: path.to_path_buf(), version: manifest.geode.version });
+        }
+
+        Ok(manifest)
+    }
+}
+
+/// Geode names end up as the first segment of paths, so they must be valid
+/// identifiers that don't clash with the rooted path segments
+fn is_valid_name(name: &str) -> bool {
+    match Parser::new(name).identifier() {
+        Ok(ident) => ident.value == name && !["geode", "self", "super"].contains(&name),
+        Err(_) => false,
+    }
+}
+
+/// Versions are of the form `major.minor.patch`
+fn is_valid_version(version: &str) -> bool {
+    let parts: Vec<&str> = version.split('.').collect();
+
+    parts.len() == 3 && parts.iter().all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    Toml {
+        path: PathBuf,
+        error: toml::de::Error,
+    },
+    InvalidName {
+        path: PathBuf,
+        name: String,
+    },
+    InvalidVersion {
+        path: PathBuf,
+        version: String,
+    },
+    /// A dependency was declared under a different name than the one in its
+    /// manifest
+    NameMismatch {
+        path: PathBuf,
+        expected: String,
+        found: String,
+    },
+    /// Two different geodes in the graph share a name
+    DuplicateName {
+        name: String,
+        first: PathBuf,
+        second: PathBuf,
+    },
+    /// The names of the geodes forming a dependency cycle, starting and
+    /// ending with the same geode
+    Cycle(Vec<String>),
+    Load(LoadError),
+}
+
+impl Display for ManifestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::Io { path, error } => write!(f, "Error reading `{}`: {}", path.display(), error),
+            ManifestError::Toml { path, error } => write!(f, "Error parsing `{}`: {}", path.display(), error),
+            ManifestError::InvalidName { path, name } => {
+                write!(f, "Invalid geode name `{}` in `{}`: names must be identifiers", name, path.display())
+            }
+            ManifestError::InvalidVersion { path, version } => write!(
+                f,
+                "Invalid geode version `{}` in `{}`: versions must be of the form `major.minor.patch`",
+                version,
+                path.display()
+            ),
+            ManifestError::NameMismatch { path, expected, found } => {
+                write!(f, "Dependency `{}` declared in `{}` is a geode named `{}`", expected, path.display(), found)
+            }
+            ManifestError::DuplicateName { name, first, second } => {
+                write!(f, "More than one geode is named `{}`: `{}` and `{}`", name, first.display(), second.display())
+            }
+            ManifestError::Cycle(names) => write!(f, "Cyclic geode dependency: {}", names.join(" -> ")),
+            ManifestError::Load(error) => write!(f, "Error loading geode: {:?}", error),
+        }
+    }
+}
+
+/// A geode loaded from its manifest, with its name applied to its root module
+#[derive(Debug)]
+pub struct LoadedGeode {
+    pub manifest: Manifest,
+    /// The directory containing the manifest
+    pub dir: PathBuf,
+    pub geode: Geode,
+}
+
+impl LoadedGeode {
+    pub fn name(&self) -> &str {
+        &self.manifest.geode.name
+    }
+
+    pub fn dependencies(&self) -> impl Iterator<Item = &str> {
+        self.manifest.dependencies.keys().map(String::as_str)
+    }
+}
+
+/// A geode along with all of its transitive dependencies
+#[derive(Debug)]
+pub struct GeodeGraph {
+    /// Every geode in the graph, ordered so that each geode comes after all of
+    /// its dependencies. The root geode is always last.
+    pub geodes: Vec<LoadedGeode>,
+}
+
+impl GeodeGraph {
+    /// Loads the geode whose manifest is `path`, or is in the directory
+    /// `path`, along with its dependencies
+    pub fn load(files: &mut Files<String>, path: &Path) -> Result<Self, ManifestError> {
+        let dir = match path.file_name() == Some(MANIFEST_FILE.as_ref()) {
+            true => path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            false => path.to_path_buf(),
+        };
+
+        let mut builder = GraphBuilder { files, loaded: HashMap::new(), names: HashMap::new(), stack: Vec::new() };
+        let mut geodes = Vec::new();
+        builder.visit(&dir, &mut geodes)?;
+
+        Ok(Self { geodes })
+    }
+
+    pub fn root(&self) -> &LoadedGeode {
+        self.geodes.last().expect("graph always contains the root geode")
+    }
+}
+
+struct GraphBuilder<'a> {
+    files: &'a mut Files<String>,
+    /// Canonical manifest directories to their index in the graph
+    loaded: HashMap<PathBuf, usize>,
+    names: HashMap<String, PathBuf>,
+    /// The geodes currently being loaded, used to detect cycles
+    stack: Vec<(PathBuf, String)>,
+}
+
+impl GraphBuilder<'_> {
+    fn visit(&mut self, dir: &Path, geodes: &mut Vec<LoadedGeode>) -> Result<usize, ManifestError> {
+        let canonical = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+
+        if let Some(&idx) = self.loaded.get(&canonical) {
+            return Ok(idx);
+        }
+
+        let manifest_path = dir.join(MANIFEST_FILE);
+        let manifest = Manifest::from_file(&manifest_path)?;
+        let name = manifest.geode.name.clone();
+
+        if let Some(pos) = self.stack.iter().position(|(path, _)| *path == canonical) {
+            let mut names: Vec<String> = self.stack[pos..].iter().map(|(_, name)| name.clone()).collect();
+            names.push(name);
+
+            return Err(ManifestError::Cycle(names));
+        }
+
+        if let Some(first) = self.names.get(&name) {
+            if *first != canonical {
+                return Err(ManifestError::DuplicateName { name, first: first.clone(), second: canonical });
+            }
+        }
+        self.names.insert(name.clone(), canonical.clone());
+
+        self.stack.push((canonical.clone(), name.clone()));
+        let res = self.visit_dependencies(dir, &manifest_path, &manifest, geodes);
+        self.stack.pop();
+        res?;
+
+        let mut geode =
+            Loader::new(self.files).load_geode(&dir.join(&manifest.geode.root)).map_err(ManifestError::Load)?;
+        geode.module.name = Identifier { value: name, span: Span::new(0, 0) };
+
+        geodes.push(LoadedGeode { manifest, dir: dir.to_path_buf(), geode });
+        self.loaded.insert(canonical, geodes.len() - 1);
+
+        Ok(geodes.len() - 1)
+    }
+
+    fn visit_dependencies(
+        &mut self,
+        dir: &Path,
+        manifest_path: &Path,
+        manifest: &Manifest,
+        geodes: &mut Vec<LoadedGeode>,
+    ) -> Result<(), ManifestError> {
+        for (name, dependency) in &manifest.dependencies {
+            let idx = self.visit(&dir.join(&dependency.path), geodes)?;
+            let found = geodes[idx].name();
+
+            if found != name {
+                return Err(ManifestError::NameMismatch {
+                    path: manifest_path.to_path_buf(),
+                    expected: name.clone(),
+                    found: found.to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory of geodes that's removed once the test is done
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("bismite-manifest-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+
+            Self(dir)
+        }
+
+        /// Writes a geode in the directory `path` with the given manifest and
+        /// an empty root file
+        fn geode(&self, path: &str, manifest: &str) -> PathBuf {
+            let dir = self.0.join(path);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join(MANIFEST_FILE), manifest).unwrap();
+            std::fs::write(dir.join(Package::default_root()), "").unwrap();
+
+            dir
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn load(path: &Path) -> Result<GeodeGraph, ManifestError> {
+        GeodeGraph::load(&mut Files::new(), path)
+    }
+
+    #[test]
+    fn dependencies_come_before_dependents() {
+        let dir = TempDir::new("order");
+        let root = dir.geode(
+            "app",
+            r#"
+            [geode]
+            name = "app"
+            version = "0.1.0"
+
+            [dependencies]
+            util = { path = "../util" }
+            log = { path = "../log" }
+            "#,
+        );
+        dir.geode(
+            "util",
+            r#"
+            [geode]
+            name = "util"
+            version = "1.2.3"
+
+            [dependencies]
+            log = { path = "../log" }
+            "#,
+        );
+        dir.geode("log", "[geode]\nname = \"log\"\nversion = \"0.0.1\"");
+
+        let graph = load(&root.join(MANIFEST_FILE)).unwrap();
+        let names: Vec<&str> = graph.geodes.iter().map(LoadedGeode::name).collect();
+        assert_eq!(names, ["log", "util", "app"]);
+        assert_eq!(graph.root().geode.module.name.value, "app");
+    }
+
+    #[test]
+    fn invalid_names_are_rejected() {
+        let dir = TempDir::new("name");
+
+        for name in &["", "1geode", "has-dash", "two words", "geode", "self", "super"] {
+            let root = dir.geode("app", &format!("[geode]\nname = {:?}\nversion = \"0.1.0\"", name));
+
+            match load(&root) {
+                Err(ManifestError::InvalidName { name: found, .. }) => assert_eq!(found, *name),
+                res => panic!("expected `{}` to be invalid, got {:?}", name, res.map(|_| ())),
+            }
+        }
+
+        assert!(is_valid_name("snake_case"));
+    }
+
+    #[test]
+    fn invalid_versions_are_rejected() {
+        let dir = TempDir::new("version");
+
+        for version in &["", "1", "1.0", "1.0.0.0", "1..0", "1.0.x", "v1.0.0", "1.0.0-beta"] {
+            let root = dir.geode("app", &format!("[geode]\nname = \"app\"\nversion = {:?}", version));
+
+            match load(&root) {
+                Err(ManifestError::InvalidVersion { version: found, .. }) => assert_eq!(found, *version),
+                res => panic!("expected `{}` to be invalid, got {:?}", version, res.map(|_| ())),
+            }
+        }
+
+        assert!(is_valid_version("10.20.30"));
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let dir = TempDir::new("toml");
+        let root = dir.geode("app", "[geode]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2018\"");
+
+        assert!(matches!(load(&root), Err(ManifestError::Toml { .. })));
+    }
+
+    #[test]
+    fn dependencies_must_match_their_name() {
+        let dir = TempDir::new("mismatch");
+        let root = dir.geode(
+            "app",
+            r#"
+            [geode]
+            name = "app"
+            version = "0.1.0"
+
+            [dependencies]
+            util = { path = "../util" }
+            "#,
+        );
+        dir.geode("util", "[geode]\nname = \"utils\"\nversion = \"0.1.0\"");
+
+        match load(&root) {
+            Err(ManifestError::NameMismatch { path, expected, found }) => {
+                assert_eq!(path, root.join(MANIFEST_FILE));
+                assert_eq!(expected, "util");
+                assert_eq!(found, "utils");
+            }
+            res => panic!("expected a name mismatch, got {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn different_geodes_cannot_share_a_name() {
+        let dir = TempDir::new("duplicate");
+        let root = dir.geode(
+            "app",
+            r#"
+            [geode]
+            name = "app"
+            version = "0.1.0"
+
+            [dependencies]
+            a = { path = "../a" }
+            b = { path = "../b" }
+            "#,
+        );
+        dir.geode("a", "[geode]\nname = \"a\"\nversion = \"0.1.0\"\n[dependencies]\nutil = { path = \"util\" }");
+        dir.geode("b", "[geode]\nname = \"b\"\nversion = \"0.1.0\"\n[dependencies]\nutil = { path = \"util\" }");
+        dir.geode("a/util", "[geode]\nname = \"util\"\nversion = \"0.1.0\"");
+        dir.geode("b/util", "[geode]\nname = \"util\"\nversion = \"0.1.0\"");
+
+        match load(&root) {
+            Err(ManifestError::DuplicateName { name, first, second }) => {
+                assert_eq!(name, "util");
+                assert_eq!(first, dir.0.join("a/util").canonicalize().unwrap());
+                assert_eq!(second, dir.0.join("b/util").canonicalize().unwrap());
+            }
+            res => panic!("expected a duplicate name, got {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn shared_dependencies_are_loaded_once() {
+        let dir = TempDir::new("shared");
+        let root = dir.geode(
+            "app",
+            r#"
+            [geode]
+            name = "app"
+            version = "0.1.0"
+
+            [dependencies]
+            a = { path = "../a" }
+            b = { path = "../b" }
+            "#,
+        );
+        dir.geode("a", "[geode]\nname = \"a\"\nversion = \"0.1.0\"\n[dependencies]\nutil = { path = \"../util\" }");
+        dir.geode("b", "[geode]\nname = \"b\"\nversion = \"0.1.0\"\n[dependencies]\nutil = { path = \"../util\" }");
+        dir.geode("util", "[geode]\nname = \"util\"\nversion = \"0.1.0\"");
+
+        assert_eq!(load(&root).unwrap().geodes.len(), 4);
+    }
+
+    #[test]
+    fn cycles_are_reported_from_where_they_start() {
+        let dir = TempDir::new("cycle");
+        let root =
+            dir.geode("app", "[geode]\nname = \"app\"\nversion = \"0.1.0\"\n[dependencies]\na = { path = \"../a\" }");
+        dir.geode("a", "[geode]\nname = \"a\"\nversion = \"0.1.0\"\n[dependencies]\nb = { path = \"../b\" }");
+        dir.geode("b", "[geode]\nname = \"b\"\nversion = \"0.1.0\"\n[dependencies]\na = { path = \"../a\" }");
+
+        match load(&root) {
+            Err(ManifestError::Cycle(names)) => assert_eq!(names, ["a", "b", "a"]),
+            res => panic!("expected a cycle, got {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn geodes_depending_on_themselves_are_cycles() {
+        let dir = TempDir::new("self-cycle");
+        let root =
+            dir.geode("app", "[geode]\nname = \"app\"\nversion = \"0.1.0\"\n[dependencies]\napp = { path = \".\" }");
+
+        assert!(matches!(load(&root), Err(ManifestError::Cycle(names)) if names == ["app", "app"]));
+    }
+}
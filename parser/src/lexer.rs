@@ -61,6 +61,10 @@ pub enum TokenKind {
     Star,
     #[token("/")]
     Slash,
+    #[token("<")]
+    LeftAngle,
+    #[token(">")]
+    RightAngle,
     #[token("==")]
     DoubleEq,
     #[token("=")]
@@ -69,8 +73,14 @@ pub enum TokenKind {
     Colon,
     #[token(".")]
     Period,
+    #[token("..")]
+    DotDot,
     #[token("::")]
     PathSep,
+    #[token("#")]
+    Pound,
+    #[token("!")]
+    Bang,
 
     // Keywords
     #[token("fn")]
@@ -91,6 +101,18 @@ pub enum TokenKind {
     Module,
     #[token("else")]
     Else,
+    #[token("pub")]
+    Pub,
+    #[token("as")]
+    As,
+    #[token("const")]
+    Const,
+    #[token("static")]
+    Static,
+    #[token("type")]
+    Type,
+    #[token("box")]
+    Box,
 
     // FIXME: Move to an enum when we can
     #[token("true")]
@@ -124,10 +146,7 @@ pub enum TokenKind {
 
 impl TokenKind {
     pub fn is_binop(&self) -> bool {
-        match self {
-            TokenKind::Plus | TokenKind::Minus | TokenKind::Star | TokenKind::Slash | TokenKind::DoubleEq => true,
-            _ => false,
-        }
+        matches!(self, TokenKind::Plus | TokenKind::Minus | TokenKind::Star | TokenKind::Slash | TokenKind::DoubleEq)
     }
 
     pub fn as_str(&self) -> &'static str {
@@ -147,10 +166,15 @@ impl TokenKind {
             Minus => "-",
             Star => "*",
             Slash => "/",
+            LeftAngle => "<",
+            RightAngle => ">",
             DoubleEq => "==",
             Eq => "=",
             Colon => ":",
             Period => ".",
+            DotDot => "..",
+            Pound => "#",
+            Bang => "!",
             Fn => "fn",
             If => "if",
             While => "while",
@@ -160,6 +184,12 @@ impl TokenKind {
             Struct => "struct",
             Module => "module",
             Else => "else",
+            Pub => "pub",
+            As => "as",
+            Const => "const",
+            Static => "static",
+            Type => "type",
+            Box => "box",
             True => "true",
             False => "false",
             Int => "Int",
@@ -1,8 +1,10 @@
 #![allow(clippy::match_bool)]
 
 mod lexer;
+mod loader;
 
 pub use lexer::{Token, TokenKind};
+pub use loader::{LoadError, Loader, FILE_EXTENSION};
 
 use ast::*;
 use codespan::Span;
@@ -23,6 +25,7 @@ pub enum ParseError {
     BadBinOp,
 }
 
+#[derive(Clone)]
 pub struct Parser<'a> {
     lexer: Lexer<'a, TokenKind>,
     peeks: VecDeque<Token>,
@@ -39,9 +42,15 @@ impl<'a> Parser<'a> {
         }
 
         match self.peek()?.kind {
-            TokenKind::Fn | TokenKind::Struct | TokenKind::Module | TokenKind::Use => {
-                Ok(Some(AstNode::Item(self.item()?)))
-            }
+            TokenKind::Fn
+            | TokenKind::Struct
+            | TokenKind::Module
+            | TokenKind::Use
+            | TokenKind::Pub
+            | TokenKind::Const
+            | TokenKind::Static
+            | TokenKind::Type => Ok(Some(AstNode::Item(self.item()?))),
+            TokenKind::Pound if self.attributes_precede_item()? => Ok(Some(AstNode::Item(self.item()?))),
             _ => match self.statement_or_expression()? {
                 Either::Left(stmt) => Ok(Some(AstNode::Statement(stmt))),
                 Either::Right(expr) => Ok(Some(AstNode::Expression(expr))),
@@ -57,23 +66,50 @@ impl<'a> Parser<'a> {
     }
 
     pub fn module(&mut self, implicit: bool) -> Result<Module> {
-        let (start_span, name) = match implicit {
+        let (start_span, mut attributes, visibility, name) = match implicit {
             true => match self.peek() {
-                Ok(token) => (token.span(), Identifier::dummy()),
+                Ok(token) => (token.span(), Vec::new(), Visibility::private(token.span()), Identifier::dummy()),
                 Err(ParseError::Eof) => {
-                    return Ok(Module { name: Identifier::dummy(), items: Vec::new(), span: Span::new(0, 0) })
+                    let span = Span::new(0, 0);
+                    return Ok(Module {
+                        attributes: Vec::new(),
+                        visibility: Visibility::private(span),
+                        name: Identifier::dummy(),
+                        items: Vec::new(),
+                        inline: true,
+                        file: None,
+                        span,
+                    });
                 }
                 Err(e) => return Err(e),
             },
             false => {
-                let start = self.eat(TokenKind::Module)?;
+                let (attributes, visibility, start) = self.item_prefix()?;
+                let start = start.merge(self.eat(TokenKind::Module)?);
                 let name = self.identifier()?;
+
+                // `module foo;`, whose items are loaded from another file
+                if self.peek()?.kind == TokenKind::Semicolon {
+                    let span = start.merge(self.eat(TokenKind::Semicolon)?);
+                    return Ok(Module {
+                        attributes,
+                        visibility,
+                        name,
+                        items: Vec::new(),
+                        inline: false,
+                        file: None,
+                        span,
+                    });
+                }
+
                 self.eat(TokenKind::LeftBrace)?;
 
-                (start, name)
+                (start, attributes, visibility, name)
             }
         };
 
+        attributes.extend(self.inner_attributes()?);
+
         let mut items = Vec::new();
 
         loop {
@@ -100,21 +136,159 @@ impl<'a> Parser<'a> {
 
         let span = start_span.merge(end_span);
 
-        Ok(Module { name, items, span })
+        Ok(Module { attributes, visibility, name, items, inline: true, file: None, span })
     }
 
     pub fn item(&mut self) -> Result<Item> {
-        match self.peek()?.kind {
+        let kind = match self.peek()?.kind {
+            TokenKind::Pub | TokenKind::Pound => self.peek_past_item_prefix()?.kind,
+            kind => kind,
+        };
+
+        match kind {
             TokenKind::Fn => Ok(Item::Function(self.function()?)),
             TokenKind::Struct => Ok(Item::Struct(self.r#struct()?)),
             TokenKind::Module => Ok(Item::Module(self.module(false)?)),
             TokenKind::Use => Ok(Item::Use(self.usage()?)),
-            _ => Err(ParseError::BadToken { got: self.peek()?, expected: vec!["fn", "struct", "module", "use"] }),
+            TokenKind::Const => Ok(Item::Const(self.r#const()?)),
+            TokenKind::Static => Ok(Item::Static(self.r#static()?)),
+            TokenKind::Type => Ok(Item::TypeAlias(self.type_alias()?)),
+            _ => Err(ParseError::BadToken {
+                got: self.peek()?,
+                expected: vec!["fn", "struct", "module", "use", "const", "static", "type"],
+            }),
+        }
+    }
+
+    pub fn visibility(&mut self) -> Result<Visibility> {
+        let token = self.peek()?;
+
+        if token.kind != TokenKind::Pub {
+            return Ok(Visibility::private(token.span()));
+        }
+
+        let start_span = self.eat(TokenKind::Pub)?;
+
+        if self.peek()?.kind != TokenKind::LeftParen {
+            return Ok(Visibility { kind: VisibilityKind::Public, span: start_span });
         }
+
+        self.eat(TokenKind::LeftParen)?;
+        let scope = self.identifier()?;
+
+        if scope.value != "geode" {
+            let got = Token::new(scope.span, TokenKind::Identifier(scope.value));
+            return Err(ParseError::BadToken { got, expected: vec!["geode"] });
+        }
+
+        let end_span = self.eat(TokenKind::RightParen)?;
+
+        Ok(Visibility { kind: VisibilityKind::Geode, span: start_span.merge(end_span) })
+    }
+
+    /// Returns the first token following any attributes and a `pub` or
+    /// `pub(geode)` qualifier without consuming anything
+    fn peek_past_item_prefix(&mut self) -> Result<Token> {
+        let mut lookahead = self.clone();
+
+        lookahead.attributes()?;
+        lookahead.visibility()?;
+        lookahead.peek()
+    }
+
+    /// Whether the attributes at the front of the input are on an item, as
+    /// opposed to an expression
+    fn attributes_precede_item(&mut self) -> Result<bool> {
+        Ok(matches!(
+            self.peek_past_item_prefix()?.kind,
+            TokenKind::Fn
+                | TokenKind::Struct
+                | TokenKind::Module
+                | TokenKind::Use
+                | TokenKind::Const
+                | TokenKind::Static
+                | TokenKind::Type
+        ))
+    }
+
+    /// Parses any number of outer attributes
+    pub fn attributes(&mut self) -> Result<Vec<Attribute>> {
+        let mut attributes = Vec::new();
+
+        while let Ok(Token { kind: TokenKind::Pound, .. }) = self.peek() {
+            attributes.push(self.attribute(AttributeStyle::Outer)?);
+        }
+
+        Ok(attributes)
+    }
+
+    /// Parses any number of inner attributes, which may only appear at the
+    /// start of a module
+    pub fn inner_attributes(&mut self) -> Result<Vec<Attribute>> {
+        let mut attributes = Vec::new();
+
+        while let (Ok(TokenKind::Pound), Ok(TokenKind::Bang)) =
+            (self.peek().map(|t| t.kind), self.peek2().map(|t| t.kind))
+        {
+            attributes.push(self.attribute(AttributeStyle::Inner)?);
+        }
+
+        Ok(attributes)
+    }
+
+    pub fn attribute(&mut self, style: AttributeStyle) -> Result<Attribute> {
+        let start_span = self.eat(TokenKind::Pound)?;
+        if style == AttributeStyle::Inner {
+            self.eat(TokenKind::Bang)?;
+        }
+
+        self.eat(TokenKind::LeftBracket)?;
+        let name = self.identifier()?;
+
+        let args = if self.peek()?.kind == TokenKind::LeftParen {
+            self.eat(TokenKind::LeftParen)?;
+            let args = self.list(Self::attribute_arg, TokenKind::RightParen)?;
+            self.eat(TokenKind::RightParen)?;
+
+            Some(args)
+        } else {
+            None
+        };
+
+        let span = start_span.merge(self.eat(TokenKind::RightBracket)?);
+
+        Ok(Attribute { style, name, args, span })
+    }
+
+    pub fn attribute_arg(&mut self) -> Result<AttributeArg> {
+        let token = self.peek()?;
+
+        match token.kind {
+            TokenKind::Integer(value) => {
+                self.eat(TokenKind::Integer(value))?;
+                Ok(AttributeArg::Integer(value, token.span()))
+            }
+            TokenKind::Identifier(_) => Ok(AttributeArg::Path(self.path()?)),
+            _ => Err(ParseError::BadToken { got: token, expected: vec!["path", "integer"] }),
+        }
+    }
+
+    /// Parses the attributes and visibility that can precede an item,
+    /// returning them along with the span the item starts at
+    fn item_prefix(&mut self) -> Result<(Vec<Attribute>, Visibility, Span)> {
+        let attributes = self.attributes()?;
+        let visibility = self.visibility()?;
+        let start_span = match attributes.first() {
+            Some(attribute) => attribute.span.merge(visibility.span),
+            None => visibility.span,
+        };
+
+        Ok((attributes, visibility, start_span))
     }
 
     pub fn function(&mut self) -> Result<Function> {
-        let start_span = self.eat(TokenKind::Fn)?;
+        let (attributes, visibility, start_span) = self.item_prefix()?;
+        let start_span = start_span.merge(self.eat(TokenKind::Fn)?);
         let name = self.identifier()?;
 
         self.eat(TokenKind::LeftParen)?;
@@ -133,7 +307,52 @@ impl<'a> Parser<'a> {
         let body = self.block()?;
         let span = start_span.merge(body.span);
 
-        Ok(Function { name, parameters, return_ty, body, span })
+        Ok(Function { attributes, visibility, name, parameters, return_ty, body, span })
+    }
+
+    pub fn r#const(&mut self) -> Result<Const> {
+        let (attributes, visibility, start_span) = self.item_prefix()?;
+        let start_span = start_span.merge(self.eat(TokenKind::Const)?);
+        let name = self.identifier()?;
+        self.eat(TokenKind::Colon)?;
+        let ty = self.ty()?;
+        self.eat(TokenKind::Eq)?;
+        let value = self.expression()?;
+        let span = start_span.merge(self.eat(TokenKind::Semicolon)?);
+
+        Ok(Const { attributes, visibility, name, ty, value, span })
+    }
+
+    pub fn r#static(&mut self) -> Result<Static> {
+        let (attributes, visibility, start_span) = self.item_prefix()?;
+        let start_span = start_span.merge(self.eat(TokenKind::Static)?);
+
+        let mutable = if self.peek()?.kind == TokenKind::Mut {
+            self.eat(TokenKind::Mut)?;
+            true
+        } else {
+            false
+        };
+
+        let name = self.identifier()?;
+        self.eat(TokenKind::Colon)?;
+        let ty = self.ty()?;
+        self.eat(TokenKind::Eq)?;
+        let value = self.expression()?;
+        let span = start_span.merge(self.eat(TokenKind::Semicolon)?);
+
+        Ok(Static { attributes, visibility, mutable, name, ty, value, span })
+    }
+
+    pub fn type_alias(&mut self) -> Result<TypeAlias> {
+        let (attributes, visibility, start_span) = self.item_prefix()?;
+        let start_span = start_span.merge(self.eat(TokenKind::Type)?);
+        let name = self.identifier()?;
+        self.eat(TokenKind::Eq)?;
+        let ty = self.ty()?;
+        let span = start_span.merge(self.eat(TokenKind::Semicolon)?);
+
+        Ok(TypeAlias { attributes, visibility, name, ty, span })
     }
 
     pub fn function_parameter(&mut self) -> Result<FunctionParameter> {
@@ -146,23 +365,25 @@ impl<'a> Parser<'a> {
     }
 
     pub fn r#struct(&mut self) -> Result<Struct> {
-        let start_span = self.eat(TokenKind::Struct)?;
+        let (attributes, visibility, start_span) = self.item_prefix()?;
+        let start_span = start_span.merge(self.eat(TokenKind::Struct)?);
         let name = self.identifier()?;
         self.eat(TokenKind::LeftBrace)?;
         let members = self.list(Self::struct_member, TokenKind::RightBrace)?;
         let end_span = self.eat(TokenKind::RightBrace)?;
         let span = start_span.merge(end_span);
 
-        Ok(Struct { name, members, span })
+        Ok(Struct { attributes, visibility, name, members, span })
     }
 
     pub fn struct_member(&mut self) -> Result<StructMember> {
+        let (attributes, visibility, start_span) = self.item_prefix()?;
         let name = self.identifier()?;
         self.eat(TokenKind::Colon)?;
         let ty = self.ty()?;
-        let span = name.span.merge(ty.span);
+        let span = start_span.merge(ty.span);
 
-        Ok(StructMember { name, ty, span })
+        Ok(StructMember { attributes, visibility, name, ty, span })
     }
 
     /// Note: does not consume the delimiter
@@ -195,7 +416,14 @@ impl<'a> Parser<'a> {
 
         while self.peek()?.kind != TokenKind::RightBrace {
             match self.peek()?.kind {
-                TokenKind::Fn | TokenKind::Struct => items.push(self.item()?),
+                TokenKind::Fn
+                | TokenKind::Struct
+                | TokenKind::Use
+                | TokenKind::Pub
+                | TokenKind::Const
+                | TokenKind::Static
+                | TokenKind::Type => items.push(self.item()?),
+                TokenKind::Pound if self.attributes_precede_item()? => items.push(self.item()?),
                 _ => match self.statement_or_expression()? {
                     Either::Left(stmt) if !ate_expr => statements.push(stmt),
                     Either::Right(expr) if return_expr.is_none() => {
@@ -316,7 +544,7 @@ impl<'a> Parser<'a> {
     }
 
     fn inner_expr(&mut self, curr_binop: Option<BinOp>) -> Result<Expression> {
-        let mut primary = self.primary_expr()?;
+        let mut primary = self.postfix_expr()?;
 
         loop {
             if self.peek().is_err() {
@@ -335,23 +563,48 @@ impl<'a> Parser<'a> {
                     let span = primary.span.merge(rhs.span);
 
                     primary = Expression {
+                        attributes: Vec::new(),
                         kind: ExpressionKind::BinaryOperation(Box::new(primary), binop, Box::new(rhs)),
                         span,
                     };
                 }
-                TokenKind::Period => {
-                    self.eat(TokenKind::Period)?;
-                    let ident = self.identifier()?;
-                    let span = primary.span.merge(ident.span);
-
-                    primary = Expression { kind: ExpressionKind::FieldAccess(Box::new(primary), ident), span };
-                }
                 TokenKind::Eq => {
                     self.eat(TokenKind::Eq)?;
                     let rhs = self.expression()?;
                     let span = primary.span.merge(rhs.span);
 
-                    return Ok(Expression { kind: ExpressionKind::Assignment(Box::new(primary), Box::new(rhs)), span });
+                    return Ok(Expression {
+                        attributes: Vec::new(),
+                        kind: ExpressionKind::Assignment(Box::new(primary), Box::new(rhs)),
+                        span,
+                    });
+                }
+                _ => return Ok(primary),
+            }
+        }
+    }
+
+    /// An expression followed by any field accesses and calls, which is what
+    /// `box` and `*` apply to
+    fn postfix_expr(&mut self) -> Result<Expression> {
+        let mut primary = self.primary_expr()?;
+
+        loop {
+            if self.peek().is_err() {
+                return Ok(primary);
+            }
+
+            match self.peek()?.kind {
+                TokenKind::Period => {
+                    self.eat(TokenKind::Period)?;
+                    let ident = self.identifier()?;
+                    let span = primary.span.merge(ident.span);
+
+                    primary = Expression {
+                        attributes: Vec::new(),
+                        kind: ExpressionKind::FieldAccess(Box::new(primary), ident),
+                        span,
+                    };
                 }
                 TokenKind::LeftParen => {
                     let mut exprs = Vec::new();
@@ -371,6 +624,7 @@ impl<'a> Parser<'a> {
                     let start_span = primary.span;
 
                     primary = Expression {
+                        attributes: Vec::new(),
                         kind: ExpressionKind::FnCall(Box::new(primary), exprs),
                         span: start_span.merge(end_span),
                     };
@@ -385,10 +639,18 @@ impl<'a> Parser<'a> {
         let span = peek.span();
 
         match peek.kind {
+            TokenKind::Pound => {
+                let attributes = self.attributes()?;
+                let mut expr = self.postfix_expr()?;
+                expr.span = span.merge(expr.span);
+                expr.attributes.splice(0..0, attributes);
+
+                Ok(expr)
+            }
             TokenKind::Integer(n) => {
                 self.eat(TokenKind::Integer(n))?;
 
-                Ok(Expression { kind: ExpressionKind::Integer(n), span })
+                Ok(Expression { attributes: Vec::new(), kind: ExpressionKind::Integer(n), span })
             }
             TokenKind::LeftParen => {
                 let start_span = self.eat(TokenKind::LeftParen)?;
@@ -406,31 +668,57 @@ impl<'a> Parser<'a> {
                         let struct_expr = Box::new(self.struct_expr(path)?);
                         let span = struct_expr.span;
 
-                        Ok(Expression { kind: ExpressionKind::Struct(struct_expr), span })
+                        Ok(Expression { attributes: Vec::new(), kind: ExpressionKind::Struct(struct_expr), span })
                     }
-                    _ => Ok(Expression { kind: ExpressionKind::Path(path), span }),
+                    _ => Ok(Expression { attributes: Vec::new(), kind: ExpressionKind::Path(path), span }),
                 }
             }
             TokenKind::If => {
                 let if_expr = self.r#if()?;
                 let span = if_expr.span;
-                Ok(Expression { kind: ExpressionKind::If(Box::new(if_expr)), span })
+                Ok(Expression { attributes: Vec::new(), kind: ExpressionKind::If(Box::new(if_expr)), span })
             }
             TokenKind::Unit => {
                 self.token()?;
-                Ok(Expression { kind: ExpressionKind::Unit, span })
+                Ok(Expression { attributes: Vec::new(), kind: ExpressionKind::Unit, span })
             }
             TokenKind::LeftBrace => {
                 let block = self.block()?;
                 let span = block.span;
-                Ok(Expression { kind: ExpressionKind::Block(Box::new(block)), span })
+                Ok(Expression { attributes: Vec::new(), kind: ExpressionKind::Block(Box::new(block)), span })
             }
             TokenKind::Minus => {
                 let start = self.eat(TokenKind::Minus)?;
                 let expr = self.expression()?;
                 let span = start.merge(expr.span);
 
-                Ok(Expression { kind: ExpressionKind::Unary(UnaryOp::Minus, Box::new(expr)), span })
+                Ok(Expression {
+                    attributes: Vec::new(),
+                    kind: ExpressionKind::Unary(UnaryOp::Minus, Box::new(expr)),
+                    span,
+                })
+            }
+            TokenKind::Box => {
+                let start = self.eat(TokenKind::Box)?;
+                let expr = self.postfix_expr()?;
+                let span = start.merge(expr.span);
+
+                Ok(Expression {
+                    attributes: Vec::new(),
+                    kind: ExpressionKind::Unary(UnaryOp::Box, Box::new(expr)),
+                    span,
+                })
+            }
+            TokenKind::Star => {
+                let start = self.eat(TokenKind::Star)?;
+                let expr = self.postfix_expr()?;
+                let span = start.merge(expr.span);
+
+                Ok(Expression {
+                    attributes: Vec::new(),
+                    kind: ExpressionKind::Unary(UnaryOp::Deref, Box::new(expr)),
+                    span,
+                })
             }
             b @ TokenKind::True | b @ TokenKind::False => {
                 let value = match &b {
@@ -441,7 +729,7 @@ impl<'a> Parser<'a> {
 
                 self.eat(b)?;
 
-                Ok(Expression { kind: ExpressionKind::Boolean(value), span })
+                Ok(Expression { attributes: Vec::new(), kind: ExpressionKind::Boolean(value), span })
             }
             _ => Err(ParseError::BadToken { got: peek, expected: vec!["expression"] }),
         }
@@ -449,17 +737,46 @@ impl<'a> Parser<'a> {
 
     pub fn struct_expr(&mut self, name: Path) -> Result<StructExpr> {
         self.eat(TokenKind::LeftBrace)?;
-        let members = self.list(Self::struct_expr_member, TokenKind::RightBrace)?;
+
+        let mut members = Vec::new();
+        let mut base = None;
+
+        while self.peek()?.kind != TokenKind::RightBrace {
+            if self.peek()?.kind == TokenKind::DotDot {
+                self.eat(TokenKind::DotDot)?;
+                base = Some(Box::new(self.expression()?));
+                break;
+            }
+
+            members.push(self.struct_expr_member()?);
+
+            if self.peek()?.kind == TokenKind::Comma {
+                self.eat(TokenKind::Comma)?;
+            } else {
+                break;
+            }
+        }
+
         let end_span = self.eat(TokenKind::RightBrace)?;
         let span = name.span.merge(end_span);
 
-        Ok(StructExpr { name, members, span })
+        Ok(StructExpr { name, members, base, span })
     }
 
     pub fn struct_expr_member(&mut self) -> Result<StructExprMember> {
         let name = self.identifier()?;
-        self.eat(TokenKind::Colon)?;
-        let expression = self.expression()?;
+
+        let expression = match self.peek()?.kind {
+            TokenKind::Colon => {
+                self.eat(TokenKind::Colon)?;
+                self.expression()?
+            }
+            // Field init shorthand, `Point { x }` is `Point { x: x }`
+            _ => {
+                let path = Path { segments: vec![name.clone()], span: name.span };
+                Expression { attributes: Vec::new(), kind: ExpressionKind::Path(path), span: name.span }
+            }
+        };
         let span = name.span.merge(expression.span);
 
         Ok(StructExprMember { name, expression, span })
@@ -474,7 +791,27 @@ impl<'a> Parser<'a> {
                 self.eat(TokenKind::Int)?;
                 Ok(Type { kind: TypeKind::Integer, span })
             }
-            TokenKind::Identifier(_) => Ok(Type { kind: TypeKind::Named(self.path()?), span }),
+            TokenKind::Identifier(_) => {
+                let path = self.path()?;
+
+                // `Box` is only special when it's given a type to box, so
+                // that it can still be used as a name otherwise
+                let is_box = matches!(&path.segments[..], [segment] if segment.value == "Box");
+                match (is_box, self.peek()) {
+                    (true, Ok(Token { kind: TokenKind::LeftAngle, .. })) => {
+                        self.eat(TokenKind::LeftAngle)?;
+                        let ty = self.ty()?;
+                        let end = self.eat(TokenKind::RightAngle)?;
+
+                        Ok(Type { kind: TypeKind::Box(Box::new(ty)), span: span.merge(end) })
+                    }
+                    _ => Ok(Type { kind: TypeKind::Named(path), span }),
+                }
+            }
+            TokenKind::Bang => {
+                self.eat(TokenKind::Bang)?;
+                Ok(Type { kind: TypeKind::Never, span })
+            }
             _ => Err(ParseError::BadToken { got: token, expected: vec!["type"] }),
         }
     }
@@ -510,12 +847,67 @@ impl<'a> Parser<'a> {
     }
 
     pub fn usage(&mut self) -> Result<Use> {
+        let attributes = self.attributes()?;
         let start_span = self.eat(TokenKind::Use)?;
-        let path = self.path()?;
+        let start_span = attributes.first().map_or(start_span, |attribute| attribute.span.merge(start_span));
+        let tree = self.use_tree()?;
         let end_span = self.eat(TokenKind::Semicolon)?;
         let span = start_span.merge(end_span);
 
-        Ok(Use { path, span })
+        Ok(Use { attributes, tree, span })
+    }
+
+    pub fn use_tree(&mut self) -> Result<UseTree> {
+        let start_span = self.peek()?.span();
+        let mut segments = Vec::new();
+
+        let prefix = |segments: Vec<Identifier>| {
+            let span = match (segments.first(), segments.last()) {
+                (Some(first), Some(last)) => first.span.merge(last.span),
+                _ => Span::new(start_span.start(), start_span.start()),
+            };
+
+            Path { segments, span }
+        };
+
+        loop {
+            match self.peek()?.kind {
+                TokenKind::LeftBrace => {
+                    self.eat(TokenKind::LeftBrace)?;
+                    let trees = self.list(Self::use_tree, TokenKind::RightBrace)?;
+                    let end_span = self.eat(TokenKind::RightBrace)?;
+                    let span = start_span.merge(end_span);
+
+                    return Ok(UseTree { prefix: prefix(segments), kind: UseTreeKind::Nested(trees), span });
+                }
+                TokenKind::Star => {
+                    let end_span = self.eat(TokenKind::Star)?;
+                    let span = start_span.merge(end_span);
+
+                    return Ok(UseTree { prefix: prefix(segments), kind: UseTreeKind::Glob, span });
+                }
+                _ => segments.push(self.identifier()?),
+            }
+
+            if self.peek()?.kind != TokenKind::PathSep {
+                break;
+            }
+
+            self.eat(TokenKind::PathSep)?;
+        }
+
+        let rename = match self.peek()?.kind {
+            TokenKind::As => {
+                self.eat(TokenKind::As)?;
+                Some(self.identifier()?)
+            }
+            _ => None,
+        };
+
+        let prefix = prefix(segments);
+        let span = prefix.span.merge(rename.as_ref().map(|r| r.span).unwrap_or(prefix.span));
+
+        Ok(UseTree { prefix, kind: UseTreeKind::Simple(rename), span })
     }
 
     pub fn eat(&mut self, kind: TokenKind) -> Result<Span> {
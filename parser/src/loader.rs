@@ -0,0 +1,228 @@
+use crate::{ParseError, Parser};
+use ast::{Geode, Item, Module};
+use codespan::{FileId, Files, Span};
+use std::path::{Path, PathBuf};
+
+pub const FILE_EXTENSION: &str = "bm";
+
+#[derive(Debug)]
+pub enum LoadError {
+    /// The root file of a geode couldn't be read
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    /// A module file couldn't be read
+    ModuleIo {
+        file: FileId,
+        span: Span,
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    Parse {
+        file: FileId,
+        error: ParseError,
+    },
+    /// Neither of the candidate files for `module foo;` exist
+    MissingModule {
+        file: FileId,
+        span: Span,
+        name: String,
+        candidates: Vec<PathBuf>,
+    },
+    /// Both of the candidate files for `module foo;` exist
+    AmbiguousModule {
+        file: FileId,
+        span: Span,
+        name: String,
+        candidates: Vec<PathBuf>,
+    },
+    /// A module file (transitively) declares itself as a module
+    Cycle {
+        file: FileId,
+        span: Span,
+        path: PathBuf,
+    },
+}
+
+/// Loads a geode and its out-of-line modules from the filesystem, adding each
+/// file to `files` so that spans can be resolved to the file they came from.
+///
+/// Modules declared with `module foo;` are loaded from `foo.bm` or
+/// `foo/mod.bm` in the module directory of the declaring file, which is the
+/// directory containing the file for the root file and `mod.bm` files, and a
+/// directory named after the module otherwise.
+pub struct Loader<'a> {
+    files: &'a mut Files<String>,
+    stack: Vec<PathBuf>,
+}
+
+impl<'a> Loader<'a> {
+    pub fn new(files: &'a mut Files<String>) -> Self {
+        Self { files, stack: Vec::new() }
+    }
+
+    pub fn load_geode(&mut self, root: &Path) -> Result<Geode, LoadError> {
+        let source =
+            std::fs::read_to_string(root).map_err(|error| LoadError::Io { path: root.to_path_buf(), error })?;
+        let dir = root.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        self.stack.push(root.canonicalize().unwrap_or_else(|_| root.to_path_buf()));
+        let res = self.load_file(root, source, &dir);
+        self.stack.pop();
+
+        let module = res?;
+        let span = module.span;
+
+        Ok(Geode { module, span })
+    }
+
+    /// Loads the out-of-line modules declared in `items`, which were parsed
+    /// from `file` with the module directory `dir`
+    pub fn load_modules(&mut self, items: &mut [Item], file: FileId, dir: &Path) -> Result<(), LoadError> {
+        for item in items {
+            if let Item::Module(module) = item {
+                let child_dir = dir.join(&module.name.value);
+
+                match module.inline {
+                    true => self.load_modules(&mut module.items, file, &child_dir)?,
+                    false => self.load_module(module, file, dir, &child_dir)?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn load_module(
+        &mut self,
+        module: &mut Module,
+        file: FileId,
+        dir: &Path,
+        child_dir: &Path,
+    ) -> Result<(), LoadError> {
+        let name = module.name.value.clone();
+        let candidates =
+            vec![dir.join(&name).with_extension(FILE_EXTENSION), child_dir.join("mod").with_extension(FILE_EXTENSION)];
+
+        let path = match candidates.iter().filter(|path| path.is_file()).collect::<Vec<_>>()[..] {
+            [path] => path.clone(),
+            [] => return Err(LoadError::MissingModule { file, span: module.span, name, candidates }),
+            _ => return Err(LoadError::AmbiguousModule { file, span: module.span, name, candidates }),
+        };
+
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+        if self.stack.contains(&canonical) {
+            return Err(LoadError::Cycle { file, span: module.span, path });
+        }
+
+        let source = std::fs::read_to_string(&path).map_err(|error| LoadError::ModuleIo {
+            file,
+            span: module.span,
+            path: path.clone(),
+            error,
+        })?;
+
+        self.stack.push(canonical);
+        let res = self.load_file(&path, source, child_dir);
+        self.stack.pop();
+
+        let loaded = res?;
+        module.attributes.extend(loaded.attributes);
+        module.items = loaded.items;
+        module.file = loaded.file;
+
+        Ok(())
+    }
+
+    fn load_file(&mut self, path: &Path, source: String, dir: &Path) -> Result<Module, LoadError> {
+        let file = self.files.add(path, source);
+        let mut module =
+            Parser::new(self.files.source(file)).module(true).map_err(|error| LoadError::Parse { file, error })?;
+
+        module.file = Some(file);
+        self.load_modules(&mut module.items, file, dir)?;
+
+        Ok(module)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory of source files that's removed once the test is done
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("bismite-loader-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+
+            Self(dir)
+        }
+
+        fn file(&self, path: &str, source: &str) -> PathBuf {
+            let path = self.0.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, source).unwrap();
+
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn load(root: &Path) -> Result<Geode, LoadError> {
+        Loader::new(&mut Files::new()).load_geode(root)
+    }
+
+    #[test]
+    fn modules_are_loaded_from_either_candidate() {
+        let dir = TempDir::new("found");
+        let root = dir.file("main.bm", "module a; module b;");
+        dir.file("a.bm", "fn f() {}");
+        dir.file("b/mod.bm", "module c;");
+        dir.file("b/c.bm", "fn g() {}");
+
+        let geode = load(&root).unwrap();
+        assert_eq!(geode.module.items.len(), 2);
+    }
+
+    #[test]
+    fn missing_modules_list_both_candidates() {
+        let dir = TempDir::new("missing");
+        let root = dir.file("main.bm", "module foo;");
+
+        match load(&root) {
+            Err(LoadError::MissingModule { name, candidates, .. }) => {
+                assert_eq!(name, "foo");
+                assert_eq!(candidates, [dir.0.join("foo.bm"), dir.0.join("foo").join("mod.bm")]);
+            }
+            res => panic!("expected a missing module, got {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn modules_in_both_candidates_are_ambiguous() {
+        let dir = TempDir::new("ambiguous");
+        let root = dir.file("main.bm", "module foo;");
+        dir.file("foo.bm", "");
+        dir.file("foo/mod.bm", "");
+
+        assert!(matches!(load(&root), Err(LoadError::AmbiguousModule { name, .. }) if name == "foo"));
+    }
+
+    #[test]
+    fn modules_declaring_themselves_are_cycles() {
+        let dir = TempDir::new("cycle");
+        let root = dir.file("main.bm", "module main;");
+
+        assert!(matches!(load(&root), Err(LoadError::Cycle { path, .. }) if path == root));
+    }
+}
@@ -0,0 +1,178 @@
+//! Conversion of the errors produced while loading and evaluating code into
+//! diagnostics that point at the code they're about
+
+use crate::hir_engine::HirEngineError;
+use codespan::{FileId, Files, Span};
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
+use hir::attributes::AttributeDiagnostic;
+use hir::resolve::{DuplicateKind, ResolveError, ResolveErrorKind};
+use lint::{LevelSource, LintDiagnostic};
+use parser::{LoadError, ParseError};
+use typecheck::{TypeEngine, TypeError};
+
+pub fn emit(files: &Files<String>, diagnostic: &Diagnostic<FileId>) {
+    let writer = StandardStream::stderr(ColorChoice::Always);
+    let config = codespan_reporting::term::Config::default();
+
+    codespan_reporting::term::emit(&mut writer.lock(), &config, files, diagnostic).unwrap();
+}
+
+/// Evaluation stops at the first error, except for type errors, which are all
+/// reported together
+pub fn evaluation_error(error: &HirEngineError) -> Vec<Diagnostic<FileId>> {
+    let message = format!("{:?}", error);
+
+    let diagnostic = match error {
+        HirEngineError::TypeErrors(errors) => return errors.clone(),
+        HirEngineError::Lints(lints) => return lints.iter().map(lint).collect(),
+        HirEngineError::ResolveError(error) => resolve_error(error),
+        HirEngineError::GlobalCycle(cycle) => {
+            let (first, rest) = cycle.split_first().expect("cycles contain at least one global");
+            let mut labels: Vec<_> = primary(first.file, first.name().span).into_iter().collect();
+            labels.extend(
+                rest.iter()
+                    .filter(|def| def.path != first.path)
+                    .filter_map(|def| secondary(def.file, def.name().span, "which refers to this")),
+            );
+
+            Diagnostic::error().with_message(message).with_labels(labels)
+        }
+        HirEngineError::Arithmetic(_, file, span) | HirEngineError::RecursionLimitReached(file, span) => {
+            Diagnostic::error().with_message(message).with_labels(primary(*file, *span).into_iter().collect())
+        }
+        HirEngineError::NotMutable(ident, file) | HirEngineError::UnknownIdentifier(ident, file) => {
+            Diagnostic::error().with_message(message).with_labels(primary(*file, ident.span).into_iter().collect())
+        }
+    };
+
+    vec![diagnostic]
+}
+
+pub fn type_error(error: &TypeError, engine: &TypeEngine) -> Diagnostic<FileId> {
+    type_diagnostic(Diagnostic::error(), error, engine)
+}
+
+pub fn type_warning(warning: &TypeError, engine: &TypeEngine) -> Diagnostic<FileId> {
+    type_diagnostic(Diagnostic::warning(), warning, engine)
+}
+
+fn type_diagnostic(severity: Diagnostic<FileId>, error: &TypeError, engine: &TypeEngine) -> Diagnostic<FileId> {
+    let mut labels: Vec<_> = primary(error.file, error.span).into_iter().collect();
+    labels.extend(error.labels.iter().filter_map(|label| secondary(label.file, label.span, &label.message)));
+
+    severity.with_message(format!("{:?}", error.debug(engine))).with_labels(labels)
+}
+
+/// Lints set to `deny` are errors, and the rest are warnings
+pub fn lint(lint: &LintDiagnostic) -> Diagnostic<FileId> {
+    let severity = match lint.is_error() {
+        true => Diagnostic::error(),
+        false => Diagnostic::warning(),
+    };
+
+    let note = match &lint.source {
+        LevelSource::Default => format!("`{}` is set to `{}` by default", lint.lint, lint.level),
+        LevelSource::CommandLine(name) => format!("`--lint {}={}` is in effect here", name, lint.level),
+        LevelSource::Manifest { geode, name } => {
+            format!("`{} = \"{}\"` in the `[lints]` of geode `{}` is in effect here", name, lint.level, geode)
+        }
+        LevelSource::Attribute(name) => format!("`#[{}({})]` is in effect here", lint.level, name),
+    };
+
+    severity
+        .with_message(&lint.message)
+        .with_labels(primary(lint.file, lint.span).into_iter().collect())
+        .with_notes(vec![note])
+}
+
+fn resolve_error(error: &ResolveError) -> Diagnostic<FileId> {
+    let mut labels: Vec<_> = primary(error.file, error.span).into_iter().collect();
+
+    if let ResolveErrorKind::Duplicate { kind, first_file, first, .. } = &error.kind {
+        let message = match kind {
+            DuplicateKind::Item => "first defined here",
+            DuplicateKind::Field | DuplicateKind::Parameter => "first declared here",
+            DuplicateKind::StructExprField => "first specified here",
+            DuplicateKind::ItemAfterImport => "imported here",
+            DuplicateKind::ImportAfterItem => "defined here",
+        };
+
+        labels.extend(secondary(*first_file, *first, message));
+    }
+
+    Diagnostic::error().with_message(error.to_string()).with_labels(labels)
+}
+
+pub fn attribute(diagnostic: AttributeDiagnostic) -> Diagnostic<FileId> {
+    let severity = match diagnostic.is_error() {
+        true => Diagnostic::error(),
+        false => Diagnostic::warning(),
+    };
+
+    severity
+        .with_message(diagnostic.kind.to_string())
+        .with_labels(vec![Label::primary(diagnostic.file, diagnostic.span)])
+}
+
+/// Errors reading the root file of a load have nothing to point at, and have
+/// to be reported separately
+pub fn load_error(files: &Files<String>, e: LoadError) -> Diagnostic<FileId> {
+    match e {
+        LoadError::Io { .. } => unreachable!("no file to point at"),
+        LoadError::ModuleIo { file, span, path, error } => Diagnostic::error()
+            .with_message(format!("Error reading `{}`", path.display()))
+            .with_labels(vec![Label::primary(file, span).with_message("module declared here")])
+            .with_notes(vec![error.to_string()]),
+        LoadError::Parse { file, error: ParseError::BadToken { got, expected } } => {
+            let message = match &expected[..] {
+                [single] => format!("Expected {}, got {}", single, got),
+                _ => format!("Expected one of {}, got {}", expected.join(", "), got),
+            };
+
+            Diagnostic::error()
+                .with_message("Unexpected token")
+                .with_labels(vec![Label::primary(file, got.span)])
+                .with_notes(vec![message])
+        }
+        LoadError::Parse { file, error: ParseError::BadBinOp } => Diagnostic::error()
+            .with_message("Multiple binary operations in the same expression require parenthesis")
+            .with_labels(vec![Label::primary(file, files.source_span(file))]),
+        LoadError::Parse { file, error: ParseError::Eof } => {
+            let end = files.source_span(file).end();
+
+            Diagnostic::error()
+                .with_message("Unexpected end of file")
+                .with_labels(vec![Label::primary(file, Span::new(end, end))])
+        }
+        LoadError::MissingModule { file, span, name, candidates } => Diagnostic::error()
+            .with_message(format!("Couldn't find a file for module `{}`", name))
+            .with_labels(vec![Label::primary(file, span).with_message("module declared here")])
+            .with_notes(vec![format!(
+                "expected `{}` or `{}` to exist",
+                candidates[0].display(),
+                candidates[1].display()
+            )]),
+        LoadError::AmbiguousModule { file, span, name, candidates } => Diagnostic::error()
+            .with_message(format!("Found more than one file for module `{}`", name))
+            .with_labels(vec![Label::primary(file, span).with_message("module declared here")])
+            .with_notes(vec![format!(
+                "only one of `{}` and `{}` can exist",
+                candidates[0].display(),
+                candidates[1].display()
+            )]),
+        LoadError::Cycle { file, span, path } => Diagnostic::error()
+            .with_message("Cyclic module declaration")
+            .with_labels(vec![Label::primary(file, span).with_message("module declared here")])
+            .with_notes(vec![format!("`{}` is already being loaded", path.display())]),
+    }
+}
+
+/// Labels can only be shown for code that came from a file
+fn primary(file: Option<FileId>, span: Span) -> Option<Label<FileId>> {
+    Some(Label::primary(file?, span))
+}
+
+fn secondary(file: Option<FileId>, span: Span, message: &str) -> Option<Label<FileId>> {
+    Some(Label::secondary(file?, span).with_message(message))
+}
@@ -0,0 +1,84 @@
+//! Evaluation of `const` and `static` initializers, which happens once when the
+//! item is defined rather than every time the item is used
+
+use super::{expr::ExpressionId, symbol_table::SymbolTable, HirEngine, HirEngineError};
+use hir::{DefId, Expression};
+
+#[derive(Clone)]
+pub enum GlobalState {
+    /// Defined, but not evaluated yet
+    Pending {
+        value: Expression,
+    },
+    /// Currently being evaluated, so encountering it again means the
+    /// initializer (transitively) refers to itself
+    Evaluating,
+    Evaluated(ExpressionId),
+}
+
+impl HirEngine {
+    pub(super) fn define_global(&mut self, def: DefId, value: Expression) {
+        self.globals.insert(def, GlobalState::Pending { value });
+        self.pending_globals.push(def);
+    }
+
+    /// Evaluates every global that has been defined since the last call, in
+    /// definition order. Globals referred to by an earlier global's initializer
+    /// are evaluated on demand.
+    pub(super) fn evaluate_pending_globals(&mut self) -> Result<(), HirEngineError> {
+        for def in std::mem::take(&mut self.pending_globals) {
+            self.evaluate_global(def)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the location of the value of the global `def`, evaluating its
+    /// initializer if that hasn't happened yet
+    pub(super) fn evaluate_global(&mut self, def: DefId) -> Result<ExpressionId, HirEngineError> {
+        let value = match self.globals.get(&def) {
+            Some(GlobalState::Evaluated(id)) => return Ok(*id),
+            Some(GlobalState::Evaluating) => {
+                let start = self.global_stack.iter().position(|&d| d == def).unwrap();
+                let resolver = self.type_engine.resolver();
+                let cycle = self.global_stack[start..].iter().chain(Some(&def));
+
+                return Err(HirEngineError::GlobalCycle(cycle.map(|&d| resolver.def(d).clone()).collect()));
+            }
+            Some(GlobalState::Pending { value }) => value.clone(),
+            None => unreachable!("globals are defined before they're typechecked"),
+        };
+
+        self.globals.insert(def, GlobalState::Evaluating);
+        self.global_stack.push(def);
+
+        // Initializers are evaluated from the scope the item was defined in,
+        // and can't see the locals of whatever caused them to be evaluated
+        let scope = self.type_engine.resolver().def(def).path.parent();
+        let file = self.type_engine.resolver().def(def).file;
+        let old_path = std::mem::replace(&mut self.current_path, scope);
+        let old_file = std::mem::replace(&mut self.current_file, file);
+        let old_symtab = std::mem::replace(&mut self.symbol_table, SymbolTable::new());
+
+        let res = self.evaluate_expression(&value);
+
+        self.current_path = old_path;
+        self.current_file = old_file;
+        self.symbol_table = old_symtab;
+        self.global_stack.pop();
+
+        match res {
+            Ok(expr) => {
+                let id = self.new_expr(expr);
+                self.globals.insert(def, GlobalState::Evaluated(id));
+
+                Ok(id)
+            }
+            Err(e) => {
+                self.globals.insert(def, GlobalState::Pending { value });
+
+                Err(e)
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use hir::{Identifier, Path};
+use hir::{DefId, Identifier, Path};
 use std::collections::HashMap;
 
 #[derive(Debug, Copy, Clone)]
@@ -9,16 +9,15 @@ pub enum Expression {
     Integer(i128),
     Bool(bool),
     Struct(Path, HashMap<Identifier, ExpressionId>),
-    Function(Path),
+    /// A value moved onto the heap, which is the arena
+    Box(ExpressionId),
+    Function(DefId, Path),
     Unit,
 }
 
 impl Expression {
     pub fn is_unit(&self) -> bool {
-        match self {
-            Expression::Unit => true,
-            _ => false,
-        }
+        matches!(self, Expression::Unit)
     }
 
     pub fn debug<'a>(&'a self, arena: &'a [Expression]) -> ExpressionDebug<'a> {
@@ -58,8 +57,11 @@ impl std::fmt::Debug for ExpressionDebug<'_> {
                 }
                 write!(f, "{:<width$}}}", "", width = self.indent_level * 4)
             }
+            Expression::Box(value) => {
+                write!(f, "Box({:?})", self.arena[value.0].debug(self.arena).add_indent(self.indent_level))
+            }
             Expression::Unit => write!(f, "Unit"),
-            Expression::Function(path) => write!(f, "{}", path),
+            Expression::Function(_, path) => write!(f, "{}", path),
         }
     }
 }
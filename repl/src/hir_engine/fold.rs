@@ -0,0 +1,189 @@
+//! Compile-time evaluation of the parts of expressions that only depend on
+//! literals and `const`s. Folding happens once an input has typechecked, so
+//! arithmetic that's bound to fail is reported before anything is evaluated.
+
+use super::{const_eval::GlobalState, expr, HirEngine, HirEngineError};
+use codespan::FileId;
+use hir::{
+    resolve::DefKind,
+    visit_mut::{walk, VisitorMut},
+    BinOp, Expression, ExpressionKind, IfExpr, Item, ItemKind, Res, UnaryOp,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArithmeticError {
+    DivisionByZero,
+    Overflow,
+}
+
+/// Applies the arithmetic operator `op` to two integers. Shared by folding and
+/// evaluation, so that both fail in the same way.
+pub(super) fn integer_operation(op: BinOp, lhs: i128, rhs: i128) -> Result<i128, ArithmeticError> {
+    let result = match op {
+        BinOp::Add => lhs.checked_add(rhs),
+        BinOp::Subtract => lhs.checked_sub(rhs),
+        BinOp::Multiply => lhs.checked_mul(rhs),
+        BinOp::Divide if rhs == 0 => return Err(ArithmeticError::DivisionByZero),
+        BinOp::Divide => lhs.checked_div(rhs),
+        _ => unreachable!("only called with arithmetic operators"),
+    };
+
+    result.ok_or(ArithmeticError::Overflow)
+}
+
+pub(super) fn negate(i: i128) -> Result<i128, ArithmeticError> {
+    i.checked_neg().ok_or(ArithmeticError::Overflow)
+}
+
+impl HirEngine {
+    /// Folds the constant expressions within `item`, which was defined at the
+    /// root of `file`
+    pub(super) fn fold_item(&mut self, item: &mut Item, file: FileId) -> Result<(), HirEngineError> {
+        let mut folder = Folder { engine: self, file: Some(file), error: None };
+        folder.visit_item(item);
+
+        folder.error.map_or(Ok(()), Err)
+    }
+
+    /// Folds the constant parts of `expr`, which is in the file being
+    /// evaluated
+    pub(super) fn fold_expression(&mut self, expr: &mut Expression) -> Result<(), HirEngineError> {
+        let file = self.current_file;
+        let mut folder = Folder { engine: self, file, error: None };
+        folder.visit_expression(expr);
+
+        folder.error.map_or(Ok(()), Err)
+    }
+
+    /// The value of the `const` `def`, if it has been evaluated and is simple
+    /// enough to be written as a literal
+    fn const_literal(&self, def: hir::DefId) -> Option<ExpressionKind> {
+        if self.type_engine.resolver().def(def).kind != DefKind::Const {
+            return None;
+        }
+
+        match self.globals.get(&def)? {
+            GlobalState::Evaluated(id) => match self.values[id.0] {
+                expr::Expression::Integer(i) => Some(ExpressionKind::Integer(i)),
+                expr::Expression::Bool(b) => Some(ExpressionKind::Boolean(b)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Rewrites expressions whose operands are all literals into the literal they
+/// evaluate to, from the innermost outwards, stopping at the first error. Code
+/// that can never run, like the body of an `if false`, is left alone, since
+/// failing there doesn't matter.
+struct Folder<'a> {
+    engine: &'a mut HirEngine,
+    /// The file of the expression being folded
+    file: Option<FileId>,
+    error: Option<HirEngineError>,
+}
+
+impl Folder<'_> {
+    /// The literal `expression` evaluates to, if its operands are literals
+    fn fold(&self, expression: &Expression) -> Result<Option<ExpressionKind>, ArithmeticError> {
+        Ok(Some(match &expression.kind {
+            ExpressionKind::BinaryOperation(lhs, op, rhs) => match (&lhs.kind, op, &rhs.kind) {
+                (ExpressionKind::Integer(lhs), op, ExpressionKind::Integer(rhs)) if op.is_arith_op() => {
+                    ExpressionKind::Integer(integer_operation(*op, *lhs, *rhs)?)
+                }
+                (ExpressionKind::Integer(lhs), BinOp::Equal, ExpressionKind::Integer(rhs)) => {
+                    ExpressionKind::Boolean(lhs == rhs)
+                }
+                (ExpressionKind::Boolean(lhs), BinOp::Equal, ExpressionKind::Boolean(rhs)) => {
+                    ExpressionKind::Boolean(lhs == rhs)
+                }
+                (ExpressionKind::Boolean(lhs), BinOp::LogicalAnd, ExpressionKind::Boolean(rhs)) => {
+                    ExpressionKind::Boolean(*lhs && *rhs)
+                }
+                _ => return Ok(None),
+            },
+            ExpressionKind::Unary(UnaryOp::Minus, operand) => match operand.kind {
+                ExpressionKind::Integer(i) => ExpressionKind::Integer(negate(i)?),
+                ExpressionKind::Boolean(b) => ExpressionKind::Boolean(!b),
+                _ => return Ok(None),
+            },
+            ExpressionKind::Path(path) => match path.res {
+                Some(Res::Def(def)) => return Ok(self.engine.const_literal(def)),
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        }))
+    }
+}
+
+impl VisitorMut for Folder<'_> {
+    fn visit_item(&mut self, item: &mut Item) {
+        let def = match item.def {
+            Some(def) => self.engine.type_engine.resolver().def(def),
+            None => return,
+        };
+
+        let file = match &item.kind {
+            ItemKind::Module(module) => module.file.or(def.file),
+            _ => def.file,
+        };
+
+        let outer = std::mem::replace(&mut self.file, file);
+        walk::item(self, item);
+        self.file = outer;
+    }
+
+    fn visit_expression(&mut self, expression: &mut Expression) {
+        if self.error.is_some() {
+            return;
+        }
+
+        match &mut expression.kind {
+            // The right hand side isn't evaluated if the left is `false`
+            ExpressionKind::BinaryOperation(lhs, BinOp::LogicalAnd, rhs) => {
+                self.visit_expression(lhs);
+                if !matches!(lhs.kind, ExpressionKind::Boolean(false)) {
+                    self.visit_expression(rhs);
+                }
+            }
+            _ => walk::expression(self, expression),
+        }
+
+        if self.error.is_some() {
+            return;
+        }
+
+        match self.fold(expression) {
+            Ok(Some(kind)) => {
+                // The operands are gone, so their types no longer need to be
+                // remembered
+                match std::mem::replace(&mut expression.kind, kind) {
+                    ExpressionKind::BinaryOperation(lhs, _, rhs) => {
+                        self.engine.type_engine.forget_expression(&lhs);
+                        self.engine.type_engine.forget_expression(&rhs);
+                    }
+                    ExpressionKind::Unary(_, operand) => self.engine.type_engine.forget_expression(&operand),
+                    _ => {}
+                }
+            }
+            Ok(None) => {}
+            Err(error) => self.error = Some(HirEngineError::Arithmetic(error, self.file, expression.span)),
+        }
+    }
+
+    fn visit_if_expr(&mut self, if_expr: &mut IfExpr) {
+        for branch in &mut if_expr.ifs {
+            self.visit_expression(&mut branch.condition);
+
+            match branch.condition.kind {
+                ExpressionKind::Boolean(false) => {}
+                // None of the branches after this one can be taken
+                ExpressionKind::Boolean(true) => return self.visit_block(&mut branch.body),
+                _ => self.visit_block(&mut branch.body),
+            }
+        }
+
+        self.visit_block(&mut if_expr.r#else);
+    }
+}
@@ -1,33 +1,73 @@
+mod const_eval;
 mod expr;
+mod fold;
 mod symbol_table;
 
+use crate::diagnostics;
+use codespan::{FileId, Span};
+use codespan_reporting::diagnostic::Diagnostic;
+use const_eval::GlobalState;
+use fold::ArithmeticError;
 use hir::{
-    visit::Visitor, BinOp, Block, Expression, ExpressionKind, Identifier, Item, ItemKind, Local, Path, Statement,
+    resolve::{Definition, ResolveError},
+    snapshot::SnapshotMap,
+    visit::{walk, Visitor},
+    BinOp, Block, DefId, Expression, ExpressionKind, Identifier, Item, ItemKind, Local, Path, Res, Statement,
     StatementKind, UnaryOp,
 };
+use lint::{LintDiagnostic, LintRegistry};
 use std::{
-    collections::HashMap,
+    collections::{hash_map::Entry, HashMap},
     fmt::{self, Debug, Formatter},
 };
 use symbol_table::SymbolTable;
-use typecheck::{Context, TypeEngine, TypeError, TypeId, TypeInfo};
+use typecheck::{Completions, Context, TypeEngine, TypeError, TypeId, TypeInfo};
 
 pub enum HirEngineError {
-    NotMutable(Identifier),
-    RecursionLimitReached,
-    TypeError(Box<TypeError>, Box<TypeEngine>),
-    UnknownImport(Path),
-    UnknownIdentifier(Identifier),
+    /// Arithmetic at the given span failed, either while folding or
+    /// evaluating it
+    Arithmetic(ArithmeticError, Option<FileId>, Span),
+    /// The initializers of the globals form a cycle, starting and ending with
+    /// the same global
+    GlobalCycle(Vec<Definition>),
+    /// Every lint set to `deny` that found something in the input
+    Lints(Vec<LintDiagnostic>),
+    NotMutable(Identifier, Option<FileId>),
+    /// Evaluating the expression at the given span went too deep
+    RecursionLimitReached(Option<FileId>, Span),
+    ResolveError(ResolveError),
+    /// Every type error found in the input, in the order they were found,
+    /// rendered before the types they mention were discarded along with the
+    /// input
+    TypeErrors(Vec<Diagnostic<FileId>>),
+    UnknownIdentifier(Identifier, Option<FileId>),
 }
 
 impl Debug for HirEngineError {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
-            HirEngineError::NotMutable(ident) => write!(f, "Local `{}` was not declared mutable", ident),
-            HirEngineError::RecursionLimitReached => write!(f, "Reached recursion limit while evaluating expression"),
-            HirEngineError::TypeError(e, engine) => write!(f, "{:?}", e.debug(engine)),
-            HirEngineError::UnknownImport(path) => write!(f, "UnknownImport({})", path),
-            HirEngineError::UnknownIdentifier(ident) => write!(f, "UnknownIdentifier({})", ident),
+            HirEngineError::Arithmetic(ArithmeticError::DivisionByZero, ..) => write!(f, "Attempt to divide by zero"),
+            HirEngineError::Arithmetic(ArithmeticError::Overflow, ..) => write!(f, "Arithmetic operation overflowed"),
+            HirEngineError::GlobalCycle(cycle) => write!(
+                f,
+                "Cycle detected while evaluating `{}`: {}",
+                cycle[0].path,
+                cycle.iter().map(|def| format!("`{}`", def.path)).collect::<Vec<_>>().join(" -> ")
+            ),
+            HirEngineError::Lints(lints) => {
+                let lints: Vec<_> = lints.iter().map(|lint| lint.message.as_str()).collect();
+                write!(f, "{}", lints.join("\n"))
+            }
+            HirEngineError::NotMutable(ident, _) => write!(f, "Local `{}` was not declared mutable", ident),
+            HirEngineError::RecursionLimitReached(..) => {
+                write!(f, "Reached recursion limit while evaluating expression")
+            }
+            HirEngineError::ResolveError(e) => write!(f, "{}", e),
+            HirEngineError::TypeErrors(errors) => {
+                let errors: Vec<_> = errors.iter().map(|error| error.message.as_str()).collect();
+                write!(f, "{}", errors.join("\n"))
+            }
+            HirEngineError::UnknownIdentifier(ident, _) => write!(f, "UnknownIdentifier({})", ident),
         }
     }
 }
@@ -36,13 +76,21 @@ pub struct HirEngine {
     type_engine: TypeEngine,
     symbol_table: SymbolTable,
     current_path: Path,
-    aliases: HashMap<Path, HashMap<Path, Path>>,
-    functions: HashMap<Path, (hir::Function, TypeId, usize)>,
+    /// The file of the code being evaluated
+    current_file: Option<FileId>,
+    functions: SnapshotMap<DefId, hir::Function>,
+    globals: SnapshotMap<DefId, GlobalState>,
+    pending_globals: Vec<DefId>,
+    global_stack: Vec<DefId>,
     values: Vec<expr::Expression>,
-    do_typechecking: bool,
     expr_eval_count: usize,
     expr_eval_limit: usize,
-    unnamable_count: usize,
+    /// Type warnings from the inputs evaluated since they were last taken,
+    /// including inputs that failed
+    warnings: Vec<TypeError>,
+    lints: LintRegistry,
+    /// Lint warnings from the inputs evaluated since they were last taken
+    lint_warnings: Vec<LintDiagnostic>,
 }
 
 impl Default for HirEngine {
@@ -51,26 +99,48 @@ impl Default for HirEngine {
             type_engine: Default::default(),
             symbol_table: Default::default(),
             current_path: Default::default(),
-            aliases: Default::default(),
+            current_file: None,
             functions: Default::default(),
+            globals: Default::default(),
+            pending_globals: Default::default(),
+            global_stack: Default::default(),
             values: Default::default(),
-            do_typechecking: true,
             expr_eval_count: 0,
             expr_eval_limit: 100,
-            unnamable_count: 0,
+            warnings: Vec::new(),
+            lints: LintRegistry::new(),
+            lint_warnings: Vec::new(),
         }
     }
 }
 
 impl HirEngine {
-    pub fn new() -> Self {
-        Self::default()
+    pub fn new(lints: LintRegistry) -> Self {
+        Self { lints, ..Self::default() }
+    }
+
+    pub fn lints_mut(&mut self) -> &mut LintRegistry {
+        &mut self.lints
     }
 
     pub fn type_engine(&self) -> &TypeEngine {
         &self.type_engine
     }
 
+    pub fn take_warnings(&mut self) -> Vec<TypeError> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    pub fn take_lint_warnings(&mut self) -> Vec<LintDiagnostic> {
+        std::mem::take(&mut self.lint_warnings)
+    }
+
+    /// Registers a geode with the resolver, which must be done before its
+    /// root module is evaluated
+    pub fn register_geode(&mut self, name: Identifier, dependencies: Vec<Identifier>) {
+        self.type_engine.resolver_mut().register_geode(name, dependencies);
+    }
+
     pub fn expr_arena(&self) -> &[expr::Expression] {
         &self.values
     }
@@ -81,190 +151,318 @@ impl HirEngine {
         id
     }
 
-    pub fn evaluate_item(&mut self, item: &Item) -> Result<(), HirEngineError> {
-        self.type_engine.typecheck_item(&self.mk_context(), item).map_err(|e| self.mk_type_error(e))?;
+    /// Evaluates `item`, which was defined at the root of `file`
+    pub fn evaluate_item(&mut self, item: &Item, file: FileId) -> Result<(), HirEngineError> {
+        self.transaction(file, |engine| {
+            let mut item = item.clone();
+            engine.type_engine.resolver_mut().resolve_item(&mut item, file).map_err(HirEngineError::ResolveError)?;
+            engine.type_engine.typecheck_item(&engine.mk_context(), &item);
+            engine.check_type_errors()?;
+            engine.check_lints(engine.lints.check_item(&item, engine.type_engine.resolver(), file))?;
+            engine.fold_item(&mut item, file)?;
+
+            Registrar { engine }.visit_item(&item);
+            engine.evaluate_pending_globals()
+        })
+    }
 
-        match &item.kind {
-            // alreayd inserted in typechecker
-            ItemKind::Struct(_) => Ok(()),
-            ItemKind::Module(module) => {
-                self.current_path = self.current_path.with_ident(module.name);
+    /// Evaluates a statement entered at the top level of `file`
+    pub fn evaluate_top_level_statement(&mut self, statement: &Statement, file: FileId) -> Result<(), HirEngineError> {
+        self.transaction(file, |engine| {
+            let mut statement = statement.clone();
+            engine
+                .type_engine
+                .resolver_mut()
+                .resolve_statement(&mut statement, file)
+                .map_err(HirEngineError::ResolveError)?;
+
+            match &statement.kind {
+                StatementKind::Local(local) => {
+                    engine.type_engine.typecheck_local(&engine.mk_context(), local);
+                    engine.check_type_errors()?;
+                    engine.check_lints(engine.lints.check_statement(
+                        &statement,
+                        engine.type_engine.resolver(),
+                        file,
+                    ))?;
+
+                    let mut value = local.value.clone();
+                    engine.fold_expression(&mut value)?;
+                    let expr = engine.evaluate_typechecked(&value)?;
+                    let expr = engine.new_expr(expr);
+                    engine.type_engine.forget_expression(&value);
+
+                    engine.symbol_table.new_binding(symbol_table::Local::new(
+                        local.local_id(),
+                        local.name,
+                        expr,
+                        local.mutable,
+                    ));
+
+                    Ok(())
+                }
+                StatementKind::Expression(expr) => {
+                    let expected = engine.type_engine.fresh_infer();
+                    engine.evaluate_checked(expr, expected).map(drop)
+                }
+            }
+        })
+    }
 
-                let aliases = self.aliases.entry(self.current_path.clone()).or_default();
-                UseCollector::new(aliases).visit_module(&module);
+    /// Evaluates an expression entered at the top level of `file`
+    pub fn evaluate_top_level_expression(
+        &mut self,
+        expr: &Expression,
+        file: FileId,
+    ) -> Result<expr::Expression, HirEngineError> {
+        self.transaction(file, |engine| {
+            let mut expr = expr.clone();
+            engine
+                .type_engine
+                .resolver_mut()
+                .resolve_expression(&mut expr, file)
+                .map_err(HirEngineError::ResolveError)?;
+
+            let expected = engine.type_engine.fresh_infer();
+            engine.evaluate_checked(&expr, expected)
+        })
+    }
 
-                // TODO: clear stuff on errors
-                for item in &module.items {
-                    self.evaluate_item(item)?;
-                }
+    /// Typechecks `expr` and registers any items nested within it before
+    /// evaluating it, since those items have to be known before any of the
+    /// expression is evaluated
+    fn evaluate_checked(&mut self, expr: &Expression, expected: TypeId) -> Result<expr::Expression, HirEngineError> {
+        self.type_engine.typecheck_expression_from(
+            &self.mk_context(),
+            &self.current_path,
+            self.current_file,
+            expr,
+            expected,
+        );
+        self.check_type_errors()?;
+        if let Some(file) = self.current_file {
+            self.check_lints(self.lints.check_expression(expr, self.type_engine.resolver(), file))?;
+        }
 
-                self.current_path.pop();
+        let mut expr = expr.clone();
+        self.fold_expression(&mut expr)?;
+        let value = self.evaluate_typechecked(&expr)?;
+        self.type_engine.forget_expression(&expr);
 
-                Ok(())
-            }
-            ItemKind::Use(usage) => {
-                let ctx = self.mk_context();
-                if self.type_engine.typeid_from_path(&ctx, &usage.path).is_none() {
-                    return Err(HirEngineError::UnknownImport(usage.path.clone()));
-                }
+        Ok(value)
+    }
 
-                self.aliases
-                    .entry(self.current_path.clone())
-                    .or_default()
-                    .insert(Path::from_identifier(usage.path.last()), usage.path.clone());
+    /// Evaluates `expr`, which has already been typechecked
+    fn evaluate_typechecked(&mut self, expr: &Expression) -> Result<expr::Expression, HirEngineError> {
+        Registrar { engine: self }.visit_expression(expr);
+        self.evaluate_pending_globals()?;
+
+        self.evaluate_expression(expr)
+    }
 
-                Ok(())
+    /// Runs `f` on the input from `file`, discarding anything registered
+    /// while running it if it fails, since the input that caused the
+    /// registration is discarded as well
+    fn transaction<T>(
+        &mut self,
+        file: FileId,
+        f: impl FnOnce(&mut Self) -> Result<T, HirEngineError>,
+    ) -> Result<T, HirEngineError> {
+        self.current_file = Some(file);
+        self.type_engine.set_file(file);
+
+        let type_engine = self.type_engine.snapshot();
+        let functions = self.functions.snapshot();
+        let globals = self.globals.snapshot();
+
+        let res = f(self);
+        self.warnings.extend(self.type_engine.take_warnings());
+
+        match res {
+            Ok(_) => {
+                self.type_engine.commit(type_engine);
+                self.functions.commit(functions);
+                self.globals.commit(globals);
+                self.type_engine.collect_garbage();
             }
-            ItemKind::Function(f) => {
-                let ctx = self.mk_context();
-                let path = self.current_path.with_ident(f.name);
-                let id = self.type_engine.typeid_from_path(&ctx, &path).unwrap();
-                self.functions.insert(path, (f.clone(), id, self.unnamable_count));
+            Err(_) => {
+                self.type_engine.rollback_to(type_engine);
+                self.functions.rollback_to(functions);
+                self.globals.rollback_to(globals);
+                self.pending_globals.clear();
+            }
+        }
+
+        res
+    }
 
-                Ok(())
+    /// Registers an item that has already been typechecked. Items nested
+    /// within it are registered separately by [`Registrar`].
+    fn register_item(&mut self, item: &Item) {
+        let def = match item.def {
+            Some(def) => def,
+            None => return,
+        };
+
+        match &item.kind {
+            ItemKind::Function(f) => {
+                self.functions.insert(def, f.clone());
+            }
+            ItemKind::Const(hir::Const { value, .. }) | ItemKind::Static(hir::Static { value, .. }) => {
+                self.define_global(def, value.clone());
             }
+            // Everything else only matters to the typechecker
+            _ => {}
         }
     }
 
     pub fn evaluate_statement(&mut self, statement: &Statement) -> Result<(), HirEngineError> {
         match &statement.kind {
             StatementKind::Local(local) => self.evaluate_local(local),
-            StatementKind::Expression(expr) => self.evaluate_expression(expr, None).map(drop),
+            StatementKind::Expression(expr) => self.evaluate_expression(expr).map(drop),
         }
     }
 
     pub fn evaluate_local(&mut self, local: &Local) -> Result<(), HirEngineError> {
-        let ctx = self.mk_context();
-        let expected = self.type_engine.from_hir_type(&ctx, &local.ty).map_err(|e| self.mk_type_error(e))?;
-        let expr = self.evaluate_expression(&local.value, Some(expected))?;
+        let expr = self.evaluate_expression(&local.value)?;
         let expr = self.new_expr(expr);
 
-        self.symbol_table.new_binding(symbol_table::Local::new(local.name, expr, expected, local.mutable));
+        self.symbol_table.new_binding(symbol_table::Local::new(local.local_id(), local.name, expr, local.mutable));
 
         Ok(())
     }
 
-    pub fn evaluate_expression(
-        &mut self,
-        expr: &Expression,
-        expected_type: Option<TypeId>,
-    ) -> Result<expr::Expression, HirEngineError> {
-        let expected_type = expected_type.unwrap_or_else(|| self.type_engine.fresh_infer());
-        let ctx = self.mk_context();
-
+    /// Evaluates `expr`, which must have been typechecked along with the input
+    /// or item it's part of
+    pub fn evaluate_expression(&mut self, expr: &Expression) -> Result<expr::Expression, HirEngineError> {
         self.expr_eval_count += 1;
         if self.expr_eval_count > self.expr_eval_limit {
-            self.do_typechecking = true;
-            return Err(HirEngineError::RecursionLimitReached);
+            return Err(HirEngineError::RecursionLimitReached(self.current_file, expr.span));
         }
 
-        if self.do_typechecking {
-            self.type_engine.typecheck_expression(&ctx, expr, expected_type).map_err(|e| self.mk_type_error(e))?;
-        }
-
-        self.do_typechecking = false;
-
         let res = (|| {
             Ok(match &expr.kind {
-                ExpressionKind::Block(block) => self.evaluate_block(block, Some(expected_type), None)?,
+                ExpressionKind::Block(block) => self.evaluate_block(block)?,
                 ExpressionKind::BinaryOperation(lhs, op, rhs) => {
-                    let lhs = self.evaluate_expression(lhs, None)?;
-                    let rhs = self.evaluate_expression(rhs, None)?;
+                    let lhs = self.evaluate_expression(lhs)?;
+
+                    // `&&` only evaluates its right hand side when it has to
+                    if let (BinOp::LogicalAnd, expr::Expression::Bool(false)) = (op, &lhs) {
+                        return Ok(lhs);
+                    }
+
+                    let rhs = self.evaluate_expression(rhs)?;
 
                     match op {
                         op if op.is_arith_op() => match (lhs, rhs) {
-                            (expr::Expression::Integer(lhs), expr::Expression::Integer(rhs)) => match op {
-                                BinOp::Add => expr::Expression::Integer(lhs + rhs),
-                                BinOp::Subtract => expr::Expression::Integer(lhs - rhs),
-                                BinOp::Multiply => expr::Expression::Integer(lhs * rhs),
-                                BinOp::Divide => expr::Expression::Integer(lhs / rhs),
-                                _ => unreachable!(),
-                            },
+                            (expr::Expression::Integer(lhs), expr::Expression::Integer(rhs)) => {
+                                let result = fold::integer_operation(*op, lhs, rhs)
+                                    .map_err(|e| HirEngineError::Arithmetic(e, self.current_file, expr.span))?;
+
+                                expr::Expression::Integer(result)
+                            }
                             _ => todo!("actual eval stuff"),
                         },
                         BinOp::Equal => expr::Expression::Bool(self.expressions_are_equal(&lhs, &rhs)),
+                        BinOp::LogicalAnd => rhs,
                         _ => unreachable!(),
                     }
                 }
                 ExpressionKind::Boolean(b) => expr::Expression::Bool(*b),
-                ExpressionKind::FnCall(lhs, args) => self.evaluate_fn_call(lhs, args, Some(expected_type))?,
+                ExpressionKind::FnCall(lhs, args) => self.evaluate_fn_call(lhs, args)?,
                 ExpressionKind::If(if_expr) => {
                     for if_expr in &if_expr.ifs {
-                        let condition = self.evaluate_expression(&if_expr.condition, None)?;
+                        let condition = self.evaluate_expression(&if_expr.condition)?;
 
                         match condition {
                             expr::Expression::Bool(true) => {
-                                return self.evaluate_block(&if_expr.body, Some(expected_type), None);
+                                return self.evaluate_block(&if_expr.body);
                             }
                             expr::Expression::Bool(false) => continue,
                             _ => unreachable!(),
                         }
                     }
 
-                    self.evaluate_block(&if_expr.r#else, Some(expected_type), None)?
+                    self.evaluate_block(&if_expr.r#else)?
                 }
                 ExpressionKind::Integer(i) => expr::Expression::Integer(*i),
-                ExpressionKind::Path(path) => match path.is_identifier() {
-                    Some(ident) => match self.symbol_table.resolve_binding(ident) {
-                        Some(local) => self.values[local.value.0].clone(),
-                        None => match self
-                            .aliases
-                            .get(&self.current_path)
-                            .and_then(|map| {
-                                let real_path = map.get(path)?;
-                                Some((real_path, self.functions.get(real_path)?))
-                            })
-                            .or_else(|| Some((path, self.functions.get(path)?)))
-                        {
-                            Some((real_path, _)) => expr::Expression::Function(real_path.clone()),
-                            None => return Err(HirEngineError::UnknownIdentifier(ident)),
-                        },
-                    },
-                    _ => match self.functions.get(path) {
-                        Some(_) => expr::Expression::Function(path.clone()),
-                        _ => todo!("path stuff"),
+                ExpressionKind::Path(path) => match path.resolution() {
+                    Res::Local(id) => match self.symbol_table.resolve_binding(id) {
+                        Some(local) => self.copy_value(local.value),
+                        None => return Err(HirEngineError::UnknownIdentifier(path.last(), self.current_file)),
                     },
+                    Res::Def(def) if self.globals.contains_key(&def) => {
+                        let id = self.evaluate_global(def)?;
+                        self.copy_value(id)
+                    }
+                    Res::Def(def) if self.functions.contains_key(&def) => {
+                        expr::Expression::Function(def, self.type_engine.resolver().def(def).path.clone())
+                    }
+                    Res::Def(_) => return Err(HirEngineError::UnknownIdentifier(path.last(), self.current_file)),
                 },
-                ExpressionKind::Struct(s) => expr::Expression::Struct(
-                    s.name.clone(),
-                    s.members
+                ExpressionKind::Struct(s) => {
+                    let mut members = s
+                        .members
                         .iter()
                         .map(|member| {
-                            let expr = self.evaluate_expression(&member.expression, None)?;
+                            let expr = self.evaluate_expression(&member.expression)?;
                             let expr = self.new_expr(expr);
 
                             Ok((member.name, expr))
                         })
-                        .collect::<Result<_, _>>()?,
-                ),
+                        .collect::<Result<HashMap<_, _>, _>>()?;
+
+                    if let Some(base) = &s.base {
+                        match self.evaluate_expression(base)? {
+                            expr::Expression::Struct(_, base_members) => {
+                                for (ident, id) in base_members {
+                                    if let Entry::Vacant(entry) = members.entry(ident) {
+                                        let value = self.copy_value(id);
+                                        entry.insert(self.new_expr(value));
+                                    }
+                                }
+                            }
+                            _ => unreachable!(),
+                        }
+                    }
+
+                    expr::Expression::Struct(s.name.clone(), members)
+                }
                 ExpressionKind::FieldAccess(lhs, ident) => {
-                    let s = self.evaluate_expression(lhs, None)?;
+                    let s = self.evaluate_expression(lhs)?;
+                    let s = self.unbox(s);
 
                     match s {
-                        expr::Expression::Struct(_, members) => self.values[members.get(ident).unwrap().0].clone(),
+                        expr::Expression::Struct(_, members) => self.copy_value(members[ident]),
                         _ => unreachable!(),
                     }
                 }
                 ExpressionKind::Assignment(lhs, rhs) => {
-                    let rhs = self.evaluate_expression(rhs, None)?;
+                    let rhs = self.evaluate_expression(rhs)?;
                     *self.get_place(lhs)? = rhs;
 
                     expr::Expression::Unit
                 }
                 ExpressionKind::Unit => expr::Expression::Unit,
-                ExpressionKind::Unary(op, expr) => {
-                    let expr = self.evaluate_expression(expr, None)?;
+                ExpressionKind::Unary(op, operand) => {
+                    let operand = self.evaluate_expression(operand)?;
 
-                    match (op, expr) {
-                        (UnaryOp::Minus, expr::Expression::Integer(i)) => expr::Expression::Integer(-i),
+                    match (op, operand) {
+                        (UnaryOp::Minus, expr::Expression::Integer(i)) => {
+                            let i = fold::negate(i)
+                                .map_err(|e| HirEngineError::Arithmetic(e, self.current_file, expr.span))?;
+
+                            expr::Expression::Integer(i)
+                        }
                         (UnaryOp::Minus, expr::Expression::Bool(b)) => expr::Expression::Bool(!b),
+                        (UnaryOp::Box, value) => expr::Expression::Box(self.new_expr(value)),
+                        (UnaryOp::Deref, expr::Expression::Box(id)) => self.copy_value(id),
                         _ => unreachable!(),
                     }
                 }
             })
         })();
 
-        self.do_typechecking = true;
         self.expr_eval_count -= 1;
 
         res
@@ -274,44 +472,31 @@ impl HirEngine {
         &mut self,
         callable: &Expression,
         args: &[Expression],
-        expected_type: Option<TypeId>,
     ) -> Result<expr::Expression, HirEngineError> {
-        match self.evaluate_expression(callable, None)? {
-            expr::Expression::Function(path) => {
-                self.do_typechecking = false;
-                let (f, fn_id, unnamable) = self.functions.get(&path).unwrap().clone();
-                let parameters = match self.type_engine.typeinfo(fn_id) {
-                    TypeInfo::Function { parameters, .. } => parameters.clone(),
-                    _ => unreachable!(),
-                };
-                let iter = parameters.iter().zip(args.iter());
-
+        match self.evaluate_expression(callable)? {
+            expr::Expression::Function(def, path) => {
+                let f = self.functions.get(&def).unwrap().clone();
                 let mut new_symbols = SymbolTable::new();
-                let old_aliases = self.aliases.clone();
 
-                for (param, arg) in iter {
-                    let expr = self.evaluate_expression(arg, Some(param.1));
+                for (param, arg) in f.parameters.iter().zip(args) {
+                    let expr = self.evaluate_expression(arg)?;
+                    let expr = self.new_expr(expr);
 
-                    if expr.is_err() {
-                        self.do_typechecking = true;
-                    }
-
-                    let expr = self.new_expr(expr?);
-
-                    new_symbols.new_binding(symbol_table::Local::new(param.0, expr, param.1, false));
+                    new_symbols.new_binding(symbol_table::Local::new(param.local_id(), param.name, expr, false));
                 }
 
                 let old_symtab = self.symbol_table.clone();
                 self.symbol_table = new_symbols;
 
-                self.current_path = self.current_path.with_ident(f.name);
+                let old_path = std::mem::replace(&mut self.current_path, path);
+                let file = self.type_engine.resolver().def(def).file;
+                let old_file = std::mem::replace(&mut self.current_file, file);
 
-                let res = self.evaluate_block(&f.body, expected_type, Some(unnamable));
+                let res = self.evaluate_block(&f.body);
 
-                self.current_path.pop();
-                self.aliases = old_aliases;
+                self.current_path = old_path;
+                self.current_file = old_file;
                 self.symbol_table = old_symtab;
-                self.do_typechecking = true;
 
                 res
             }
@@ -319,66 +504,25 @@ impl HirEngine {
         }
     }
 
-    pub fn evaluate_block(
-        &mut self,
-        block: &Block,
-        expected_type: Option<TypeId>,
-        existing_unnamable: Option<usize>,
-    ) -> Result<expr::Expression, HirEngineError> {
+    /// Evaluates the statements and return expression of `block`. Items
+    /// within the block were registered along with the enclosing input.
+    pub fn evaluate_block(&mut self, block: &Block) -> Result<expr::Expression, HirEngineError> {
         let old_symtab = self.symbol_table.clone();
-        let old_aliases = self.aliases.clone();
+        let scope = self.type_engine.resolver().def(block.scope_id()).path.clone();
 
         self.symbol_table = SymbolTable::with_parent(&old_symtab);
-        self.current_path = self.current_path.with_ident(Identifier::new(&match existing_unnamable {
-            Some(n) => n.to_string(),
-            None => {
-                let n = self.unnamable_count.to_string();
-                self.unnamable_count += 1;
-                n
-            }
-        }));
+        let old_path = std::mem::replace(&mut self.current_path, scope);
 
         let res = (|| {
-            for item in &block.items {
-                self.evaluate_item(item)?;
-                match &item.kind {
-                    ItemKind::Struct(s) => {
-                        self.aliases
-                            .entry(self.current_path.clone())
-                            .or_default()
-                            .insert(Path::from_identifier(s.name), self.current_path.with_ident(s.name));
-                    }
-                    ItemKind::Function(f) => {
-                        self.aliases
-                            .entry(self.current_path.clone())
-                            .or_default()
-                            .insert(Path::from_identifier(f.name), self.current_path.with_ident(f.name));
-                    }
-                    ItemKind::Module(m) => {
-                        self.aliases
-                            .entry(self.current_path.clone())
-                            .or_default()
-                            .insert(Path::from_identifier(m.name), self.current_path.with_ident(m.name));
-                    }
-                    ItemKind::Use(u) => {
-                        self.aliases
-                            .entry(self.current_path.clone())
-                            .or_default()
-                            .insert(Path::from_identifier(u.path.last()), u.path.clone());
-                    }
-                }
-            }
-
             for statement in &block.statements {
                 self.evaluate_statement(statement)?;
             }
 
-            self.evaluate_expression(&block.return_expr, expected_type)
+            self.evaluate_expression(&block.return_expr)
         })();
 
-        self.current_path.pop();
+        self.current_path = old_path;
         self.symbol_table = old_symtab;
-        self.aliases = old_aliases;
 
         res
     }
@@ -399,82 +543,257 @@ impl HirEngine {
 
                 true
             }
-            (expr::Expression::Function(p), expr::Expression::Function(p2)) => p == p2,
+            (expr::Expression::Function(a, _), expr::Expression::Function(b, _)) => a == b,
+            (expr::Expression::Box(a), expr::Expression::Box(b)) => {
+                self.expressions_are_equal(&self.expr_arena()[a.0], &self.expr_arena()[b.0])
+            }
             _ => unreachable!(),
         }
     }
 
+    /// A copy of the value in `id` that shares no boxes or fields with it, so
+    /// that assigning through one doesn't change the other
+    fn copy_value(&mut self, id: expr::ExpressionId) -> expr::Expression {
+        match self.values[id.0].clone() {
+            expr::Expression::Box(boxed) => {
+                let value = self.copy_value(boxed);
+                expr::Expression::Box(self.new_expr(value))
+            }
+            expr::Expression::Struct(path, members) => {
+                let members = members
+                    .into_iter()
+                    .map(|(name, member)| {
+                        let value = self.copy_value(member);
+                        (name, self.new_expr(value))
+                    })
+                    .collect();
+
+                expr::Expression::Struct(path, members)
+            }
+            value => value,
+        }
+    }
+
+    /// The value behind any number of boxes of `value`, which is what fields
+    /// are accessed through
+    fn unbox(&self, mut value: expr::Expression) -> expr::Expression {
+        while let expr::Expression::Box(id) = value {
+            value = self.values[id.0].clone();
+        }
+
+        value
+    }
+
     fn get_place(&mut self, expr: &Expression) -> Result<&mut expr::Expression, HirEngineError> {
         match &expr.kind {
             ExpressionKind::FieldAccess(lhs, field) => {
-                let lhs = self.get_place(lhs)?;
+                let lhs = self.get_place(lhs)?.clone();
 
-                match lhs {
+                match self.unbox(lhs) {
                     expr::Expression::Struct(_, members) => {
-                        let id = members.get_mut(field).unwrap().0;
+                        let id = members.get(field).unwrap().0;
                         Ok(&mut self.values[id])
                     }
                     _ => unreachable!(),
                 }
             }
-            ExpressionKind::Path(path) => match path.is_identifier() {
-                Some(ident) => match self.symbol_table.resolve_binding(ident) {
-                    Some(e) if e.mutable => Ok(&mut self.values[e.value.0]),
-                    Some(_) => Err(HirEngineError::NotMutable(ident)),
-                    None => unreachable!(),
+            ExpressionKind::Unary(UnaryOp::Deref, operand) => match self.get_place(operand)? {
+                &mut expr::Expression::Box(id) => Ok(&mut self.values[id.0]),
+                _ => unreachable!(),
+            },
+            ExpressionKind::Path(path) => match path.resolution() {
+                Res::Local(id) => match self.symbol_table.resolve_binding(id) {
+                    Some(local) if local.mutable => Ok(&mut self.values[local.value.0]),
+                    Some(_) => Err(HirEngineError::NotMutable(path.last(), self.current_file)),
+                    None => Err(HirEngineError::UnknownIdentifier(path.last(), self.current_file)),
                 },
-                None => unreachable!(),
+                // `static mut`s, which the typechecker has already checked
+                Res::Def(def) => {
+                    let id = self.evaluate_global(def)?;
+                    Ok(&mut self.values[id.0])
+                }
             },
             _ => unreachable!(),
         }
     }
 
     pub fn typeinfo(&self, path: &Path) -> Option<TypeInfo> {
-        let ctx = self.mk_context();
-        let id = self.type_engine.typeid_from_path(&ctx, path)?;
-        Some(self.type_engine.typeinfo(id).clone())
+        let id = self.type_engine.typeid_from_path(path)?;
+        Some(self.type_engine.declared_typeinfo(id).clone())
     }
 
     pub fn varinfo(&self, ident: Identifier) -> Option<symbol_table::Local> {
-        self.symbol_table.resolve_binding(ident)
+        let id = self.type_engine.resolver().top_level_local(ident)?;
+        self.symbol_table.resolve_binding(id)
     }
 
-    fn mk_type_error(&self, error: TypeError) -> HirEngineError {
-        HirEngineError::TypeError(Box::new(error), Box::new(self.type_engine.clone()))
+    /// Fails with the type errors reported since this was last called, if
+    /// there were any
+    fn check_type_errors(&mut self) -> Result<(), HirEngineError> {
+        let errors = self.type_engine.take_errors();
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(HirEngineError::TypeErrors(
+                errors.iter().map(|error| diagnostics::type_error(error, &self.type_engine)).collect(),
+            )),
+        }
+    }
+
+    /// Keeps the lint warnings in `diagnostics`, failing with the lint errors
+    /// if there are any
+    fn check_lints(&mut self, diagnostics: Vec<LintDiagnostic>) -> Result<(), HirEngineError> {
+        let (errors, warnings): (Vec<_>, Vec<_>) = diagnostics.into_iter().partition(LintDiagnostic::is_error);
+        self.lint_warnings.extend(warnings);
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(HirEngineError::Lints(errors)),
+        }
+    }
+
+    /// The names in scope at the top level that could complete the
+    /// identifier ending at `offset` in `source`
+    pub fn completions(&self, source: &str, offset: usize) -> Completions {
+        self.type_engine.completions(&self.mk_context(), source, offset)
     }
 
     fn mk_context(&self) -> Context<'static> {
         Context {
-            aliases: self.aliases.get(&self.current_path).cloned().unwrap_or_default(),
             bindings: self
                 .symbol_table
                 .bindings()
-                .map(|local| (local.name, typecheck::BindingInfo { mutable: local.mutable, typeid: local.ty }))
+                .map(|local| {
+                    let typeid = self.type_engine.type_of_local(local.id).expect("bindings are typechecked");
+                    (local.id, typecheck::BindingInfo { mutable: local.mutable, typeid })
+                })
                 .collect(),
             parent: None,
         }
     }
 }
 
-struct UseCollector<'a> {
-    aliases: &'a mut HashMap<Path, Path>,
+/// Registers every function and global within the visited item or
+/// expression, including those nested in blocks, with the engine
+struct Registrar<'a> {
+    engine: &'a mut HirEngine,
 }
 
-impl<'a> UseCollector<'a> {
-    fn new(aliases: &'a mut HashMap<Path, Path>) -> Self {
-        Self { aliases }
+impl Visitor for Registrar<'_> {
+    fn visit_item(&mut self, item: &Item) {
+        self.engine.register_item(item);
+        walk::item(self, item);
     }
 }
 
-impl Visitor for UseCollector<'_> {
-    fn visit_use(&mut self, usage: &hir::Use) {
-        let path = usage.path.clone();
-        self.aliases.insert(Path::from_identifier(path.last()), path);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codespan::Files;
+
+    #[derive(Default)]
+    struct Session {
+        engine: HirEngine,
+        files: Files<String>,
     }
 
-    fn visit_item(&mut self, item: &Item) {
-        if let ItemKind::Use(usage) = &item.kind {
-            self.visit_use(usage);
+    impl Session {
+        fn item(&mut self, source: &str) -> Result<(), HirEngineError> {
+            let file = self.files.add("test", source.to_string());
+            let item = Item::convert(&parser::Parser::new(source).item().unwrap());
+            self.engine.evaluate_item(&item, file)
+        }
+
+        fn statement(&mut self, source: &str) -> Result<(), HirEngineError> {
+            let file = self.files.add("test", source.to_string());
+            let statement = Statement::convert(&parser::Parser::new(source).statement().unwrap());
+            self.engine.evaluate_top_level_statement(&statement, file)
+        }
+
+        fn expression(&mut self, source: &str) -> Result<expr::Expression, HirEngineError> {
+            self.expression_with(source, |_| {})
+        }
+
+        /// Evaluates `source` once `f` has changed it, for expressions that
+        /// can't be written yet
+        fn expression_with(
+            &mut self,
+            source: &str,
+            f: impl FnOnce(&mut Expression),
+        ) -> Result<expr::Expression, HirEngineError> {
+            let file = self.files.add("test", source.to_string());
+            let mut expr = Expression::convert(&parser::Parser::new(source).expression().unwrap());
+            f(&mut expr);
+            self.engine.evaluate_top_level_expression(&expr, file)
         }
+
+        fn integer(&mut self, source: &str) -> i128 {
+            match self.expression(source) {
+                Ok(expr::Expression::Integer(i)) => i,
+                Ok(value) => panic!("expected an integer, got {:?}", value.debug(self.engine.expr_arena())),
+                Err(e) => panic!("expected an integer, got {:?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn failed_inputs_are_rolled_back() {
+        let mut session = Session::default();
+        session.item("fn one() -> Int { 1 }").unwrap();
+
+        let error = session.item("module m { struct S { x: Int } pub fn two() -> Int { super::one() + S { x: 1 } } }");
+        assert!(matches!(error, Err(HirEngineError::TypeErrors(errors)) if !errors.is_empty()));
+        assert!(session
+            .engine
+            .typeinfo(&Path::from_identifier(Identifier::new("m")).with_ident(Identifier::new("S")))
+            .is_none());
+        assert!(matches!(session.expression("m::two()"), Err(HirEngineError::ResolveError(_))));
+
+        session.item("module m { pub fn two() -> Int { super::one() + 1 } }").unwrap();
+        assert_eq!(session.integer("m::two()"), 2);
+    }
+
+    #[test]
+    fn copies_of_boxes_and_structs_are_independent() {
+        let mut session = Session::default();
+        session.item("struct S { x: Box<Int> }").unwrap();
+        session.statement("let mut a = box 1;").unwrap();
+        session.statement("let mut b = a;").unwrap();
+        session.expression("*b = 2").unwrap();
+        assert_eq!(session.integer("*a"), 1);
+        assert_eq!(session.integer("*b"), 2);
+
+        session.statement("let mut s = S { x: box 1 };").unwrap();
+        session.statement("let mut t = s;").unwrap();
+        session.expression("*t.x = 2").unwrap();
+        assert_eq!(session.integer("*s.x"), 1);
+        assert_eq!(session.integer("*t.x"), 2);
+    }
+
+    #[test]
+    fn failed_statements_leave_no_locals_behind() {
+        let mut session = Session::default();
+        session.statement("let x = 1;").unwrap();
+
+        assert!(session.statement("let y = x + Unit;").is_err());
+        assert!(matches!(session.expression("y"), Err(HirEngineError::ResolveError(_))));
+        assert_eq!(session.integer("x"), 1);
+    }
+
+    #[test]
+    fn dead_code_is_not_folded() {
+        let mut session = Session::default();
+
+        // `&&` can't be written yet, so `==` stands in for it
+        let value = session.expression_with("false == ((1 / 0) == 0)", |expr| {
+            if let ExpressionKind::BinaryOperation(_, op, _) = &mut expr.kind {
+                *op = BinOp::LogicalAnd;
+            }
+        });
+        assert!(matches!(value, Ok(expr::Expression::Bool(false))));
+
+        assert_eq!(session.integer("if false { 1 / 0 } else { 1 }"), 1);
+        assert!(session.expression("if false { 1 / 0; }").is_ok());
+        assert!(matches!(session.expression("1 / 0"), Err(HirEngineError::Arithmetic(..))));
     }
 }
@@ -1,11 +1,11 @@
-use hir::Identifier;
+use hir::{Identifier, LocalId};
 use std::collections::HashMap;
-use typecheck::{TypeEngine, TypeId};
+use typecheck::TypeEngine;
 
 #[derive(Debug, Clone, Default)]
 pub struct SymbolTable {
     parent: Option<Box<SymbolTable>>,
-    symbols: HashMap<Identifier, Local>,
+    symbols: HashMap<LocalId, Local>,
 }
 
 impl SymbolTable {
@@ -17,18 +17,18 @@ impl SymbolTable {
         Self { parent: Some(Box::new(parent.clone())), symbols: HashMap::new() }
     }
 
-    pub fn resolve_binding(&self, ident: Identifier) -> Option<Local> {
-        match self.symbols.get(&ident) {
+    pub fn resolve_binding(&self, id: LocalId) -> Option<Local> {
+        match self.symbols.get(&id) {
             Some(local) => Some(*local),
             None => match &self.parent {
-                Some(parent) => parent.resolve_binding(ident),
+                Some(parent) => parent.resolve_binding(id),
                 None => None,
             },
         }
     }
 
     pub fn new_binding(&mut self, local: Local) {
-        self.symbols.insert(local.name, local);
+        self.symbols.insert(local.id, local);
     }
 
     pub fn bindings(&self) -> Box<dyn Iterator<Item = Local> + '_> {
@@ -41,15 +41,15 @@ impl SymbolTable {
 
 #[derive(Debug, Clone, Copy)]
 pub struct Local {
+    pub id: LocalId,
     pub name: Identifier,
     pub value: super::expr::ExpressionId,
-    pub ty: TypeId,
     pub mutable: bool,
 }
 
 impl Local {
-    pub fn new(name: Identifier, value: super::expr::ExpressionId, ty: TypeId, mutable: bool) -> Self {
-        Self { name, value, ty, mutable }
+    pub fn new(id: LocalId, name: Identifier, value: super::expr::ExpressionId, mutable: bool) -> Self {
+        Self { id, name, value, mutable }
     }
 
     pub fn debug<'a>(&'a self, arena: &'a [super::expr::Expression], engine: &'a TypeEngine) -> LocalDebug<'a> {
@@ -67,7 +67,8 @@ impl std::fmt::Debug for LocalDebug<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Binding `{}`:", self.local.name)?;
         writeln!(f, "   mutable? {}", self.local.mutable)?;
-        writeln!(f, "      type: {:?}", self.engine.typeinfo(self.local.ty).debug(self.engine).add_indent(3))?;
+        let ty = self.engine.type_of_local(self.local.id).expect("bindings are typechecked");
+        writeln!(f, "      type: {:?}", self.engine.typeinfo(ty).debug(self.engine).add_indent(3))?;
         write!(f, "     value: {:?}", self.arena[self.local.value.0].debug(self.arena).add_indent(3))
     }
 }
@@ -1,12 +1,16 @@
 #![allow(clippy::match_bool)]
 
+mod diagnostics;
 mod hir_engine;
 mod repl;
 
+use codespan::Files;
 use codespan_reporting::diagnostic::{Diagnostic, Label};
 use codespan_reporting::files::SimpleFile;
 use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
-use parser::ParseError;
+use lint::LintRegistry;
+use manifest::ManifestError;
+use parser::{LoadError, ParseError};
 use repl::{Repl, ReplError, ReplErrorKind};
 use structopt::StructOpt;
 
@@ -14,27 +18,62 @@ use structopt::StructOpt;
 struct Arguments {
     #[structopt(long = "clear-screen", short = "c")]
     clear_screen: bool,
+    /// List the available lints and their default levels, then exit
+    #[structopt(long = "list-lints")]
+    list_lints: bool,
+    /// Set the level of a lint, e.g. `--lint non_snake_case=deny`
+    #[structopt(long = "lint", number_of_values = 1)]
+    lints: Vec<String>,
 }
 
 fn main() {
     let args = Arguments::from_args();
 
-    let mut repl = Repl::new();
+    let mut lints = LintRegistry::new();
+    for setting in &args.lints {
+        if let Err(e) = lints.apply_setting(setting) {
+            println!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    if args.list_lints {
+        print_lints(&lints);
+        return;
+    }
+
+    let mut repl = Repl::new(lints);
 
     if args.clear_screen {
         println!("\x1B[2J\x1B[H");
     }
 
     loop {
-        match repl.run() {
+        let res = repl.run();
+
+        for warning in repl.take_warnings() {
+            diagnostics::emit(repl.files(), &warning);
+        }
+
+        match res {
             Ok(Some(text)) => println!("{}", text),
             Ok(None) => {}
-            Err(e) => print_err(e),
+            Err(e) => print_err(repl.files(), e),
         }
     }
 }
 
-fn print_err(e: ReplError) {
+fn print_lints(lints: &LintRegistry) {
+    let width = lints.lints().map(|lint| lint.name.len()).max().unwrap_or(0);
+
+    println!("{:width$}  level    description", "name", width = width);
+    for lint in lints.lints() {
+        let level = lints.level(lint).to_string();
+        println!("{:width$}  {:7}  {}", lint.name, level, lint.description, width = width);
+    }
+}
+
+fn print_err(files: &Files<String>, e: ReplError) {
     match e {
         ReplError { kind: ReplErrorKind::MultiExpression, .. } => {
             println!("Error: can't evaluate more than one expression at one time")
@@ -42,6 +81,24 @@ fn print_err(e: ReplError) {
         ReplError { kind: ReplErrorKind::Readline(e), .. } => {
             println!("Error reading input: {}", e);
         }
+        ReplError { kind: ReplErrorKind::Attributes(diagnostics), .. } => {
+            for diagnostic in diagnostics {
+                diagnostics::emit(files, &diagnostics::attribute(diagnostic));
+            }
+        }
+        ReplError { kind: ReplErrorKind::Evaluation(e), .. } => {
+            for diagnostic in diagnostics::evaluation_error(&e) {
+                diagnostics::emit(files, &diagnostic);
+            }
+        }
+        ReplError { kind: ReplErrorKind::LintConfig(geode, e), .. } => {
+            println!("Error in the lints of geode `{}`: {}", geode, e);
+        }
+        ReplError { kind: ReplErrorKind::LoadError(e), .. } => print_load_err(files, *e),
+        ReplError { kind: ReplErrorKind::ManifestError(e), .. } => match *e {
+            ManifestError::Load(e) => print_load_err(files, e),
+            e => println!("{}", e),
+        },
         ReplError { source, kind: ReplErrorKind::ParseError(e) } => match e {
             ParseError::BadBinOp => {
                 println!("Error: Multiple binary operations in the same expression require parenthesis")
@@ -65,3 +122,12 @@ fn print_err(e: ReplError) {
         },
     }
 }
+
+fn print_load_err(files: &Files<String>, e: LoadError) {
+    if let LoadError::Io { path, error } = e {
+        println!("Error reading `{}`: {}", path.display(), error);
+        return;
+    }
+
+    diagnostics::emit(files, &diagnostics::load_error(files, e));
+}
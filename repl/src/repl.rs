@@ -1,7 +1,15 @@
-use crate::hir_engine::HirEngine;
-use ast::AstNode;
-use parser::{ParseError, Parser};
-use rustyline::{error::ReadlineError, hint::HistoryHinter, CompletionType, Config, Editor};
+use crate::diagnostics;
+use crate::hir_engine::{HirEngine, HirEngineError};
+use ast::{AstNode, Item};
+use codespan::{FileId, Files};
+use codespan_reporting::diagnostic::Diagnostic;
+use hir::attributes::{AttributeDiagnostic, AttributeRegistry};
+use lint::{LintConfigError, LintRegistry};
+use manifest::{GeodeGraph, ManifestError};
+use parser::{LoadError, Loader, ParseError, Parser};
+use rustyline::{completion::Pair, error::ReadlineError, hint::HistoryHinter, CompletionType, Config, Editor};
+use std::{cell::RefCell, rc::Rc};
+use typecheck::CompletionKind;
 
 const HELP_MSG: &str = r"Commands:
     .help                       Displays this help text
@@ -9,7 +17,8 @@ const HELP_MSG: &str = r"Commands:
     .clear                      Clear the current screen
     .varinfo <ident>            Display the variable information associated with the given identifier
     .typeinfo <path>            Display information about the type associated with the given path
-    .loadfile <file_path>       Load the given file path as a module";
+    .loadfile <file_path>       Load the given file path as a module, along with any modules it declares
+    .loadgeode <path>           Load the geode with the given manifest or manifest directory, along with its dependencies";
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum EvalMode {
@@ -51,6 +60,12 @@ impl ReplError {
 }
 
 pub enum ReplErrorKind {
+    Attributes(Vec<AttributeDiagnostic>),
+    Evaluation(Box<HirEngineError>),
+    /// The lint levels in the manifest of the given geode are invalid
+    LintConfig(String, LintConfigError),
+    LoadError(Box<LoadError>),
+    ManifestError(Box<ManifestError>),
     ParseError(ParseError),
     MultiExpression,
     Readline(rustyline::error::ReadlineError),
@@ -60,17 +75,49 @@ pub struct Repl {
     code: String,
     editor: Editor<Helper>,
     prompt_mode: PromptMode,
-    hir_engine: HirEngine,
+    /// Shared with the editor's helper, which completes names from it
+    hir_engine: Rc<RefCell<HirEngine>>,
+    files: Files<String>,
+    attributes: AttributeRegistry,
+    /// Warnings produced by the last command, to be displayed alongside its
+    /// output
+    warnings: Vec<AttributeDiagnostic>,
 }
 
 impl Repl {
-    pub fn new() -> Self {
+    pub fn new(lints: LintRegistry) -> Self {
+        let hir_engine = Rc::new(RefCell::new(HirEngine::new(lints)));
         let mut editor = Editor::with_config(Config::builder().completion_type(CompletionType::Circular).build());
-        editor.set_helper(Some(Helper::new()));
+        editor.set_helper(Some(Helper::new(Rc::clone(&hir_engine))));
         let _ = editor.load_history("repl_history.bismite");
 
-        Self { code: String::new(), editor, prompt_mode: PromptMode::Fresh, hir_engine: HirEngine::new() }
+        Self {
+            code: String::new(),
+            editor,
+            prompt_mode: PromptMode::Fresh,
+            hir_engine,
+            files: Files::new(),
+            attributes: AttributeRegistry::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn files(&self) -> &Files<String> {
+        &self.files
     }
+
+    pub fn take_warnings(&mut self) -> Vec<Diagnostic<FileId>> {
+        let attributes = std::mem::take(&mut self.warnings).into_iter().map(diagnostics::attribute);
+        let mut hir_engine = self.hir_engine.borrow_mut();
+        let types = hir_engine.take_warnings();
+        let lints = hir_engine.take_lint_warnings();
+        let engine = hir_engine.type_engine();
+        let types = types.iter().map(|warning| diagnostics::type_warning(warning, engine));
+        let lints = lints.iter().map(diagnostics::lint);
+
+        attributes.chain(types).chain(lints).collect()
+    }
+
     pub fn run(&mut self) -> Result<Option<String>, ReplError> {
         let line = match self.read_line(self.prompt_mode) {
             LineReturn::Done(s) => s,
@@ -82,7 +129,7 @@ impl Repl {
         };
 
         self.editor.add_history_entry(&*line);
-        if self.eval_repl_command(&line) {
+        if self.eval_repl_command(&line)? {
             return Ok(None);
         }
 
@@ -125,27 +172,76 @@ impl Repl {
             }
         }
 
+        // Every input is given a file so that diagnostics can point into it
+        let file = self.files.add("<stdin>", self.code.clone());
+
+        // Out-of-line modules declared at the prompt are loaded relative to the
+        // current directory
+        if nodes.iter().any(|node| matches!(node, AstNode::Item(Item::Module(_)))) {
+            let dir = std::env::current_dir().unwrap_or_default();
+            let mut loader = Loader::new(&mut self.files);
+
+            for node in &mut nodes {
+                if let AstNode::Item(item) = node {
+                    if let Err(e) = loader.load_modules(std::slice::from_mut(item), file, &dir) {
+                        let code = self.code.clone();
+                        self.reset();
+                        return Err(ReplError::new(code, ReplErrorKind::LoadError(Box::new(e))));
+                    }
+                }
+            }
+        }
+
         let mut eval_output = None;
         for node in nodes {
             match eval_mode {
                 EvalMode::Eval => match node {
-                    AstNode::Item(item) => match self.hir_engine.evaluate_item(&hir::Item::convert(&item)) {
-                        Ok(_) => eval_output = None,
-                        Err(e) => eval_output = Some(format!("{:?}", e)),
-                    },
+                    AstNode::Item(item) => {
+                        let item = hir::Item::convert(&item);
+                        if let Err(e) = self.validate_attributes(self.attributes.validate(&item, file)) {
+                            let code = self.code.clone();
+                            self.reset();
+                            return Err(ReplError::new(code, e));
+                        }
+
+                        let result = self.hir_engine.borrow_mut().evaluate_item(&item, file);
+                        match result {
+                            Ok(_) => eval_output = None,
+                            Err(e) => return Err(self.evaluation_error(e)),
+                        }
+                    }
                     AstNode::Expression(e) => {
-                        match self.hir_engine.evaluate_expression(&hir::Expression::convert(&e), None) {
+                        let expression = hir::Expression::convert(&e);
+                        if let Err(e) = self.validate_attributes(self.attributes.validate_expression(&expression, file))
+                        {
+                            let code = self.code.clone();
+                            self.reset();
+                            return Err(ReplError::new(code, e));
+                        }
+
+                        let result = self.hir_engine.borrow_mut().evaluate_top_level_expression(&expression, file);
+                        match result {
                             Ok(e) if !e.is_unit() => {
-                                eval_output = Some(format!("{:?}", e.debug(self.hir_engine.expr_arena())))
+                                eval_output = Some(format!("{:?}", e.debug(self.hir_engine.borrow().expr_arena())))
                             }
                             Ok(_) => eval_output = None,
-                            Err(e) => eval_output = Some(format!("{:?}", e)),
+                            Err(e) => return Err(self.evaluation_error(e)),
+                        }
+                    }
+                    AstNode::Statement(s) => {
+                        let statement = hir::Statement::convert(&s);
+                        if let Err(e) = self.validate_attributes(self.attributes.validate_statement(&statement, file)) {
+                            let code = self.code.clone();
+                            self.reset();
+                            return Err(ReplError::new(code, e));
+                        }
+
+                        let result = self.hir_engine.borrow_mut().evaluate_top_level_statement(&statement, file);
+                        match result {
+                            Ok(_) => eval_output = None,
+                            Err(e) => return Err(self.evaluation_error(e)),
                         }
                     }
-                    AstNode::Statement(s) => match self.hir_engine.evaluate_statement(&hir::Statement::convert(&s)) {
-                        Ok(_) => eval_output = None,
-                        Err(e) => eval_output = Some(format!("{:?}", e)),
-                    },
                 },
                 EvalMode::Ast => eval_output = Some(format!("{:#?}", node)),
                 EvalMode::Hir => {
@@ -164,10 +260,11 @@ impl Repl {
         Ok(eval_output)
     }
 
-    fn eval_repl_command(&mut self, s: &str) -> bool {
+    fn eval_repl_command(&mut self, s: &str) -> Result<bool, ReplError> {
+        let s = s.trim();
         let command = match s.split(' ').next() {
             Some(cmd) => cmd,
-            None => return false,
+            None => return Ok(false),
         };
 
         match command {
@@ -183,7 +280,7 @@ impl Repl {
                     Some(ident) => ident,
                     None => {
                         println!("Must provide an identifier to .varinfo, e.g. .varinfo my_variable");
-                        return true;
+                        return Ok(true);
                     }
                 };
                 let valid_ident = Parser::new(ident).identifier();
@@ -191,20 +288,20 @@ impl Repl {
                 match valid_ident {
                     Ok(valid_ident) => {
                         let valid_ident = hir::Identifier::convert(&valid_ident);
-                        match self.hir_engine.varinfo(valid_ident) {
-                            Some(var_info) => println!(
-                                "{:?}",
-                                var_info.debug(self.hir_engine.expr_arena(), self.hir_engine.type_engine())
-                            ),
+                        let hir_engine = self.hir_engine.borrow();
+                        match hir_engine.varinfo(valid_ident) {
+                            Some(var_info) => {
+                                println!("{:?}", var_info.debug(hir_engine.expr_arena(), hir_engine.type_engine()))
+                            }
                             None => {
                                 println!("Variable with identifier `{}` not found in scope", valid_ident);
-                                return true;
+                                return Ok(true);
                             }
                         }
                     }
                     Err(_) => {
                         println!("Invalid identifier");
-                        return true;
+                        return Ok(true);
                     }
                 }
             }
@@ -213,19 +310,20 @@ impl Repl {
                     Some(path) => path,
                     None => {
                         println!("Must provide a path to .typeinfo, e.g. .typeinfo MyType");
-                        return true;
+                        return Ok(true);
                     }
                 };
                 let path = match Parser::new(path).path() {
                     Ok(path) => hir::Path::convert(&path),
                     Err(e) => {
                         println!("Invalid path: {:?}", e);
-                        return true;
+                        return Ok(true);
                     }
                 };
 
-                match self.hir_engine.typeinfo(&path) {
-                    Some(type_info) => println!("{:?}", type_info.debug(&self.hir_engine.type_engine())),
+                let hir_engine = self.hir_engine.borrow();
+                match hir_engine.typeinfo(&path) {
+                    Some(type_info) => println!("{:?}", type_info.debug(hir_engine.type_engine())),
                     None => println!("Type with path `{}` not found in scope", path),
                 }
             }
@@ -234,44 +332,97 @@ impl Repl {
                     Some(path) => path,
                     None => {
                         println!("Must provide a file path to .loadfile");
-                        return true;
+                        return Ok(true);
                     }
                 };
 
-                let file_contents = match std::fs::read_to_string(&file_path) {
-                    Ok(contents) => contents,
-                    Err(e) => {
-                        println!("Error reading file: {}", e);
-                        return true;
+                let file_path = std::path::Path::new(file_path);
+                let mut parsed = match Loader::new(&mut self.files).load_geode(file_path) {
+                    Ok(geode) => geode.module,
+                    Err(e) => return Err(ReplError::new(String::new(), ReplErrorKind::LoadError(Box::new(e)))),
+                };
+
+                parsed.name = ast::Identifier {
+                    value: file_path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+                    span: codespan::Span::new(0, 0),
+                };
+
+                let module = hir::Module::convert(&parsed);
+                let span = module.span;
+                let item = hir::Item { kind: hir::ItemKind::Module(module), def: None, span };
+                let file = parsed.file.expect("loaded modules always have a file");
+                if let Err(e) = self.validate_attributes(self.attributes.validate(&item, file)) {
+                    return Err(ReplError::new(String::new(), e));
+                }
+
+                if let Err(e) = self.hir_engine.borrow_mut().evaluate_item(&item, file) {
+                    return Err(ReplError::new(String::new(), ReplErrorKind::Evaluation(Box::new(e))));
+                }
+            }
+            ".loadgeode" => {
+                let path = match s.split(' ').nth(1) {
+                    Some(path) => path,
+                    None => {
+                        println!("Must provide a manifest path to .loadgeode");
+                        return Ok(true);
                     }
                 };
 
-                let parsed = match Parser::new(&file_contents).module(true) {
-                    Ok(mut module) => {
-                        module.name = ast::Identifier {
-                            value: std::path::Path::new(&file_path).file_stem().unwrap().to_string_lossy().to_string(),
-                            span: codespan::Span::new(0, 0),
-                        };
+                let graph = match GeodeGraph::load(&mut self.files, std::path::Path::new(path)) {
+                    Ok(graph) => graph,
+                    Err(e) => return Err(ReplError::new(String::new(), ReplErrorKind::ManifestError(Box::new(e)))),
+                };
 
-                        module
+                for loaded in &graph.geodes {
+                    let levels = loaded.manifest.lints.iter().map(|(lint, level)| (lint.as_str(), level.as_str()));
+                    if let Err(e) = self.hir_engine.borrow_mut().lints_mut().set_geode_levels(loaded.name(), levels) {
+                        let kind = ReplErrorKind::LintConfig(loaded.name().to_string(), e);
+                        return Err(ReplError::new(String::new(), kind));
                     }
-                    Err(e) => {
-                        println!("Error parsing file: {:?}", e);
-                        return true;
+                }
+
+                for loaded in &graph.geodes {
+                    let name = hir::Identifier::convert(&loaded.geode.module.name);
+                    let dependencies = loaded.dependencies().map(hir::Identifier::new).collect();
+                    self.hir_engine.borrow_mut().register_geode(name, dependencies);
+
+                    let module = hir::Module::convert(&loaded.geode.module);
+                    let span = module.span;
+                    let item = hir::Item { kind: hir::ItemKind::Module(module), def: None, span };
+                    let file = loaded.geode.module.file.expect("loaded modules always have a file");
+                    if let Err(e) = self.validate_attributes(self.attributes.validate(&item, file)) {
+                        return Err(ReplError::new(String::new(), e));
                     }
-                };
 
-                let module = hir::Module::convert(&parsed);
-                let span = module.span;
-                if let Err(e) = self.hir_engine.evaluate_item(&hir::Item { kind: hir::ItemKind::Module(module), span })
-                {
-                    println!("Error processing module: {:?}", e);
+                    if let Err(e) = self.hir_engine.borrow_mut().evaluate_item(&item, file) {
+                        println!("Error processing geode `{}`", loaded.name());
+                        return Err(ReplError::new(String::new(), ReplErrorKind::Evaluation(Box::new(e))));
+                    }
                 }
             }
-            _ => return false,
+            _ => return Ok(false),
         }
 
-        true
+        Ok(true)
+    }
+
+    fn evaluation_error(&mut self, error: HirEngineError) -> ReplError {
+        let code = self.code.clone();
+        self.reset();
+
+        ReplError::new(code, ReplErrorKind::Evaluation(Box::new(error)))
+    }
+
+    /// Fails if any of the attribute `diagnostics` found in the input are
+    /// errors, stashing them as warnings otherwise
+    fn validate_attributes(&mut self, diagnostics: Vec<AttributeDiagnostic>) -> Result<(), ReplErrorKind> {
+        match diagnostics.iter().any(AttributeDiagnostic::is_error) {
+            true => Err(ReplErrorKind::Attributes(diagnostics)),
+            false => {
+                self.warnings.extend(diagnostics);
+                Ok(())
+            }
+        }
     }
 
     fn eval_mode(&mut self) -> EvalMode {
@@ -309,11 +460,14 @@ impl Repl {
 
 struct Helper {
     hinter: HistoryHinter,
+    /// The engine completions are taken from, which is only borrowed while
+    /// a completion is being requested
+    hir_engine: Rc<RefCell<HirEngine>>,
 }
 
 impl Helper {
-    fn new() -> Self {
-        Self { hinter: HistoryHinter {} }
+    fn new(hir_engine: Rc<RefCell<HirEngine>>) -> Self {
+        Self { hinter: HistoryHinter {}, hir_engine }
     }
 }
 
@@ -325,7 +479,24 @@ impl rustyline::highlight::Highlighter for Helper {
 }
 impl rustyline::validate::Validator for Helper {}
 impl rustyline::completion::Completer for Helper {
-    type Candidate = rustyline::completion::Pair;
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _: &rustyline::Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let hir_engine = self.hir_engine.borrow();
+        let engine = hir_engine.type_engine();
+        let completions = hir_engine.completions(line, pos);
+        let candidates = completions.candidates.iter().map(|completion| {
+            let display = match (completion.ty, completion.kind) {
+                (Some(ty), _) => format!("{}: {}", completion.name, engine.typeinfo(ty).name(engine)),
+                (None, CompletionKind::Item(kind)) => format!("{} ({})", completion.name, kind),
+                (None, _) => completion.name.to_string(),
+            };
+
+            Pair { display, replacement: completion.name.to_string() }
+        });
+
+        Ok((completions.start, candidates.collect()))
+    }
 }
 impl rustyline::hint::Hinter for Helper {
     fn hint(&self, line: &str, pos: usize, ctx: &rustyline::Context<'_>) -> Option<String> {
@@ -3,11 +3,14 @@ use std::{
     sync::atomic::{AtomicBool, AtomicUsize},
 };
 
+// FIXME: remove the `allow`s once the cache is read from
+#[allow(dead_code)]
 #[derive(Debug, Clone, Copy)]
 pub struct Index {
     index: usize,
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct StrCache {
     backing: UnsafeCell<Vec<&'static str>>,
@@ -15,6 +18,12 @@ pub struct StrCache {
     write_locked: AtomicBool,
 }
 
+impl Default for StrCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl StrCache {
     pub const fn new() -> Self {
         Self {
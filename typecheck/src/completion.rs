@@ -0,0 +1,355 @@
+//! What can be written at a point in some code, based on the names in scope
+//! there and the types of the values they refer to. This is what tab
+//! completion at the REPL is built on, and is meant to be usable by an editor
+//! integration as well.
+
+use crate::{BindingInfo, Context, TypeEngine, TypeId, TypeInfo};
+use hir::{resolve::DefKind, Identifier, LocalId, Path};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompletionKind {
+    Local,
+    Item(DefKind),
+    Field,
+}
+
+#[derive(Clone, Debug)]
+pub struct Completion {
+    pub name: Identifier,
+    pub kind: CompletionKind,
+    /// The type of the value the name refers to, for anything that's a value
+    pub ty: Option<TypeId>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Completions {
+    /// The byte offset where the partially written name being completed
+    /// starts, which is what the completions replace
+    pub start: usize,
+    pub candidates: Vec<Completion>,
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Splits `text` before the identifier at its end, which is empty if it
+/// doesn't end in one
+fn split_identifier(text: &str) -> (&str, &str) {
+    let start = text.char_indices().rev().take_while(|&(_, c)| is_identifier_char(c)).last();
+    text.split_at(start.map_or(text.len(), |(i, _)| i))
+}
+
+/// The identifiers separated by `separator` that `text` ends with, if it
+/// ends with `separator`, e.g. `a` and `b` for `a::b::`
+fn trailing_segments(text: &str, separator: &str) -> Option<Vec<Identifier>> {
+    let mut rest = text.strip_suffix(separator)?;
+    let mut segments = Vec::new();
+
+    loop {
+        let (before, segment) = split_identifier(rest);
+        if segment.is_empty() {
+            // Something other than a name, like a call, which completions
+            // can't be found for without typechecking it
+            return None;
+        }
+
+        segments.push(Identifier::new(segment));
+        match before.strip_suffix(separator) {
+            Some(before) => rest = before,
+            None => break,
+        }
+    }
+
+    segments.reverse();
+    Some(segments)
+}
+
+impl TypeEngine {
+    /// Returns what can be written at the byte offset `offset` of `source`,
+    /// which is code within the current path with the locals in `ctx` in
+    /// scope. After a `.`, that's the visible fields of the struct before it,
+    /// after a `::`, the visible members of the module before it, and
+    /// otherwise every local and item in scope. Only names starting with
+    /// whatever has already been written of the name at `offset` are included.
+    pub fn completions(&self, ctx: &Context<'_>, source: &str, offset: usize) -> Completions {
+        let (before, partial) = split_identifier(&source[..offset]);
+        let start = before.len();
+
+        let candidates = if let Some(segments) = trailing_segments(before, "::") {
+            self.module_completions(Path { segments, res: None })
+        } else if let Some(segments) = trailing_segments(before, ".") {
+            self.field_completions(ctx, &segments)
+        } else if before.ends_with(':') || before.ends_with('.') {
+            Vec::new()
+        } else {
+            self.scope_completions(ctx)
+        };
+
+        let candidates = candidates.into_iter().filter(|completion| completion.name.string().starts_with(partial));
+        Completions { start, candidates: candidates.collect() }
+    }
+
+    /// The members of the module `path` refers to that are visible from the
+    /// current path
+    fn module_completions(&self, path: Path) -> Vec<Completion> {
+        let module = match self.resolver.resolve_path(&self.current_path, &path) {
+            Some(module) if self.resolver.def(module).kind == DefKind::Module => self.resolver.def(module).path.clone(),
+            _ => return Vec::new(),
+        };
+
+        let mut candidates: Vec<_> = self
+            .resolver
+            .children(&module)
+            .filter(|&id| self.resolver.check_visibility(&self.current_path, &self.resolver.def(id).path).is_ok())
+            .map(|id| self.item_completion(self.resolver.def(id).name(), id))
+            .collect();
+
+        candidates.sort_by_key(|completion| completion.name.string());
+        candidates
+    }
+
+    /// The visible fields of the struct that the locals, items and fields in
+    /// `segments` lead to
+    fn field_completions(&self, ctx: &Context<'_>, segments: &[Identifier]) -> Vec<Completion> {
+        let (path, members) = match self.receiver_type(ctx, segments).map(|ty| self.typeinfo(self.unboxed(ty))) {
+            Some(TypeInfo::Struct { full_path, members }) => (full_path, members),
+            _ => return Vec::new(),
+        };
+
+        let mut candidates: Vec<_> = members
+            .iter()
+            .filter(|&(&name, _)| self.is_field_visible(&self.current_path, path, name))
+            .map(|(&name, &ty)| Completion { name, kind: CompletionKind::Field, ty: Some(ty) })
+            .collect();
+
+        candidates.sort_by_key(|completion| completion.name.string());
+        candidates
+    }
+
+    /// The type of the value written as `segments` joined by `.`, starting
+    /// with a local or item and followed by fields
+    fn receiver_type(&self, ctx: &Context<'_>, segments: &[Identifier]) -> Option<TypeId> {
+        let (&first, fields) = segments.split_first()?;
+        let mut ty = match self.locals(ctx).get(&first) {
+            Some(&(_, binding)) => binding.typeid,
+            None => {
+                let def = self.resolver.resolve_path(&self.current_path, &Path::from_identifier(first))?;
+                self.type_of_def(def)?
+            }
+        };
+
+        for field in fields {
+            ty = match self.typeinfo(self.unboxed(ty)) {
+                TypeInfo::Struct { members, .. } => *members.get(field)?,
+                _ => return None,
+            };
+        }
+
+        Some(ty)
+    }
+
+    /// The locals in `ctx`, followed by the items that can be named from the
+    /// current path
+    fn scope_completions(&self, ctx: &Context<'_>) -> Vec<Completion> {
+        let mut locals: Vec<_> = self
+            .locals(ctx)
+            .into_iter()
+            .map(|(name, (_, binding))| Completion { name, kind: CompletionKind::Local, ty: Some(binding.typeid) })
+            .collect();
+        locals.sort_by_key(|completion| completion.name.string());
+
+        let mut items: Vec<_> = self
+            .resolver
+            .names_in_scope(&self.current_path)
+            .into_iter()
+            .map(|(name, id)| self.item_completion(name, id))
+            .collect();
+        items.sort_by_key(|completion| completion.name.string());
+
+        locals.into_iter().chain(items).collect()
+    }
+
+    /// The locals bound in `ctx` and its parents by name. Where several
+    /// locals share a name, the one bound last shadows the others.
+    fn locals(&self, ctx: &Context<'_>) -> HashMap<Identifier, (LocalId, BindingInfo)> {
+        let mut locals: HashMap<_, (LocalId, _)> = HashMap::new();
+        let mut current = Some(ctx);
+
+        while let Some(ctx) = current {
+            for (&id, &binding) in &ctx.bindings {
+                let name = self.resolver.local(id).name;
+                match locals.get(&name) {
+                    Some(&(shadowing, _)) if shadowing.0 > id.0 => {}
+                    _ => {
+                        locals.insert(name, (id, binding));
+                    }
+                }
+            }
+
+            current = ctx.parent;
+        }
+
+        locals
+    }
+
+    fn item_completion(&self, name: Identifier, id: hir::DefId) -> Completion {
+        let kind = self.resolver.def(id).kind;
+        let ty = match kind.is_value() {
+            true => self.type_of_def(id),
+            false => None,
+        };
+
+        Completion { name, kind: CompletionKind::Item(kind), ty }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codespan::Files;
+    use hir::{resolve::DefKind, Item, Statement, StatementKind};
+
+    /// Defines items and binds locals one input at a time, the way the REPL
+    /// does, to complete code written after them
+    struct Session {
+        engine: TypeEngine,
+        files: Files<String>,
+        bindings: HashMap<LocalId, BindingInfo>,
+    }
+
+    impl Session {
+        fn new() -> Self {
+            Self { engine: TypeEngine::new(), files: Files::new(), bindings: HashMap::new() }
+        }
+
+        fn item(&mut self, source: &str) {
+            let file = self.files.add("item", source.to_string());
+            let mut item = Item::convert(&parser::Parser::new(source).item().unwrap());
+            self.engine.resolver_mut().resolve_item(&mut item, file).unwrap();
+            self.engine.set_file(file);
+            self.engine.typecheck_item(&Context::new(), &item);
+            assert!(self.engine.take_errors().is_empty(), "`{}` failed to typecheck", source);
+        }
+
+        fn local(&mut self, source: &str) {
+            let file = self.files.add("local", source.to_string());
+            let mut statement = Statement::convert(&parser::Parser::new(source).statement().unwrap());
+            self.engine.resolver_mut().resolve_statement(&mut statement, file).unwrap();
+            self.engine.set_file(file);
+
+            let local = match &statement.kind {
+                StatementKind::Local(local) => local,
+                StatementKind::Expression(_) => panic!("`{}` isn't a `let`", source),
+            };
+
+            let ctx = Context { bindings: self.bindings.clone(), parent: None };
+            let typeid = self.engine.typecheck_local(&ctx, local);
+            assert!(self.engine.take_errors().is_empty(), "`{}` failed to typecheck", source);
+            self.bindings.insert(local.local_id(), BindingInfo { mutable: local.mutable, typeid });
+        }
+
+        /// The names that could complete the end of `source`, along with
+        /// where the name being completed starts
+        fn complete(&self, source: &str) -> (usize, Vec<(String, CompletionKind)>) {
+            let ctx = Context { bindings: self.bindings.clone(), parent: None };
+            let completions = self.engine.completions(&ctx, source, source.len());
+            let names = completions.candidates.iter().map(|c| (c.name.string(), c.kind)).collect();
+
+            (completions.start, names)
+        }
+
+        fn names(&self, source: &str) -> Vec<String> {
+            self.complete(source).1.into_iter().map(|(name, _)| name).collect()
+        }
+    }
+
+    #[test]
+    fn redefined_items_complete_as_their_latest_definition() {
+        let mut session = Session::new();
+        session.item("module m { pub fn f() -> Int { 1 } }");
+        session.item("module m { pub const f: Int = 2; }");
+        session.item("fn g() -> Int { 1 }");
+        session.item("const g: Int = 2;");
+
+        let (_, members) = session.complete("m::");
+        assert_eq!(members, [("f".to_string(), CompletionKind::Item(DefKind::Const))]);
+
+        let (_, names) = session.complete("g");
+        assert_eq!(names, [("g".to_string(), CompletionKind::Item(DefKind::Const))]);
+    }
+
+    fn segments(text: &str, separator: &str) -> Option<Vec<String>> {
+        trailing_segments(text, separator).map(|segments| segments.into_iter().map(Identifier::string).collect())
+    }
+
+    #[test]
+    fn split_identifier_splits_before_the_trailing_identifier() {
+        assert_eq!(split_identifier("let x = foo"), ("let x = ", "foo"));
+        assert_eq!(split_identifier("a::"), ("a::", ""));
+        assert_eq!(split_identifier("x.größe_2"), ("x.", "größe_2"));
+        assert_eq!(split_identifier(""), ("", ""));
+    }
+
+    #[test]
+    fn trailing_segments_are_the_names_before_the_separator() {
+        assert_eq!(segments("a::b::", "::"), Some(vec!["a".to_string(), "b".to_string()]));
+        assert_eq!(segments("1 + x.y.", "."), Some(vec!["x".to_string(), "y".to_string()]));
+        assert_eq!(segments("a::b", "::"), None);
+        assert_eq!(segments("foo().", "."), None);
+    }
+
+    #[test]
+    fn module_members_complete_after_a_path() {
+        let mut session = Session::new();
+        session.item("module a { pub module b { pub fn c() {} pub fn cd() {} fn hidden() {} } }");
+
+        assert_eq!(session.names("a::b::"), ["c", "cd"]);
+        assert_eq!(
+            session.complete("a::b::cd"),
+            (6, vec![("cd".to_string(), CompletionKind::Item(DefKind::Function))])
+        );
+    }
+
+    #[test]
+    fn fields_complete_after_a_local() {
+        let mut session = Session::new();
+        session.item("struct T { z: Int, zz: Int }");
+        session.item("struct S { y: T }");
+        session.local("let x = S { y: T { z: 1, zz: 2 } };");
+
+        let (start, fields) = session.complete("x.y.z");
+        assert_eq!(start, 4);
+        assert_eq!(fields, [("z".to_string(), CompletionKind::Field), ("zz".to_string(), CompletionKind::Field)]);
+    }
+
+    #[test]
+    fn names_in_scope_complete_a_partial_name() {
+        let mut session = Session::new();
+        session.item("fn abc() {}");
+        session.item("fn abd() {}");
+        session.item("fn xyz() {}");
+        session.local("let abe = 1;");
+
+        assert_eq!(session.complete("1 + ab").0, 4);
+        assert_eq!(session.names("1 + ab"), ["abe", "abc", "abd"]);
+    }
+
+    #[test]
+    fn non_ascii_names_complete() {
+        let mut session = Session::new();
+        session.item("fn größe() -> Int { 1 }");
+
+        assert_eq!(session.complete("grö"), (0, vec![("größe".to_string(), CompletionKind::Item(DefKind::Function))]));
+    }
+
+    #[test]
+    fn nothing_completes_after_a_call() {
+        let mut session = Session::new();
+        session.item("struct S { x: Int }");
+        session.item("fn foo() -> S { S { x: 1 } }");
+
+        assert!(session.names("foo().").is_empty());
+    }
+}
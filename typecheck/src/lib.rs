@@ -1,11 +1,23 @@
+#![allow(clippy::result_large_err)]
+
+mod completion;
+mod union_find;
+
+use codespan::{FileId, Span};
+use hir::visit::{walk, Visitor};
 use hir::{
-    BinOp, Block, Expression, ExpressionKind, Function, Identifier, Item, ItemKind, Path, Statement, StatementKind,
-    Struct, StructExpr, Type, TypeKind, UnaryOp,
+    resolve::{self, DefKind, Resolver},
+    snapshot::{MapSnapshot, SnapshotMap, SnapshotVec, VecSnapshot},
+    BinOp, Block, DefId, ExprId, Expression, ExpressionKind, Function, Identifier, Item, ItemKind, Local, LocalId,
+    Path, Res, Statement, StatementKind, Struct, Type, TypeKind, UnaryOp, VisibilityKind,
 };
 use std::{
     collections::HashMap,
-    fmt::{self, Debug, Formatter},
+    fmt::{self, Debug, Display, Formatter},
 };
+use union_find::UnionFind;
+
+pub use completion::{Completion, CompletionKind, Completions};
 
 pub type Result<T> = std::result::Result<T, TypeError>;
 pub type TypeId = usize;
@@ -18,109 +30,285 @@ pub struct BindingInfo {
 
 #[derive(Default)]
 pub struct Context<'a> {
-    pub aliases: HashMap<Path, Path>,
-    pub bindings: HashMap<Identifier, BindingInfo>,
+    pub bindings: HashMap<LocalId, BindingInfo>,
     pub parent: Option<&'a Context<'a>>,
 }
 
 impl<'a> Context<'a> {
     pub fn new() -> Context<'static> {
-        Context { aliases: HashMap::new(), bindings: HashMap::new(), parent: None }
+        Context { bindings: HashMap::new(), parent: None }
     }
 
     pub fn new_child<'b: 'a>(&'b self) -> Context<'a> {
-        Self { aliases: HashMap::new(), bindings: HashMap::new(), parent: Some(self) }
-    }
-
-    pub fn new_path_alias(&mut self, original: Path, alias: Path) {
-        self.aliases.insert(original, alias);
+        Self { bindings: HashMap::new(), parent: Some(self) }
     }
 
-    pub fn new_binding(&mut self, ident: Identifier, binding: BindingInfo) {
-        self.bindings.insert(ident, binding);
+    pub fn new_binding(&mut self, id: LocalId, binding: BindingInfo) {
+        self.bindings.insert(id, binding);
     }
 
-    pub fn resolve_path_alias(&self, path: &Path) -> Option<&Path> {
-        match self.aliases.get(path) {
-            Some(path) => Some(path),
-            None => match &self.parent {
-                Some(parent) => parent.resolve_path_alias(path),
-                None => None,
-            },
-        }
-    }
-
-    pub fn resolve_binding(&self, ident: Identifier) -> Option<BindingInfo> {
-        match self.bindings.get(&ident) {
+    pub fn resolve_binding(&self, id: LocalId) -> Option<BindingInfo> {
+        match self.bindings.get(&id) {
             Some(binding) => Some(*binding),
             None => match &self.parent {
-                Some(parent) => parent.resolve_binding(ident),
+                Some(parent) => parent.resolve_binding(id),
                 None => None,
             },
         }
     }
 }
 
-pub enum TypeError {
+/// Items that name a value rather than a type or function
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Global {
+    Const,
+    Static { mutable: bool },
+}
+
+#[derive(Debug, Clone)]
+pub enum TypeErrorKind {
+    AssignToConst(Path),
     CannotInferType,
-    MismatchedTypes { wanted: TypeInfo, have: TypeInfo },
+    InfiniteType(TypeInfo),
+    /// A struct that contains itself, through the fields in `cycle`, which
+    /// starts with one of its own fields
+    InfinitelySized {
+        strukt: Path,
+        cycle: Vec<Path>,
+    },
+    /// A function type's parameter, counting from zero, didn't match
+    MismatchedParameter {
+        index: usize,
+        wanted: TypeInfo,
+        have: TypeInfo,
+    },
+    MismatchedReturnType {
+        wanted: TypeInfo,
+        have: TypeInfo,
+    },
+    MismatchedTypes {
+        wanted: TypeInfo,
+        have: TypeInfo,
+    },
+    MissingFields {
+        strukt: Path,
+        fields: Vec<Identifier>,
+    },
     NoField(TypeInfo, Identifier),
+    NotAStruct(Path),
     NotCallable(TypeInfo),
-    NotEnoughArgs,
+    NotEnoughArgs {
+        expected: usize,
+        found: usize,
+    },
     NotMutable(Identifier),
+    NotMutableStatic(Path),
     NotValidRhs,
-    TooManyArgs,
-    UnknownBinOp { lhs: TypeInfo, op: BinOp, rhs: TypeInfo },
+    PrivateField {
+        strukt: Path,
+        field: Identifier,
+    },
+    RecursiveTypeAlias(Path),
+    TooManyArgs {
+        expected: usize,
+        found: usize,
+    },
+    UnknownBinOp {
+        lhs: TypeInfo,
+        op: BinOp,
+        rhs: TypeInfo,
+    },
+    UnknownFields {
+        strukt: Path,
+        fields: Vec<Identifier>,
+    },
     UnknownIdentifier(Identifier),
     UnknownType(Path),
-    UnknownUnaryOp { op: UnaryOp, info: TypeInfo },
+    UnknownUnaryOp {
+        op: UnaryOp,
+        info: TypeInfo,
+    },
+    /// Code after an expression that never finishes, which is only a warning
+    UnreachableExpression,
+    UnreachableStatement,
+}
+
+/// A location related to a type error other than where it occurred, such as
+/// where an expected type came from
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub file: Option<FileId>,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(file: Option<FileId>, span: Span, message: impl Into<String>) -> Self {
+        Self { file, span, message: message.into() }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeError {
+    pub kind: TypeErrorKind,
+    /// The file and span of the code the error is about
+    pub file: Option<FileId>,
+    pub span: Span,
+    pub labels: Vec<Label>,
 }
 
 impl TypeError {
+    pub fn new(kind: TypeErrorKind, file: Option<FileId>, span: Span) -> Self {
+        Self { kind, file, span, labels: Vec::new() }
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
     pub fn debug<'a>(&'a self, engine: &'a TypeEngine) -> TypeErrorDebug<'a> {
-        TypeErrorDebug { error: self, engine }
+        TypeErrorDebug { error: &self.kind, engine }
     }
 }
 
 pub struct TypeErrorDebug<'a> {
-    error: &'a TypeError,
+    error: &'a TypeErrorKind,
     engine: &'a TypeEngine,
 }
 
 impl Debug for TypeErrorDebug<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match &self.error {
-            TypeError::NoField(info, field) => write!(f, "No field `{}` on type {}", field, info.name(self.engine)),
-            TypeError::UnknownType(id) => write!(f, "Unknown type: `{}`", id.to_string()),
-            TypeError::MismatchedTypes { wanted, have } => write!(
+            TypeErrorKind::NoField(info, field) => write!(f, "No field `{}` on type {}", field, info.name(self.engine)),
+            TypeErrorKind::UnknownType(id) => write!(f, "Unknown type: `{}`", id),
+            TypeErrorKind::MismatchedTypes { wanted, have } => write!(
                 f,
                 "Type mismatch: expected `{}`, but found `{}`",
                 wanted.name(self.engine),
                 have.name(self.engine)
             ),
-            TypeError::UnknownBinOp { lhs, op, rhs } => {
-                write!(f, "No implmentation for `{}` {} `{}`", lhs.name(self.engine), op, rhs.name(self.engine))
+            TypeErrorKind::MismatchedParameter { index, wanted, have } => write!(
+                f,
+                "Type mismatch in parameter {}: expected `{}`, but found `{}`",
+                index + 1,
+                wanted.name(self.engine),
+                have.name(self.engine)
+            ),
+            TypeErrorKind::MismatchedReturnType { wanted, have } => write!(
+                f,
+                "Type mismatch in return type: expected `{}`, but found `{}`",
+                wanted.name(self.engine),
+                have.name(self.engine)
+            ),
+            TypeErrorKind::UnknownBinOp { lhs, op, rhs } => {
+                write!(f, "No implementation for `{}` {} `{}`", lhs.name(self.engine), op, rhs.name(self.engine))
+            }
+            TypeErrorKind::UnknownIdentifier(ident) => write!(f, "Unknown identifier `{}`", ident),
+            TypeErrorKind::NotValidRhs => write!(f, "Not a valid right hand side expression"),
+            TypeErrorKind::PrivateField { strukt, field } => {
+                write!(f, "Field `{}` of struct `{}` is private", field, strukt)
+            }
+            TypeErrorKind::NotCallable(info) => write!(f, "Type `{}` is not a function", info.name(self.engine)),
+            TypeErrorKind::TooManyArgs { expected, found } | TypeErrorKind::NotEnoughArgs { expected, found } => {
+                write!(
+                    f,
+                    "Function takes {} {} but {} {} supplied",
+                    expected,
+                    plural("argument", *expected),
+                    found,
+                    if *found == 1 { "was" } else { "were" }
+                )
             }
-            TypeError::UnknownIdentifier(ident) => write!(f, "(TypeError) Unknown identifier `{}`", ident),
-            TypeError::NotValidRhs => write!(f, "Not a valid right hand side expression"),
-            TypeError::NotCallable(info) => write!(f, "Type `{}` is not a function", info.name(self.engine)),
-            TypeError::TooManyArgs => write!(f, "Too many arguments <todo: fn stuff>"),
-            TypeError::NotEnoughArgs => write!(f, "Too few arguments <todo: fn stuff>"),
-            TypeError::NotMutable(ident) => write!(f, "Local `{}` was not declared as mutable", ident),
-            TypeError::UnknownUnaryOp { op, info } => {
-                write!(f, "No implmentation for {}(`{}`)", op, info.name(self.engine))
+            TypeErrorKind::NotMutable(ident) => write!(f, "Local `{}` was not declared as mutable", ident),
+            TypeErrorKind::NotMutableStatic(path) => write!(f, "Static `{}` was not declared as mutable", path),
+            TypeErrorKind::AssignToConst(path) => write!(f, "Cannot assign to constant `{}`", path),
+            TypeErrorKind::UnknownUnaryOp { op, info } => {
+                write!(f, "No implementation for {}(`{}`)", op, info.name(self.engine))
             }
-            TypeError::CannotInferType => write!(f, "Cannot infer type"),
+            TypeErrorKind::CannotInferType => write!(f, "Cannot infer type"),
+            TypeErrorKind::InfiniteType(info) => {
+                write!(f, "Cannot construct the infinite type `{}`", info.name(self.engine))
+            }
+            TypeErrorKind::InfinitelySized { strukt, cycle } => {
+                let cycle: Vec<_> = cycle.iter().map(|field| format!("`{}`", field)).collect();
+                write!(
+                    f,
+                    "Recursive struct `{}` has infinite size, as it contains itself through {}",
+                    strukt,
+                    cycle.join(" -> ")
+                )
+            }
+            TypeErrorKind::RecursiveTypeAlias(path) => write!(f, "Type alias `{}` refers to itself", path),
+            TypeErrorKind::NotAStruct(path) => write!(f, "`{}` is not a struct", path),
+            TypeErrorKind::MissingFields { strukt, fields } => {
+                write!(f, "Missing {} {} in initializer of `{}`", plural("field", fields.len()), list(fields), strukt)
+            }
+            TypeErrorKind::UnknownFields { strukt, fields } => {
+                write!(f, "Struct `{}` has no {} named {}", strukt, plural("field", fields.len()), list(fields))
+            }
+            TypeErrorKind::UnreachableExpression => write!(f, "Unreachable expression"),
+            TypeErrorKind::UnreachableStatement => write!(f, "Unreachable statement"),
         }
     }
 }
 
+fn plural(word: &str, count: usize) -> String {
+    match count {
+        1 => word.to_string(),
+        _ => format!("{}s", word),
+    }
+}
+
+fn list<T: Display>(items: &[T]) -> String {
+    items.iter().map(|item| format!("`{}`", item)).collect::<Vec<_>>().join(", ")
+}
+
 #[derive(Debug, Clone)]
 pub struct TypeEngine {
-    types: Vec<TypeInfo>,
-    name_map: HashMap<Path, TypeId>,
+    /// The information known about each type, which is only kept up to date
+    /// for the root of each set of unified types
+    types: SnapshotVec<TypeInfo>,
+    /// The sets of types that have been unified with each other. Types other
+    /// than type variables are always the root of their set.
+    unified: UnionFind,
+    /// Types that are the same whenever their contents are, so only need to
+    /// exist once
+    interned: SnapshotMap<TypeKey, TypeId>,
+    builtins: Builtins,
+    /// Types that have to be known once the code currently being checked has
+    /// been, with the code they're the type of
+    inferred: Vec<(TypeId, Option<FileId>, Span)>,
+    /// Type variables that were expected to hold a value of type `!`, which
+    /// they become if nothing else is known about them
+    diverging: Vec<TypeId>,
+    /// Whether the code checked so far within the current expression always
+    /// diverges, so never finishes
+    diverges: bool,
+    /// The types of the items typechecked so far
+    def_types: SnapshotMap<DefId, TypeId>,
+    /// The types of the expressions and locals typechecked so far, which are
+    /// only final once the item or input they're in has been checked
+    expr_types: SnapshotMap<ExprId, TypeId>,
+    local_types: SnapshotMap<LocalId, TypeId>,
+    /// The parameters of each function as they were declared, since the type
+    /// of the function may be shared with other functions
+    parameter_names: SnapshotMap<DefId, Vec<Identifier>>,
+    /// The visibilities of struct fields, keyed by the struct's path joined
+    /// with the field's name
+    field_visibilities: SnapshotMap<Path, VisibilityKind>,
+    /// The types of struct fields as written, by the path of the field, along
+    /// with the file the struct is in
+    field_types: SnapshotMap<Path, (Option<FileId>, Type)>,
+    resolver: Resolver,
     current_path: Path,
-    unnamable_count: usize,
+    /// The file errors point into
+    current_file: Option<FileId>,
+    /// The errors reported since they were last taken
+    errors: Vec<TypeError>,
+    /// Problems that don't stop the code from being run, reported since they
+    /// were last taken
+    warnings: Vec<TypeError>,
 }
 
 impl TypeEngine {
@@ -128,36 +316,114 @@ impl TypeEngine {
         Self::default()
     }
 
+    pub fn resolver(&self) -> &Resolver {
+        &self.resolver
+    }
+
+    pub fn resolver_mut(&mut self) -> &mut Resolver {
+        &mut self.resolver
+    }
+
+    /// Sets the file that the code typechecked next was written in. Items
+    /// within it are checked in the file they were defined in.
+    pub fn set_file(&mut self, file: FileId) {
+        self.current_file = Some(file);
+    }
+
+    /// Returns the type of the expression `id`, if it's been typechecked
+    pub fn type_of_expr(&self, id: ExprId) -> Option<TypeId> {
+        self.expr_types.get(&id).copied()
+    }
+
+    /// Returns the type of the local or function parameter `id`, if it's been
+    /// typechecked
+    pub fn type_of_local(&self, id: LocalId) -> Option<TypeId> {
+        self.local_types.get(&id).copied()
+    }
+
+    /// Takes the errors reported since they were last taken, in the order they
+    /// were found
+    pub fn take_errors(&mut self) -> Vec<TypeError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Records `error` and returns the error type, which stands in for the
+    /// type of whatever couldn't be typechecked so checking can continue
+    fn report(&mut self, error: TypeError) -> TypeId {
+        self.errors.push(error);
+        self.error_type()
+    }
+
+    /// Takes the warnings reported since they were last taken, in the order
+    /// they were found
+    pub fn take_warnings(&mut self) -> Vec<TypeError> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Returns the type of the item `def`, if it's been typechecked
+    pub fn type_of_def(&self, def: DefId) -> Option<TypeId> {
+        self.def_types.get(&def).copied()
+    }
+
+    /// Returns the type information for `id`, following type aliases
     pub fn typeinfo(&self, id: TypeId) -> &TypeInfo {
-        match &self.types[id] {
-            TypeInfo::Ref(r) => self.typeinfo(*r),
+        match &self.types[self.unified.find(id)] {
+            TypeInfo::Alias { aliased, .. } => self.typeinfo(*aliased),
             info => info,
         }
     }
 
-    pub fn unify(&mut self, ctx: &Context<'_>, want: TypeId, have: TypeId) -> Result<TypeId> {
-        match (self.types[want].clone(), self.types[have].clone()) {
-            (TypeInfo::Bool, TypeInfo::Bool) => Ok(self.bool()),
-            (TypeInfo::Integer, TypeInfo::Integer) => Ok(self.integer()),
-            (TypeInfo::Unit, TypeInfo::Unit) => Ok(self.unit()),
+    /// Returns the type information for `id`, without following type aliases
+    pub fn declared_typeinfo(&self, id: TypeId) -> &TypeInfo {
+        &self.types[self.unified.find(id)]
+    }
+
+    pub fn unify(&mut self, want: TypeId, have: TypeId) -> std::result::Result<TypeId, TypeErrorKind> {
+        let (want, have) = (self.unified.find(want), self.unified.find(have));
+        if want == have {
+            return Ok(want);
+        }
+
+        match (&self.types[want], &self.types[have]) {
+            // `!` has no values, so can stand in for any type. Type variables
+            // are left to whatever else is known about them.
+            (TypeInfo::Infer, TypeInfo::Never) => {
+                self.diverging.push(want);
+                Ok(want)
+            }
+            (_, TypeInfo::Never) => Ok(want),
+            (TypeInfo::Infer, _) => self.link(want, have),
+            (_, TypeInfo::Infer) => self.link(have, want),
+            // The error has already been reported
+            (TypeInfo::Error, _) | (_, TypeInfo::Error) => Ok(self.error_type()),
+            (TypeInfo::Bool, TypeInfo::Bool)
+            | (TypeInfo::Integer, TypeInfo::Integer)
+            | (TypeInfo::Unit, TypeInfo::Unit) => Ok(want),
+            (&TypeInfo::Box(boxed1), &TypeInfo::Box(boxed2)) => {
+                self.unify(boxed1, boxed2).map_err(|e| match e {
+                    // The boxes are what didn't match, not what's in them
+                    TypeErrorKind::MismatchedTypes { .. } => self.mismatch(want, have),
+                    e => e,
+                })?;
+
+                Ok(want)
+            }
             (
-                TypeInfo::Struct { full_path: full_path1, members: members1, .. },
-                TypeInfo::Struct { full_path: full_path2, members: members2, .. },
+                TypeInfo::Struct { full_path: full_path1, members: members1 },
+                TypeInfo::Struct { full_path: full_path2, members: members2 },
             ) => {
-                let full_path1 = ctx.resolve_path_alias(&full_path1).unwrap_or(&full_path1);
-                let full_path2 = ctx.resolve_path_alias(&full_path2).unwrap_or(&full_path2);
+                // A struct redefined in the REPL keeps its path but may have
+                // different fields
+                let same_fields =
+                    members1.len() == members2.len() && members1.keys().all(|field| members2.contains_key(field));
 
-                if full_path1 != full_path2 {
-                    return Err(TypeError::MismatchedTypes {
-                        wanted: self.types[want].clone(),
-                        have: self.types[have].clone(),
-                    });
+                if full_path1 != full_path2 || !same_fields {
+                    return Err(self.mismatch(want, have));
                 }
 
-                for (i, &a) in members1.iter() {
-                    let b = members2[i];
-
-                    self.unify(ctx, a, b)?;
+                let fields: Vec<_> = members1.iter().map(|(field, &a)| (a, members2[field])).collect();
+                for (a, b) in fields {
+                    self.unify(a, b)?;
                 }
 
                 Ok(want)
@@ -166,401 +432,1441 @@ impl TypeEngine {
                 TypeInfo::Function { parameters: parameters1, return_type: return_type1 },
                 TypeInfo::Function { parameters: parameters2, return_type: return_type2 },
             ) => {
-                match parameters1.len().cmp(&parameters2.len()) {
-                    std::cmp::Ordering::Less => return Err(TypeError::TooManyArgs),
-                    std::cmp::Ordering::Greater => return Err(TypeError::NotEnoughArgs),
-                    _ => {}
+                if parameters1.len() != parameters2.len() {
+                    return Err(self.mismatch(want, have));
+                }
+
+                let parameters: Vec<_> = parameters1.iter().copied().zip(parameters2.iter().copied()).collect();
+                let (return_type1, return_type2) = (*return_type1, *return_type2);
+
+                for (index, (a, b)) in parameters.into_iter().enumerate() {
+                    self.unify(a, b).map_err(|e| match e {
+                        TypeErrorKind::MismatchedTypes { wanted, have } => {
+                            TypeErrorKind::MismatchedParameter { index, wanted, have }
+                        }
+                        e => e,
+                    })?;
                 }
 
-                self.unify(ctx, return_type1, return_type2)?;
+                self.unify(return_type1, return_type2).map_err(|e| match e {
+                    TypeErrorKind::MismatchedTypes { wanted, have } => {
+                        TypeErrorKind::MismatchedReturnType { wanted, have }
+                    }
+                    e => e,
+                })?;
 
                 Ok(want)
             }
-            (TypeInfo::Infer, TypeInfo::Infer) => Err(TypeError::CannotInferType),
-            (TypeInfo::Infer, _) => {
-                self.types[want] = TypeInfo::Ref(have);
-                Ok(have)
+            // Mismatches report the alias rather than the type it expands to
+            (TypeInfo::Alias { aliased, .. }, _) => self.unify(*aliased, have).map_err(|e| match e {
+                TypeErrorKind::MismatchedTypes { have, .. } => {
+                    TypeErrorKind::MismatchedTypes { wanted: self.types[want].clone(), have }
+                }
+                e => e,
+            }),
+            (_, TypeInfo::Alias { aliased, .. }) => self.unify(want, *aliased).map_err(|e| match e {
+                TypeErrorKind::MismatchedTypes { wanted, .. } => {
+                    TypeErrorKind::MismatchedTypes { wanted, have: self.types[have].clone() }
+                }
+                e => e,
+            }),
+            _ => Err(self.mismatch(want, have)),
+        }
+    }
+
+    fn mismatch(&self, want: TypeId, have: TypeId) -> TypeErrorKind {
+        TypeErrorKind::MismatchedTypes { wanted: self.types[want].clone(), have: self.types[have].clone() }
+    }
+
+    /// Unifies the type variable `var` with `ty`, both of which must be roots,
+    /// so that `ty` is what's known about both
+    fn link(&mut self, var: TypeId, ty: TypeId) -> std::result::Result<TypeId, TypeErrorKind> {
+        if let TypeInfo::Infer = self.types[ty] {
+            return Ok(self.unified.union(var, ty));
+        }
+
+        if self.occurs(var, ty) {
+            return Err(TypeErrorKind::InfiniteType(self.types[ty].clone()));
+        }
+
+        self.unified.attach(var, ty);
+        Ok(ty)
+    }
+
+    /// Whether the type variable `var` appears within `ty`, in which case
+    /// unifying them would make an infinitely large type. Structs are named
+    /// rather than structural, so their members aren't searched.
+    fn occurs(&self, var: TypeId, ty: TypeId) -> bool {
+        let ty = self.unified.find(ty);
+
+        ty == var
+            || match &self.types[ty] {
+                TypeInfo::Function { parameters, return_type } => {
+                    parameters.iter().any(|&param| self.occurs(var, param)) || self.occurs(var, *return_type)
+                }
+                TypeInfo::Alias { aliased, .. } | TypeInfo::Box(aliased) => self.occurs(var, *aliased),
+                _ => false,
             }
-            (_, TypeInfo::Infer) => {
-                self.types[have] = TypeInfo::Ref(want);
-                Ok(want)
+    }
+
+    /// Unifies `want` with `have`, reporting any error at `span`
+    fn unify_at(&mut self, want: TypeId, have: TypeId, span: Span) -> Result<TypeId> {
+        self.unify(want, have).map_err(|kind| self.error(kind, span))
+    }
+
+    /// Creates an error pointing at `span` in the current file
+    fn error(&self, kind: TypeErrorKind, span: Span) -> TypeError {
+        TypeError::new(kind, self.current_file, span)
+    }
+
+    /// Creates a label pointing at `span` in the current file
+    fn label(&self, span: Span, message: impl Into<String>) -> Label {
+        Label::new(self.current_file, span, message)
+    }
+
+    /// Creates a label pointing at the name of the item `def`
+    fn def_label(&self, def: DefId, message: impl Into<String>) -> Label {
+        let def = self.resolver.def(def);
+        Label::new(def.file, def.name().span, message)
+    }
+
+    pub fn fresh_infer(&mut self) -> TypeId {
+        self.new_type(TypeInfo::Infer)
+    }
+
+    fn new_type(&mut self, info: TypeInfo) -> TypeId {
+        self.types.push(info);
+        self.unified.push()
+    }
+
+    /// Replaces what's known about `id` and everything unified with it
+    fn set_typeinfo(&mut self, id: TypeId, info: TypeInfo) {
+        let root = self.unified.find(id);
+        self.types.set(root, info);
+    }
+
+    /// Runs `f`, then reports any of the types it recorded in `inferred` that
+    /// are still unknown, since nothing else will make them known
+    fn inferring<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let inferred = std::mem::take(&mut self.inferred);
+        let diverging = std::mem::take(&mut self.diverging);
+        let diverges = std::mem::replace(&mut self.diverges, false);
+        let res = f(self);
+        self.zonk();
+        self.inferred = inferred;
+        self.diverging = diverging;
+        self.diverges = diverges;
+
+        res
+    }
+
+    fn zonk(&mut self) {
+        for id in std::mem::take(&mut self.diverging) {
+            if let TypeInfo::Infer = self.declared_typeinfo(id) {
+                let _ = self.unify(self.never(), id);
+            }
+        }
+
+        for (id, file, span) in std::mem::take(&mut self.inferred) {
+            if let TypeInfo::Infer = self.declared_typeinfo(id) {
+                self.report(TypeError::new(TypeErrorKind::CannotInferType, file, span));
+                // Anything else of the same type has been reported now too
+                let _ = self.unify(id, self.error_type());
             }
-            (TypeInfo::Ref(a), _) => self.unify(ctx, a, have),
-            (_, TypeInfo::Ref(b)) => self.unify(ctx, want, b),
-            (a, b) => Err(TypeError::MismatchedTypes { wanted: a, have: b }),
         }
     }
 
-    pub fn fresh_infer(&mut self) -> TypeId {
-        self.types.push(TypeInfo::Infer);
-        self.types.len() - 1
+    /// Typechecks `expr` against `expected`, reporting any errors and
+    /// returning the error type in place of the type of `expr` if it couldn't
+    /// be checked
+    pub fn typecheck_expression(&mut self, ctx: &Context<'_>, expr: &Expression, expected: TypeId) -> TypeId {
+        let (res, diverges) = self.diverges_in(|engine| engine.check_expression(ctx, expr, expected));
+        self.diverges |= diverges;
+
+        let id = match res {
+            Ok(id) => id,
+            Err(error) => {
+                let id = self.report(error);
+                // Anything waiting on the type of `expr` is an error too
+                let _ = self.unify(expected, id);
+                id
+            }
+        };
+
+        self.expr_types.insert(expr.expr_id(), id);
+        id
     }
 
-    pub fn typecheck_expression(&mut self, ctx: &Context<'_>, expr: &Expression, expected: TypeId) -> Result<TypeId> {
+    /// Typechecks `expr`, returning the error that stops it from being checked.
+    /// Errors in its subexpressions are reported as they're found.
+    fn check_expression(&mut self, ctx: &Context<'_>, expr: &Expression, expected: TypeId) -> Result<TypeId> {
         match &expr.kind {
-            ExpressionKind::Integer(_) => self.unify(ctx, expected, self.integer()),
-            ExpressionKind::Boolean(_) => self.unify(ctx, expected, self.bool()),
-            ExpressionKind::Block(block) => self.typecheck_block(ctx, block, expected),
-            ExpressionKind::Unit => self.unify(ctx, expected, self.unit()),
+            ExpressionKind::Integer(_) => self.unify_at(expected, self.integer(), expr.span),
+            ExpressionKind::Boolean(_) => self.unify_at(expected, self.bool(), expr.span),
+            ExpressionKind::Block(block) => Ok(self.typecheck_block(ctx, block, expected)),
+            ExpressionKind::Unit => self.unify_at(expected, self.unit(), expr.span),
             ExpressionKind::FnCall(lhs, args) => {
                 let infer = self.fresh_infer();
-                let fn_id = self.typecheck_expression(ctx, lhs, infer)?;
+                let fn_id = self.typecheck_expression(ctx, lhs, infer);
+
+                // Functions called by name can point at their signature
+                let callee = match &lhs.kind {
+                    ExpressionKind::Path(path) => match path.resolution() {
+                        Res::Def(def) => Some(def),
+                        Res::Local(_) => None,
+                    },
+                    _ => None,
+                };
 
                 match self.typeinfo(fn_id).clone() {
                     TypeInfo::Function { parameters, return_type } => {
-                        match parameters.len().cmp(&args.len()) {
-                            std::cmp::Ordering::Less => return Err(TypeError::TooManyArgs),
-                            std::cmp::Ordering::Greater => return Err(TypeError::NotEnoughArgs),
-                            _ => {}
+                        let (expected_args, found) = (parameters.len(), args.len());
+                        let kind = match expected_args.cmp(&found) {
+                            std::cmp::Ordering::Less => {
+                                Some(TypeErrorKind::TooManyArgs { expected: expected_args, found })
+                            }
+                            std::cmp::Ordering::Greater => {
+                                Some(TypeErrorKind::NotEnoughArgs { expected: expected_args, found })
+                            }
+                            std::cmp::Ordering::Equal => None,
+                        };
+
+                        if let Some(kind) = kind {
+                            let mut error = self.error(kind, expr.span);
+                            if let Some(def) = callee {
+                                error = error.with_label(self.def_label(def, "function defined here"));
+                            }
+
+                            self.report(error);
+                        }
+
+                        // The arguments that do line up with a parameter can
+                        // still be checked against it
+                        for (index, (&id, arg)) in parameters.iter().zip(args.iter()).enumerate() {
+                            let label = callee.and_then(|def| {
+                                let param = self.parameter_names.get(&def)?[index];
+                                Some(Label::new(self.resolver.def(def).file, param.span, "parameter declared here"))
+                            });
+                            self.typecheck_against(ctx, arg, id, label);
+                        }
+
+                        for arg in args.iter().skip(expected_args) {
+                            let infer = self.fresh_infer();
+                            self.typecheck_expression(ctx, arg, infer);
                         }
 
-                        for ((_, id), arg) in parameters.iter().zip(args.iter()) {
-                            self.typecheck_expression(ctx, arg, *id)?;
+                        self.produces(return_type);
+                        self.unify_at(expected, return_type, expr.span)
+                    }
+                    info => {
+                        for arg in args {
+                            let infer = self.fresh_infer();
+                            self.typecheck_expression(ctx, arg, infer);
                         }
 
-                        self.unify(ctx, expected, return_type)
+                        match info {
+                            TypeInfo::Error => self.unify_at(expected, fn_id, expr.span),
+                            info => Err(self.error(TypeErrorKind::NotCallable(info), lhs.span)),
+                        }
                     }
-                    _ => Err(TypeError::NotCallable(self.typeinfo(fn_id).clone())),
                 }
             }
             ExpressionKind::Struct(struct_expr) => {
-                let have = self.gen_struct_typeinfo(ctx, struct_expr)?;
-                let want = match self.resolve_two_way(ctx, &struct_expr.name) {
-                    Some(id) => id,
-                    None => return Err(TypeError::UnknownType(struct_expr.name.clone())),
+                let name_span = struct_expr.name.span();
+                // Struct literals can name the struct through an alias
+                let found = self.expand_aliases(&struct_expr.name).map(|id| (id, self.typeinfo(id).clone()));
+                let (want, full_path, members) = match found {
+                    Some((id, TypeInfo::Struct { full_path, members })) => (id, full_path, members),
+                    found => {
+                        // The fields still get checked for errors of their own
+                        let fields = struct_expr.members.iter().map(|member| &member.expression);
+                        for field in fields.chain(struct_expr.base.as_deref()) {
+                            let infer = self.fresh_infer();
+                            self.typecheck_expression(ctx, field, infer);
+                        }
+
+                        let kind = match found {
+                            Some(_) => TypeErrorKind::NotAStruct(struct_expr.name.clone()),
+                            None => TypeErrorKind::UnknownType(struct_expr.name.clone()),
+                        };
+
+                        return Err(self.error(kind, name_span));
+                    }
                 };
+                let struct_file = self.resolver.lookup(&full_path).and_then(|def| self.resolver.def(def).file);
 
-                self.unify(ctx, want, have)?;
-                self.unify(ctx, want, expected)
+                let unknown: Vec<_> = struct_expr
+                    .members
+                    .iter()
+                    .map(|member| member.name)
+                    .filter(|name| !members.contains_key(name))
+                    .collect();
+
+                if !unknown.is_empty() {
+                    let labels = unknown.iter().map(|field| self.label(field.span, "unknown field")).collect();
+                    let kind = TypeErrorKind::UnknownFields { strukt: full_path.clone(), fields: unknown };
+                    self.report(TypeError { labels, ..self.error(kind, name_span) });
+                }
+
+                for member in &struct_expr.members {
+                    // The key is the field's name as it was declared
+                    let (&declared, &id) = match members.get_key_value(&member.name) {
+                        Some(field) => field,
+                        None => {
+                            let infer = self.fresh_infer();
+                            self.typecheck_expression(ctx, &member.expression, infer);
+                            continue;
+                        }
+                    };
+                    let label = Label::new(struct_file, declared.span, "field declared here");
+
+                    if let Err(error) =
+                        self.check_field_visibility(&self.current_path, &full_path, member.name, member.name.span)
+                    {
+                        self.report(error.with_label(label.clone()));
+                    }
+
+                    self.typecheck_against(ctx, &member.expression, id, Some(label));
+                }
+
+                match &struct_expr.base {
+                    // Fields taken from the base still have to be visible here
+                    Some(base) => {
+                        self.typecheck_expression(ctx, base, want);
+
+                        for &field in members.keys() {
+                            if struct_expr.members.iter().all(|member| member.name != field) {
+                                if let Err(error) =
+                                    self.check_field_visibility(&self.current_path, &full_path, field, base.span)
+                                {
+                                    self.report(error);
+                                }
+                            }
+                        }
+                    }
+                    None => {
+                        let mut missing: Vec<_> = members
+                            .keys()
+                            .copied()
+                            .filter(|&field| struct_expr.members.iter().all(|member| member.name != field))
+                            .collect();
+
+                        // The literal still has the type of the struct
+                        if !missing.is_empty() {
+                            missing.sort_by_key(|field| field.to_string());
+                            let kind = TypeErrorKind::MissingFields { strukt: full_path, fields: missing };
+                            self.report(self.error(kind, expr.span));
+                        }
+                    }
+                }
+
+                self.unify_at(expected, want, expr.span)
             }
-            ExpressionKind::Path(path) => match path.is_identifier() {
-                Some(ident) => match ctx.resolve_binding(ident) {
-                    Some(binding) => Ok(binding.typeid),
-                    None => match self.typeid_from_path(ctx, path) {
-                        Some(id) => match self.typeinfo(id) {
-                            TypeInfo::Function { .. } => Ok(self.unify(ctx, expected, id)?),
-                            info => Err(TypeError::NotCallable(info.clone())),
-                        },
-                        None => Err(TypeError::UnknownIdentifier(ident)),
+            ExpressionKind::Path(path) => match path.resolution() {
+                Res::Local(id) => match ctx.resolve_binding(id) {
+                    Some(binding) => {
+                        self.produces(binding.typeid);
+                        self.unify_at(expected, binding.typeid, expr.span)
+                    }
+                    None => Err(self.error(TypeErrorKind::UnknownIdentifier(path.last()), expr.span)),
+                },
+                // The item may not have been typechecked yet, e.g. a function
+                // defined later in the same module
+                Res::Def(def) => match self.def_types.get(&def).copied() {
+                    Some(id) => {
+                        self.produces(id);
+                        self.unify_at(expected, id, expr.span)
+                    }
+                    None => match path.is_identifier() {
+                        Some(ident) => Err(self.error(TypeErrorKind::UnknownIdentifier(ident), expr.span)),
+                        None => Err(self.error(TypeErrorKind::UnknownType(path.clone()), expr.span)),
                     },
                 },
-                None => self.name_map.get(&path).copied().ok_or_else(|| TypeError::UnknownType(path.clone())),
             },
             ExpressionKind::FieldAccess(lhs, ident) => {
                 let infer = self.fresh_infer();
-                let lhs_id = self.typecheck_expression(ctx, lhs, infer)?;
-                let type_info = self.typeinfo(lhs_id);
+                let lhs_id = self.typecheck_expression(ctx, lhs, infer);
+                let lhs_id = self.unboxed(lhs_id);
+                let type_info = self.typeinfo(lhs_id).clone();
+
+                match &type_info {
+                    TypeInfo::Struct { full_path, members } if members.get(ident).is_some() => {
+                        if let Err(error) =
+                            self.check_field_visibility(&self.current_path, full_path, *ident, ident.span)
+                        {
+                            self.report(error);
+                        }
 
-                match type_info {
-                    TypeInfo::Struct { members, .. } if members.get(ident).is_some() => {
-                        Ok(members.get(ident).copied().unwrap())
+                        let id = members[ident];
+                        self.unify_at(expected, id, expr.span)
                     }
-                    _ => Err(TypeError::NoField(type_info.clone(), *ident)),
+                    TypeInfo::Error => self.unify_at(expected, lhs_id, expr.span),
+                    _ => Err(self.error(TypeErrorKind::NoField(type_info, *ident), ident.span)),
                 }
             }
             ExpressionKind::Assignment(lhs, rhs) => {
                 let lhs_id = match &lhs.kind {
-                    ExpressionKind::FieldAccess(_, _) => {
+                    ExpressionKind::FieldAccess(_, _) | ExpressionKind::Unary(UnaryOp::Deref, _) => {
                         let infer = self.fresh_infer();
-                        self.typecheck_expression(ctx, lhs, infer)?
+                        self.typecheck_expression(ctx, lhs, infer)
                     }
-                    ExpressionKind::Path(path) => match path.is_identifier() {
-                        Some(ident) => match ctx.resolve_binding(ident) {
+                    ExpressionKind::Path(path) => match path.resolution() {
+                        Res::Local(id) => match ctx.resolve_binding(id) {
                             Some(binding) if binding.mutable => binding.typeid,
-                            Some(_) => return Err(TypeError::NotMutable(ident)),
-                            None => return Err(TypeError::UnknownIdentifier(ident)),
+                            // The value assigned still has to have the type of
+                            // the local
+                            Some(binding) => {
+                                let local = self.resolver.local(id);
+                                let label = Label::new(local.file, local.name.span, "declared here without `mut`");
+                                let error = self.error(TypeErrorKind::NotMutable(path.last()), lhs.span);
+                                self.report(error.with_label(label));
+
+                                binding.typeid
+                            }
+                            None => {
+                                let error = self.error(TypeErrorKind::UnknownIdentifier(path.last()), lhs.span);
+                                self.report(error)
+                            }
+                        },
+                        Res::Def(def) => match self.typecheck_global_place(def, lhs.span) {
+                            Ok(id) => id,
+                            Err(error) => self.report(error),
                         },
-                        None => return Err(TypeError::NotValidRhs),
                     },
-                    _ => return Err(TypeError::NotValidRhs),
+                    _ => self.report(self.error(TypeErrorKind::NotValidRhs, lhs.span)),
                 };
 
-                let infer = self.fresh_infer();
-                let rhs_id = self.typecheck_expression(ctx, rhs, infer)?;
-
-                self.unify(ctx, lhs_id, rhs_id)?;
+                let label = self.label(lhs.span, "expected due to the type of this");
+                self.typecheck_against(ctx, rhs, lhs_id, Some(label));
 
-                Ok(self.unit())
+                self.unify_at(expected, self.unit(), expr.span)
             }
             ExpressionKind::BinaryOperation(original_lhs, op, original_rhs) => {
                 let op = *op;
 
                 let infer = self.fresh_infer();
-                let lhs_id = self.typecheck_expression(ctx, original_lhs, infer)?;
+                let lhs_id = self.typecheck_expression(ctx, original_lhs, infer);
 
                 let infer = self.fresh_infer();
-                let rhs_id = self.typecheck_expression(ctx, original_rhs, infer)?;
+                let rhs_id = self.typecheck_expression(ctx, original_rhs, infer);
+
+                // Operands whose types aren't known yet take the type the
+                // operator works on
+                let operand = match op {
+                    _ if op.is_arith_op() => Some(self.integer()),
+                    _ if op.is_logic_op() => Some(self.bool()),
+                    _ => None,
+                };
+
+                if let Some(operand) = operand {
+                    for id in [lhs_id, rhs_id] {
+                        if let TypeInfo::Infer = self.typeinfo(id) {
+                            let _ = self.unify(id, operand);
+                        }
+                    }
+                }
 
                 match (self.typeinfo(lhs_id), self.typeinfo(rhs_id)) {
                     (TypeInfo::Integer, TypeInfo::Integer) if op.is_arith_op() => {
-                        Ok(self.unify(ctx, expected, self.integer())?)
+                        self.unify_at(expected, self.integer(), expr.span)
                     }
                     (TypeInfo::Bool, TypeInfo::Bool) if op.is_logic_op() => {
-                        Ok(self.unify(ctx, expected, self.bool())?)
+                        self.unify_at(expected, self.bool(), expr.span)
                     }
                     (_, _) if op == BinOp::Equal => {
-                        self.unify(ctx, lhs_id, rhs_id)?;
-                        Ok(self.bool())
+                        let label = self.label(original_lhs.span, "expected because of this");
+                        if let Err(kind) = self.unify(lhs_id, rhs_id) {
+                            self.report(self.error(kind, original_rhs.span).with_label(label));
+                        }
+
+                        self.unify_at(expected, self.bool(), expr.span)
+                    }
+                    (TypeInfo::Error, _) | (_, TypeInfo::Error) => {
+                        self.unify_at(expected, self.error_type(), expr.span)
+                    }
+                    (lhs, rhs) => {
+                        let (lhs_label, rhs_label) = (lhs.name(self), rhs.name(self));
+                        let kind = TypeErrorKind::UnknownBinOp { lhs: lhs.clone(), op, rhs: rhs.clone() };
+
+                        Err(self
+                            .error(kind, expr.span)
+                            .with_label(self.label(original_lhs.span, lhs_label))
+                            .with_label(self.label(original_rhs.span, rhs_label)))
                     }
-                    (_, _) => Err(TypeError::UnknownBinOp {
-                        lhs: self.types[lhs_id].clone(),
-                        op,
-                        rhs: self.types[rhs_id].clone(),
-                    }),
                 }
             }
             ExpressionKind::If(if_expr) => {
-                let expected = {
-                    let infer = self.fresh_infer();
-                    // FIXME: probably less confusing to not early return
-                    // here and check each `if` and then `else` individually
-                    // to report their types
-                    let typeid = self.typecheck_block(ctx, &if_expr.r#else, infer)?;
-                    self.unify(ctx, expected, typeid)?
+                // Branches are checked in the order they're written, so the
+                // first branch is what the others are expected to match
+                let mut branches = Vec::new();
+                for if_ in &if_expr.ifs {
+                    let (_, condition) =
+                        self.diverges_in(|engine| engine.typecheck_expression(ctx, &if_.condition, engine.bool()));
+                    let (_, body) = self.diverges_in(|engine| engine.typecheck_block(ctx, &if_.body, expected));
+                    branches.push((condition, body));
+                }
+
+                // The `else` block may not be written, so mismatches with it
+                // are reported on the whole chain
+                let infer = self.fresh_infer();
+                let (typeid, mut diverges) =
+                    self.diverges_in(|engine| engine.typecheck_block(ctx, &if_expr.r#else, infer));
+                let expected = match self.unify_at(expected, typeid, expr.span) {
+                    Ok(id) => id,
+                    Err(error) => self.report(error),
                 };
 
-                for if_ in &if_expr.ifs {
-                    self.typecheck_expression(ctx, &if_.condition, self.bool())?;
-                    self.typecheck_block(ctx, &if_.body, expected)?;
+                // The chain diverges if the first condition does, or if every
+                // branch that could be taken does
+                for (condition, body) in branches.into_iter().rev() {
+                    diverges = condition || (body && diverges);
                 }
 
+                self.diverges |= diverges;
                 Ok(expected)
             }
-            ExpressionKind::Unary(op, expr) => {
-                let expr = self.typecheck_expression(ctx, expr, expected)?;
+            ExpressionKind::Unary(UnaryOp::Minus, inner) => {
+                let id = self.typecheck_expression(ctx, inner, expected);
+
+                match self.typeinfo(id) {
+                    TypeInfo::Integer | TypeInfo::Bool | TypeInfo::Error => Ok(id),
+                    info => {
+                        let kind = TypeErrorKind::UnknownUnaryOp { op: UnaryOp::Minus, info: info.clone() };
+                        Err(self.error(kind, expr.span))
+                    }
+                }
+            }
+            ExpressionKind::Unary(UnaryOp::Box, inner) => {
+                // What's boxed is known if the box's type is
+                let boxed = match self.typeinfo(expected) {
+                    &TypeInfo::Box(boxed) => boxed,
+                    _ => self.fresh_infer(),
+                };
 
-                match self.typeinfo(expr) {
-                    TypeInfo::Integer | TypeInfo::Bool => Ok(expr),
-                    info => Err(TypeError::UnknownUnaryOp { op: *op, info: info.clone() }),
+                let boxed = self.typecheck_expression(ctx, inner, boxed);
+                let id = self.box_type(boxed);
+                self.unify_at(expected, id, expr.span)
+            }
+            ExpressionKind::Unary(UnaryOp::Deref, inner) => {
+                let infer = self.fresh_infer();
+                let id = self.typecheck_expression(ctx, inner, infer);
+
+                match self.typeinfo(id).clone() {
+                    TypeInfo::Box(boxed) => self.unify_at(expected, boxed, expr.span),
+                    TypeInfo::Infer => {
+                        let boxed = self.fresh_infer();
+                        let box_type = self.box_type(boxed);
+                        self.unify_at(box_type, id, inner.span)?;
+                        self.unify_at(expected, boxed, expr.span)
+                    }
+                    TypeInfo::Error => self.unify_at(expected, id, expr.span),
+                    info => Err(self.error(TypeErrorKind::UnknownUnaryOp { op: UnaryOp::Deref, info }, expr.span)),
                 }
             }
         }
     }
 
-    pub fn register_path_type(&mut self, path: &Path, type_id: TypeId) -> Result<()> {
-        // TODO: collisions?
-        self.name_map.insert(path.clone(), type_id);
+    /// Runs `f`, returning whether the code it checked always diverges
+    fn diverges_in<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> (T, bool) {
+        let outer = std::mem::replace(&mut self.diverges, false);
+        let res = f(self);
 
-        Ok(())
+        (res, std::mem::replace(&mut self.diverges, outer))
     }
 
-    pub fn typecheck_struct(&mut self, ctx: &Context<'_>, strukt: &Struct) -> Result<TypeId> {
-        let struct_path = self.current_path.with_ident(strukt.name);
+    /// Notes that the code being checked diverges if it produces a value of
+    /// type `id`, since there are no values of type `!`
+    fn produces(&mut self, id: TypeId) {
+        if let TypeInfo::Never = self.typeinfo(id) {
+            self.diverges = true;
+        }
+    }
 
-        let type_info = TypeInfo::Struct {
-            members: {
-                strukt
-                    .members
-                    .iter()
-                    .map(|m| {
-                        let id = match &m.ty.kind {
-                            hir::TypeKind::Integer => self.integer(),
-                            hir::TypeKind::Bool => self.bool(),
-                            hir::TypeKind::Path(path) => match self.resolve_two_way(ctx, path) {
-                                Some(id) => id,
-                                None => return Err(TypeError::UnknownType(path.clone())),
-                            },
-                            _ => todo!("more type stuff"),
-                        };
+    /// Typechecks `expr` against the declared type `expected`, pointing at
+    /// `origin` as the reason it was expected if the types don't match
+    fn typecheck_against(
+        &mut self,
+        ctx: &Context<'_>,
+        expr: &Expression,
+        expected: TypeId,
+        origin: Option<Label>,
+    ) -> TypeId {
+        let infer = self.fresh_infer();
+        let have = self.typecheck_expression(ctx, expr, infer);
+
+        match self.unify(expected, have) {
+            Ok(id) => id,
+            Err(kind) => {
+                let error = self.error(kind, expr.span);
+                self.report(match origin {
+                    Some(label) => error.with_label(label),
+                    None => error,
+                })
+            }
+        }
+    }
 
-                        Ok((m.name, id))
-                    })
-                    .collect::<Result<_>>()?
-            },
-            full_path: struct_path.clone(),
+    /// Typechecks `expr` as though it appeared at `scope` in `file` rather
+    /// than the current path and file
+    pub fn typecheck_expression_from(
+        &mut self,
+        ctx: &Context<'_>,
+        scope: &Path,
+        file: Option<FileId>,
+        expr: &Expression,
+        expected: TypeId,
+    ) -> TypeId {
+        self.in_file(file, |engine| {
+            engine.at_path(scope, |engine| engine.inferring(|engine| engine.typecheck_expression(ctx, expr, expected)))
+        })
+    }
+
+    fn at_path<T>(&mut self, scope: &Path, f: impl FnOnce(&mut Self) -> T) -> T {
+        let old_path = std::mem::replace(&mut self.current_path, scope.clone());
+        let res = f(self);
+        self.current_path = old_path;
+
+        res
+    }
+
+    /// Runs `f` with `file` as the file errors point into, if it's known
+    fn in_file<T>(&mut self, file: Option<FileId>, f: impl FnOnce(&mut Self) -> T) -> T {
+        let file = file.or(self.current_file);
+        let old_file = std::mem::replace(&mut self.current_file, file);
+        let res = f(self);
+        self.current_file = old_file;
+
+        res
+    }
+
+    /// Returns the type of the `static mut` item `def`, which is being
+    /// assigned to at `span`
+    fn typecheck_global_place(&self, def: DefId, span: Span) -> Result<TypeId> {
+        let path = self.resolver.def(def).path.clone();
+        let kind = match (self.global(def), self.def_types.get(&def)) {
+            (Some(Global::Static { mutable: true }), Some(&id)) => return Ok(id),
+            (Some(Global::Static { mutable: true }), None) => TypeErrorKind::UnknownType(path),
+            (Some(Global::Static { mutable: false }), _) => TypeErrorKind::NotMutableStatic(path),
+            (Some(Global::Const), _) => TypeErrorKind::AssignToConst(path),
+            (None, _) => return Err(self.error(TypeErrorKind::NotValidRhs, span)),
         };
 
-        self.name_map.insert(struct_path, self.types.len());
-        self.types.push(type_info);
+        Err(self.error(kind, span).with_label(self.def_label(def, "defined here")))
+    }
 
-        Ok(self.types.len() - 1)
+    /// Ensures the field `field` of the struct at `struct_path` is visible
+    /// from the path `from`, reporting an error at `span` if it isn't
+    pub fn check_field_visibility(&self, from: &Path, struct_path: &Path, field: Identifier, span: Span) -> Result<()> {
+        match self.is_field_visible(from, struct_path, field) {
+            true => Ok(()),
+            false => Err(self.error(TypeErrorKind::PrivateField { strukt: struct_path.clone(), field }, span)),
+        }
     }
 
-    pub fn typecheck_function(&mut self, ctx: &Context<'_>, function: &Function) -> Result<TypeId> {
-        let mut ctx = Context { aliases: ctx.aliases.clone(), bindings: HashMap::new(), parent: None };
+    /// Whether the field `field` of the struct at `struct_path` is visible
+    /// from the path `from`
+    fn is_field_visible(&self, from: &Path, struct_path: &Path, field: Identifier) -> bool {
+        match self.field_visibilities.get(&struct_path.with_ident(field)) {
+            Some(VisibilityKind::Private) => from.starts_with(&struct_path.parent()),
+            Some(VisibilityKind::Geode) => self.resolver.geode_of(from) == self.resolver.geode_of(struct_path),
+            _ => true,
+        }
+    }
 
-        let mut parameters = Vec::new();
+    /// Typechecks `items`, which are all defined in the same module or block.
+    /// Items are processed in phases so they can refer to each other no matter
+    /// the order they're defined in: every type is named first, then the
+    /// signatures of all items are collected, and only then are function
+    /// bodies and initializers checked. Each phase carries on past errors,
+    /// so every error in `items` is reported.
+    pub fn typecheck_items(&mut self, ctx: &Context<'_>, items: &[Item]) {
+        self.collect_types(items);
+        self.collect_signatures(items);
+        self.check_aliases(items);
+        self.check_struct_sizes(items, &mut Vec::new());
+        self.check_bodies(ctx, items);
+    }
 
-        for fp in &function.parameters {
-            let parameter_id = self.from_hir_type(&ctx, &fp.ty)?;
-            ctx.bindings.insert(fp.name, BindingInfo { mutable: false, typeid: parameter_id });
-            parameters.push((fp.name, parameter_id));
+    /// Registers a placeholder type for every struct and type alias, which is
+    /// filled in once all of them can be named
+    fn collect_types(&mut self, items: &[Item]) {
+        for item in items {
+            match &item.kind {
+                ItemKind::Module(module) => self.collect_types(&module.items),
+                ItemKind::Struct(_) => {
+                    let full_path = self.resolver.def(item.def_id()).path.clone();
+                    let id = self.new_type(TypeInfo::Struct { full_path, members: HashMap::new() });
+                    self.def_types.insert(item.def_id(), id);
+                }
+                ItemKind::TypeAlias(_) => {
+                    let full_path = self.resolver.def(item.def_id()).path.clone();
+                    let aliased = self.fresh_infer();
+                    let id = self.new_type(TypeInfo::Alias { full_path, aliased });
+                    self.def_types.insert(item.def_id(), id);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Fills in the members of structs and the types of aliases, and
+    /// registers the types of functions and globals
+    fn collect_signatures(&mut self, items: &[Item]) {
+        for item in items {
+            let def = match item.def {
+                Some(def) => def,
+                None => continue,
+            };
+
+            match &item.kind {
+                ItemKind::Module(module) => {
+                    self.in_file(module.file, |engine| engine.collect_signatures(&module.items))
+                }
+                ItemKind::Struct(strukt) => self.typecheck_struct(def, strukt),
+                ItemKind::TypeAlias(alias) => {
+                    let full_path = self.resolver.def(def).path.clone();
+                    let aliased = self.from_hir_type(&alias.ty);
+                    self.set_typeinfo(self.def_types[&def], TypeInfo::Alias { full_path, aliased });
+                }
+                ItemKind::Function(function) => self.declare_function(def, function),
+                ItemKind::Const(hir::Const { ty, .. }) | ItemKind::Static(hir::Static { ty, .. }) => {
+                    self.declare_global(def, ty)
+                }
+                ItemKind::Use(_) => {}
+            }
         }
+    }
+
+    /// Rejects type aliases that expand to themselves. The cycle is broken by
+    /// making the alias stand for the error type, so anything using it can
+    /// still be checked.
+    fn check_aliases(&mut self, items: &[Item]) {
+        for item in items {
+            match &item.kind {
+                ItemKind::Module(module) => self.check_aliases(&module.items),
+                ItemKind::TypeAlias(_) => {
+                    let alias = self.def_types[&item.def_id()];
+                    let mut seen = Vec::new();
+                    let mut id = self.unified.find(alias);
+
+                    while let TypeInfo::Alias { aliased: next, .. } = &self.types[id] {
+                        if seen.contains(&id) {
+                            let def = self.resolver.def(item.def_id());
+                            let kind = TypeErrorKind::RecursiveTypeAlias(def.path.clone());
+                            let error = TypeError::new(kind, def.file, def.name().span);
+
+                            let full_path = def.path.clone();
+                            self.set_typeinfo(alias, TypeInfo::Alias { full_path, aliased: self.error_type() });
+                            self.report(error);
+                            break;
+                        }
 
-        let return_type = self.from_hir_type(&ctx, &function.return_type)?;
+                        seen.push(id);
+                        id = self.unified.find(*next);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
 
-        let fn_id = self.types.len();
-        self.name_map.insert(self.current_path.with_ident(function.name), fn_id);
-        ctx.aliases.insert(Path::from_identifier(function.name), self.current_path.with_ident(function.name));
+    /// Rejects structs that contain themselves, which could never be
+    /// constructed. Aliases must already be known not to be cyclic. Structs
+    /// in the same cycle are only reported once, for the first of them.
+    fn check_struct_sizes(&mut self, items: &[Item], reported: &mut Vec<Vec<Path>>) {
+        for item in items {
+            match &item.kind {
+                ItemKind::Module(module) => self.check_struct_sizes(&module.items, reported),
+                ItemKind::Struct(_) => {
+                    let id = self.unified.find(self.def_types[&item.def_id()]);
 
-        let type_info = TypeInfo::Function { parameters, return_type };
-        self.types.push(type_info);
+                    let mut cycle = Vec::new();
+                    if !self.find_size_cycle(id, id, &mut Vec::new(), &mut cycle) {
+                        continue;
+                    }
 
-        self.current_path = self.current_path.with_ident(function.name);
-        let res = self.typecheck_block(&ctx, &function.body, return_type);
-        self.current_path.pop();
+                    let mut fields = cycle.clone();
+                    fields.sort_by_key(|field| field.to_string());
+                    if reported.contains(&fields) {
+                        continue;
+                    }
+                    reported.push(fields);
+
+                    let def = self.resolver.def(item.def_id());
+                    let mut error = TypeError::new(
+                        TypeErrorKind::InfinitelySized { strukt: def.path.clone(), cycle: cycle.clone() },
+                        def.file,
+                        def.name().span,
+                    );
+
+                    for field in &cycle {
+                        let (file, ty) = &self.field_types[field];
+                        error =
+                            error.with_label(Label::new(*file, ty.span, format!("contains `{}` by value", ty.kind)));
+                    }
 
-        if res.is_err() {
-            self.name_map.remove(&self.current_path.with_ident(function.name));
-            res?;
+                    // Boxing the first field is enough to break the cycle
+                    let (file, ty) = &self.field_types[&cycle[0]];
+                    let help = format!("insert indirection to give it a finite size, e.g. `Box<{}>`", ty.kind);
+                    self.report(error.with_label(Label::new(*file, ty.span, help)));
+                }
+                _ => {}
+            }
         }
+    }
+
+    /// Whether a value of the type `id` contains a value of the struct
+    /// `target`, which makes `target` infinitely sized if `id` is one of its
+    /// members. If it does, the fields leading from `id` to `target` are
+    /// pushed to `cycle`. Boxes are a fixed size whatever they hold, so aren't
+    /// searched.
+    fn find_size_cycle(&self, target: TypeId, id: TypeId, visited: &mut Vec<TypeId>, cycle: &mut Vec<Path>) -> bool {
+        let id = self.expand(id);
+
+        match &self.types[id] {
+            TypeInfo::Struct { full_path, members } if !visited.contains(&id) => {
+                visited.push(id);
+
+                // Sorted so that the same cycle is found every time
+                let mut members: Vec<_> = members.iter().collect();
+                members.sort_by_key(|(name, _)| name.string());
+
+                for (&name, &member) in members {
+                    cycle.push(full_path.with_ident(name));
+
+                    let member = self.expand(member);
+                    if member == target || self.find_size_cycle(target, member, visited, cycle) {
+                        return true;
+                    }
 
-        Ok(fn_id)
+                    cycle.pop();
+                }
+
+                false
+            }
+            _ => false,
+        }
     }
 
-    pub fn typecheck_block(&mut self, ctx: &Context<'_>, block: &Block, expected: TypeId) -> Result<TypeId> {
-        let mut child_ctx = ctx.new_child();
+    /// The type of what's behind any number of boxes of `id`, which is what
+    /// fields are accessed through
+    pub fn unboxed(&self, mut id: TypeId) -> TypeId {
+        while let &TypeInfo::Box(boxed) = self.typeinfo(id) {
+            id = boxed;
+        }
 
-        let unnamable = Identifier::new(&self.unnamable_count.to_string());
-        self.unnamable_count += 1;
-        self.current_path = self.current_path.with_ident(unnamable);
+        id
+    }
 
-        for item in &block.items {
-            self.typecheck_item(ctx, item)?;
+    /// Follows type aliases to the root of the type they stand for
+    fn expand(&self, mut id: TypeId) -> TypeId {
+        id = self.unified.find(id);
+        while let TypeInfo::Alias { aliased, .. } = &self.types[id] {
+            id = self.unified.find(*aliased);
+        }
+
+        id
+    }
 
+    fn check_bodies(&mut self, ctx: &Context<'_>, items: &[Item]) {
+        for item in items {
             match &item.kind {
-                ItemKind::Struct(s) => {
-                    child_ctx.aliases.insert(Path::from_identifier(s.name), self.current_path.with_ident(s.name));
-                }
-                ItemKind::Function(f) => {
-                    child_ctx.aliases.insert(Path::from_identifier(f.name), self.current_path.with_ident(f.name));
+                ItemKind::Module(module) => {
+                    let path = self.resolver.def(item.def_id()).path.clone();
+                    self.in_file(module.file, |engine| {
+                        engine.at_path(&path, |engine| engine.check_bodies(&ctx.new_child(), &module.items))
+                    });
                 }
-                ItemKind::Module(m) => {
-                    child_ctx.aliases.insert(Path::from_identifier(m.name), self.current_path.with_ident(m.name));
+                ItemKind::Function(function) => self.typecheck_function_body(item.def_id(), function),
+                ItemKind::Const(hir::Const { ty, value, .. }) | ItemKind::Static(hir::Static { ty, value, .. }) => {
+                    let def = item.def_id();
+                    let id = self.def_types[&def];
+
+                    self.in_file(self.resolver.def(def).file, |engine| {
+                        let label = engine.label(ty.span, "expected due to this");
+                        engine.inferring(|engine| engine.typecheck_against(ctx, value, id, Some(label)))
+                    });
                 }
-                _ => {}
+                ItemKind::Struct(_) | ItemKind::TypeAlias(_) | ItemKind::Use(_) => {}
             }
         }
+    }
+
+    fn typecheck_struct(&mut self, def: DefId, strukt: &Struct) {
+        let struct_path = self.resolver.def(def).path.clone();
+
+        let type_info = TypeInfo::Struct {
+            members: strukt.members.iter().map(|m| (m.name, self.from_hir_type(&m.ty))).collect(),
+            full_path: struct_path.clone(),
+        };
+
+        let file = self.resolver.def(def).file;
+        for member in &strukt.members {
+            let field = struct_path.with_ident(member.name);
+            self.field_visibilities.insert(field.clone(), member.visibility.kind);
+            self.field_types.insert(field, (file, member.ty.clone()));
+        }
+
+        self.set_typeinfo(self.def_types[&def], type_info);
+    }
+
+    fn declare_function(&mut self, def: DefId, function: &Function) {
+        let parameters = function.parameters.iter().map(|fp| self.from_hir_type(&fp.ty)).collect();
+        let return_type = self.from_hir_type(&function.return_type);
+
+        let id = self.function_type(parameters, return_type);
+        self.def_types.insert(def, id);
+        self.parameter_names.insert(def, function.parameters.iter().map(|fp| fp.name).collect());
+    }
+
+    fn typecheck_function_body(&mut self, def: DefId, function: &Function) {
+        let (parameters, return_type) = match self.declared_typeinfo(self.def_types[&def]) {
+            TypeInfo::Function { parameters, return_type } => (parameters.clone(), *return_type),
+            _ => unreachable!("functions are declared before their bodies are checked"),
+        };
+
+        let mut ctx = Context::new();
+        for (fp, &typeid) in function.parameters.iter().zip(&parameters) {
+            ctx.new_binding(fp.local_id(), BindingInfo { mutable: false, typeid });
+            self.local_types.insert(fp.local_id(), typeid);
+        }
+
+        let path = self.resolver.def(def).path.clone();
+        let file = self.resolver.def(def).file;
+
+        self.in_file(file, |engine| {
+            let infer = engine.fresh_infer();
+            let have = engine.at_path(&path, |engine| {
+                engine.inferring(|engine| engine.typecheck_block(&ctx, &function.body, infer))
+            });
+
+            // Functions without a return type return `Unit`
+            let label = match function.return_type.kind {
+                TypeKind::Unit => engine.label(function.name.span, "function has no return type, so returns `Unit`"),
+                _ => engine.label(function.return_type.span, "expected because of this return type"),
+            };
+
+            if let Err(kind) = engine.unify(return_type, have) {
+                engine.report(engine.error(kind, function.body.return_expr.span).with_label(label));
+            }
+        });
+    }
+
+    pub fn typecheck_block(&mut self, ctx: &Context<'_>, block: &Block, expected: TypeId) -> TypeId {
+        let path = self.resolver.def(block.scope_id()).path.clone();
+        self.at_path(&path, |engine| engine.typecheck_block_contents(ctx, block, expected))
+    }
+
+    fn typecheck_block_contents(&mut self, ctx: &Context<'_>, block: &Block, expected: TypeId) -> TypeId {
+        let mut child_ctx = ctx.new_child();
+        self.typecheck_items(ctx, &block.items);
 
+        // Only the first unreachable statement is warned about, pointing at
+        // the one that made it unreachable
+        let mut diverged_at = None;
+        let mut warned = false;
         for statement in &block.statements {
-            if let Some((name, id)) = self.typecheck_statement(&child_ctx, statement)? {
-                child_ctx.bindings.insert(name, id);
+            if let (Some(span), false) = (diverged_at, warned) {
+                self.warn_unreachable(TypeErrorKind::UnreachableStatement, statement.span, span);
+                warned = true;
+            }
+
+            if let Some((id, binding)) = self.typecheck_statement(&child_ctx, statement) {
+                child_ctx.new_binding(id, binding);
+            }
+
+            if self.diverges && diverged_at.is_none() {
+                diverged_at = Some(statement.span);
             }
         }
 
-        let res = self.typecheck_expression(&child_ctx, &block.return_expr, expected);
-        self.current_path.pop();
+        let return_expr = &block.return_expr;
+        match (diverged_at, &return_expr.kind) {
+            // A block without a final expression never produces its `Unit`
+            // if it diverges before the end
+            (Some(_), ExpressionKind::Unit) => {
+                self.typecheck_expression(&child_ctx, return_expr, self.unit());
+                self.unify(expected, self.never()).expect("`!` unifies with every type")
+            }
+            (Some(span), _) => {
+                if !warned {
+                    self.warn_unreachable(TypeErrorKind::UnreachableExpression, return_expr.span, span);
+                }
 
-        res
+                self.typecheck_expression(&child_ctx, return_expr, expected)
+            }
+            (None, _) => self.typecheck_expression(&child_ctx, return_expr, expected),
+        }
     }
 
-    pub fn typecheck_statement(
-        &mut self,
-        ctx: &Context<'_>,
-        statement: &Statement,
-    ) -> Result<Option<(Identifier, BindingInfo)>> {
+    /// Warns that the code at `span` is never run, because of the code at
+    /// `diverged_at` before it
+    fn warn_unreachable(&mut self, kind: TypeErrorKind, span: Span, diverged_at: Span) {
+        let label = self.label(diverged_at, "any code following this expression is unreachable");
+        self.warnings.push(self.error(kind, span).with_label(label));
+    }
+
+    pub fn typecheck_statement(&mut self, ctx: &Context<'_>, statement: &Statement) -> Option<(LocalId, BindingInfo)> {
         match &statement.kind {
             StatementKind::Expression(e) => {
                 let infer = self.fresh_infer();
-                self.typecheck_expression(ctx, &e, infer)?;
-                Ok(None)
+                self.typecheck_expression(ctx, e, infer);
+                None
             }
             StatementKind::Local(local) => {
-                let typeid = self.from_hir_type(ctx, &local.ty)?;
-                self.typecheck_expression(ctx, &local.value, typeid)?;
-
-                Ok(Some((local.name, BindingInfo { mutable: local.mutable, typeid })))
+                let typeid = self.check_local(ctx, local);
+                Some((local.local_id(), BindingInfo { mutable: local.mutable, typeid }))
             }
         }
     }
 
-    pub fn typecheck_item(&mut self, ctx: &Context<'_>, item: &Item) -> Result<()> {
-        match &item.kind {
-            ItemKind::Module(module) => {
-                self.current_path = self.current_path.with_ident(module.name);
-                let ctx = ctx.new_child();
-                for item in &module.items {
-                    self.typecheck_item(&ctx, item)?;
-                }
-                self.current_path.pop();
-            }
-            ItemKind::Struct(strukt) => {
-                self.typecheck_struct(ctx, strukt)?;
+    /// Typechecks the initializer of `local` against its type annotation, if
+    /// it has one, returning the type of the local
+    pub fn typecheck_local(&mut self, ctx: &Context<'_>, local: &Local) -> TypeId {
+        self.inferring(|engine| engine.check_local(ctx, local))
+    }
+
+    /// Typechecks a local whose type doesn't have to be known until the code
+    /// around it has been checked
+    fn check_local(&mut self, ctx: &Context<'_>, local: &Local) -> TypeId {
+        let typeid = self.from_hir_type(&local.ty);
+        self.local_types.insert(local.local_id(), typeid);
+
+        match local.ty.kind {
+            TypeKind::Infer => {
+                self.inferred.push((typeid, self.current_file, local.name.span));
+                self.typecheck_expression(ctx, &local.value, typeid)
             }
-            ItemKind::Function(f) => {
-                self.typecheck_function(ctx, f)?;
+            _ => {
+                let label = self.label(local.ty.span, "expected due to this");
+                self.typecheck_against(ctx, &local.value, typeid, Some(label))
             }
-            ItemKind::Use(u) => {
-                self.typeid_from_path(ctx, &u.path).ok_or_else(|| TypeError::UnknownType(u.path.clone()))?;
+        };
+
+        typeid
+    }
+
+    pub fn typecheck_item(&mut self, ctx: &Context<'_>, item: &Item) {
+        self.typecheck_items(ctx, std::slice::from_ref(item))
+    }
+
+    fn declare_global(&mut self, def: DefId, ty: &Type) {
+        let id = self.from_hir_type(ty);
+        self.def_types.insert(def, id);
+    }
+
+    /// Returns what kind of value the item `def` is, if it's a `const` or
+    /// `static` item
+    pub fn global(&self, def: DefId) -> Option<Global> {
+        match self.resolver.def(def).kind {
+            DefKind::Const => Some(Global::Const),
+            DefKind::Static { mutable } => Some(Global::Static { mutable }),
+            _ => None,
+        }
+    }
+
+    /// Returns the type of the item `path` refers to from the root module
+    pub fn typeid_from_path(&self, path: &Path) -> Option<TypeId> {
+        let def = self.resolver.resolve_path(&Path::new(), path)?;
+        self.def_types.get(&def).copied()
+    }
+
+    /// Returns the type `ty` names, reporting an error and returning the
+    /// error type if it doesn't name one
+    pub fn from_hir_type(&mut self, ty: &Type) -> TypeId {
+        match &ty.kind {
+            TypeKind::Integer => self.integer(),
+            TypeKind::Bool => self.bool(),
+            TypeKind::Path(path) => match self.def_type(path) {
+                Some(id) => id,
+                None => self.report(self.error(TypeErrorKind::UnknownType(path.clone()), ty.span)),
+            },
+            TypeKind::Infer => self.fresh_infer(),
+            TypeKind::Unit => self.unit(),
+            TypeKind::Never => self.never(),
+            TypeKind::Box(boxed) => {
+                let boxed = self.from_hir_type(boxed);
+                self.box_type(boxed)
             }
         }
+    }
 
-        Ok(())
+    /// Returns the type of the item the resolved `path` refers to
+    fn def_type(&self, path: &Path) -> Option<TypeId> {
+        match path.resolution() {
+            Res::Def(def) => self.def_types.get(&def).copied(),
+            Res::Local(_) => None,
+        }
     }
 
-    pub fn typeid_from_path(&self, ctx: &Context<'_>, path: &Path) -> Option<TypeId> {
-        self.name_map.get(ctx.resolve_path_alias(path).unwrap_or(path)).copied()
+    /// Returns the type the resolved `path` names, expanding type aliases
+    fn expand_aliases(&self, path: &Path) -> Option<TypeId> {
+        Some(self.expand(self.def_type(path)?))
     }
 
-    pub fn from_hir_type(&mut self, ctx: &Context<'_>, ty: &Type) -> Result<TypeId> {
-        match &ty.kind {
-            TypeKind::Integer => Ok(self.integer()),
-            TypeKind::Bool => Ok(self.bool()),
-            TypeKind::Path(path) => {
-                self.typeid_from_path(ctx, path).ok_or_else(|| TypeError::UnknownType(path.clone()))
+    pub fn integer(&self) -> TypeId {
+        self.builtins.integer
+    }
+
+    pub fn bool(&self) -> TypeId {
+        self.builtins.bool
+    }
+
+    pub fn unit(&self) -> TypeId {
+        self.builtins.unit
+    }
+
+    /// The type of something that failed to typecheck
+    pub fn error_type(&self) -> TypeId {
+        self.builtins.error
+    }
+
+    /// The type of expressions that never finish, such as calls to functions
+    /// that never return
+    pub fn never(&self) -> TypeId {
+        self.builtins.never
+    }
+
+    /// Returns the type of boxes holding a `boxed`
+    pub fn box_type(&mut self, boxed: TypeId) -> TypeId {
+        self.intern(TypeInfo::Box(boxed))
+    }
+
+    /// Returns the type of functions taking `parameters` and returning
+    /// `return_type`
+    pub fn function_type(&mut self, parameters: Vec<TypeId>, return_type: TypeId) -> TypeId {
+        self.intern(TypeInfo::Function { parameters, return_type })
+    }
+
+    /// Returns the id of `info`, which has to be a type that's the same
+    /// whenever its contents are, reusing the existing id if there is one
+    fn intern(&mut self, info: TypeInfo) -> TypeId {
+        let key = TypeKey::of(&info, &self.unified).expect("only types without identities are interned");
+
+        match self.interned.get(&key) {
+            Some(&id) => id,
+            None => {
+                let id = self.new_type(info);
+                self.interned.insert(key, id);
+                id
             }
-            TypeKind::Infer => Ok(self.fresh_infer()),
-            TypeKind::Unit => Ok(self.unit()),
         }
     }
 
-    fn gen_struct_typeinfo(&mut self, ctx: &Context<'_>, se: &StructExpr) -> Result<TypeId> {
-        let type_info = TypeInfo::Struct {
-            full_path: se.name.clone(),
-            members: se
-                .members
+    /// Forgets the types of `expr` and the locals within it once it's no
+    /// longer needed, such as after evaluating an expression entered at the
+    /// REPL. Items within it are kept.
+    pub fn forget_expression(&mut self, expr: &Expression) {
+        Forgetter { engine: self }.visit_expression(expr);
+    }
+
+    /// Starts recording the changes made by the code checked next, so that
+    /// they can be undone if it turns out to be wrong
+    pub fn snapshot(&mut self) -> Snapshot {
+        Snapshot {
+            types: self.types.snapshot(),
+            unified: self.unified.snapshot(),
+            interned: self.interned.snapshot(),
+            def_types: self.def_types.snapshot(),
+            expr_types: self.expr_types.snapshot(),
+            local_types: self.local_types.snapshot(),
+            parameter_names: self.parameter_names.snapshot(),
+            field_visibilities: self.field_visibilities.snapshot(),
+            field_types: self.field_types.snapshot(),
+            resolver: self.resolver.snapshot(),
+        }
+    }
+
+    /// Undoes everything checked and resolved since `snapshot` was taken
+    pub fn rollback_to(&mut self, snapshot: Snapshot) {
+        self.types.rollback_to(snapshot.types);
+        self.unified.rollback_to(snapshot.unified);
+        self.interned.rollback_to(snapshot.interned);
+        self.def_types.rollback_to(snapshot.def_types);
+        self.expr_types.rollback_to(snapshot.expr_types);
+        self.local_types.rollback_to(snapshot.local_types);
+        self.parameter_names.rollback_to(snapshot.parameter_names);
+        self.field_visibilities.rollback_to(snapshot.field_visibilities);
+        self.field_types.rollback_to(snapshot.field_types);
+        self.resolver.rollback_to(snapshot.resolver);
+        self.inferred.clear();
+        self.diverging.clear();
+    }
+
+    /// Keeps everything checked and resolved since `snapshot` was taken
+    pub fn commit(&mut self, snapshot: Snapshot) {
+        self.types.commit(snapshot.types);
+        self.unified.commit(snapshot.unified);
+        self.interned.commit(snapshot.interned);
+        self.def_types.commit(snapshot.def_types);
+        self.expr_types.commit(snapshot.expr_types);
+        self.local_types.commit(snapshot.local_types);
+        self.parameter_names.commit(snapshot.parameter_names);
+        self.field_visibilities.commit(snapshot.field_visibilities);
+        self.field_types.commit(snapshot.field_types);
+        self.resolver.commit(snapshot.resolver);
+    }
+
+    /// Discards the types that can no longer be reached, such as type
+    /// variables that were only needed while checking some code, renumbering
+    /// the rest. This must only be done between inputs, when nothing else is
+    /// holding on to a type id other than those returned by the engine.
+    pub fn collect_garbage(&mut self) {
+        let old = std::mem::take(&mut self.unified);
+        let builtins = self.builtins;
+        let roots = vec![builtins.integer, builtins.bool, builtins.unit, builtins.error, builtins.never];
+        let roots = roots
+            .into_iter()
+            .chain(self.def_types.values().copied())
+            .chain(self.expr_types.values().copied())
+            .chain(self.local_types.values().copied())
+            .chain(self.inferred.iter().map(|&(id, ..)| id))
+            .chain(self.diverging.iter().copied());
+
+        // Every reference leads to the root of its set, so only roots that
+        // are still referred to have to be kept
+        let mut live = vec![false; self.types.len()];
+        let mut stack: Vec<_> = roots.map(|id| old.find(id)).collect();
+        while let Some(id) = stack.pop() {
+            if !live[id] {
+                live[id] = true;
+                stack.extend(self.types[id].children().map(|child| old.find(child)));
+            }
+        }
+
+        let mut renumbered = vec![None; self.types.len()];
+        for (id, _) in live.iter().enumerate().filter(|(_, &live)| live) {
+            renumbered[id] = Some(self.unified.push());
+        }
+
+        let renumber = |id: TypeId| renumbered[old.find(id)].expect("live types only refer to live types");
+        let types = std::mem::take(&mut self.types);
+        self.types = SnapshotVec::from(
+            types
                 .iter()
-                .map(|member| {
-                    let id = self.fresh_infer();
-                    Ok((member.name, self.typecheck_expression(ctx, &member.expression, id)?))
-                })
-                .collect::<Result<_>>()?,
-        };
+                .enumerate()
+                .filter(|&(id, _)| live[id])
+                .map(|(_, info)| info.map_ids(renumber))
+                .collect::<Vec<_>>(),
+        );
+
+        let interned = std::mem::take(&mut self.interned);
+        for &id in interned.values().filter(|&&id| live[old.find(id)]) {
+            let id = renumber(id);
+            let key = TypeKey::of(&self.types[id], &self.unified).expect("only interned types are interned");
+            self.interned.insert(key, id);
+        }
 
-        self.types.push(type_info);
-        Ok(self.types.len() - 1)
+        for id in self.def_types.values_mut().chain(self.expr_types.values_mut()).chain(self.local_types.values_mut()) {
+            *id = renumber(*id);
+        }
+
+        for (id, ..) in &mut self.inferred {
+            *id = renumber(*id);
+        }
+
+        for id in &mut self.diverging {
+            *id = renumber(*id);
+        }
+
+        self.builtins = Builtins {
+            integer: renumber(builtins.integer),
+            bool: renumber(builtins.bool),
+            unit: renumber(builtins.unit),
+            error: renumber(builtins.error),
+            never: renumber(builtins.never),
+        };
     }
+}
 
-    fn resolve_two_way(&self, ctx: &Context<'_>, path: &Path) -> Option<TypeId> {
-        self.name_map.get(ctx.resolve_path_alias(path).unwrap_or(path)).copied()
+/// What a [`TypeEngine`] looked like when a snapshot of it was taken
+#[derive(Debug, Clone, Copy)]
+pub struct Snapshot {
+    types: VecSnapshot,
+    unified: union_find::Snapshot,
+    interned: MapSnapshot,
+    def_types: MapSnapshot,
+    expr_types: MapSnapshot,
+    local_types: MapSnapshot,
+    parameter_names: MapSnapshot,
+    field_visibilities: MapSnapshot,
+    field_types: MapSnapshot,
+    resolver: resolve::Snapshot,
+}
+
+/// The ids of the types every program can use
+#[derive(Debug, Clone, Copy, Default)]
+struct Builtins {
+    integer: TypeId,
+    bool: TypeId,
+    unit: TypeId,
+    error: TypeId,
+    never: TypeId,
+}
+
+/// The contents of a type that's the same whenever its contents are, unlike
+/// structs and aliases, which are distinct for each definition
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum TypeKey {
+    Bool,
+    Box(TypeId),
+    Error,
+    Function(Vec<TypeId>, TypeId),
+    Integer,
+    Never,
+    Unit,
+}
+
+impl TypeKey {
+    fn of(info: &TypeInfo, unified: &UnionFind) -> Option<Self> {
+        match info {
+            TypeInfo::Bool => Some(TypeKey::Bool),
+            TypeInfo::Box(boxed) => Some(TypeKey::Box(unified.find(*boxed))),
+            TypeInfo::Error => Some(TypeKey::Error),
+            TypeInfo::Function { parameters, return_type } => Some(TypeKey::Function(
+                parameters.iter().map(|&id| unified.find(id)).collect(),
+                unified.find(*return_type),
+            )),
+            TypeInfo::Integer => Some(TypeKey::Integer),
+            TypeInfo::Never => Some(TypeKey::Never),
+            TypeInfo::Unit => Some(TypeKey::Unit),
+            TypeInfo::Alias { .. } | TypeInfo::Infer | TypeInfo::Struct { .. } => None,
+        }
     }
+}
 
-    fn integer(&self) -> TypeId {
-        0
+/// Removes the types of an expression and the locals within it, but not of
+/// the items within it
+struct Forgetter<'a> {
+    engine: &'a mut TypeEngine,
+}
+
+impl Visitor for Forgetter<'_> {
+    fn visit_item(&mut self, _: &Item) {
+        // Items stay around after the expression they're in is evaluated
     }
 
-    fn bool(&self) -> TypeId {
-        1
+    fn visit_local(&mut self, local: &Local) {
+        self.engine.local_types.remove(&local.local_id());
+        walk::local(self, local);
     }
 
-    fn unit(&self) -> TypeId {
-        2
+    fn visit_expression(&mut self, expression: &Expression) {
+        self.engine.expr_types.remove(&expression.expr_id());
+        walk::expression(self, expression);
     }
 }
 
 impl Default for TypeEngine {
     fn default() -> Self {
-        Self {
-            types: vec![TypeInfo::Integer, TypeInfo::Bool, TypeInfo::Unit],
-            name_map: HashMap::new(),
+        let mut engine = Self {
+            types: SnapshotVec::new(),
+            unified: UnionFind::new(),
+            interned: SnapshotMap::new(),
+            builtins: Builtins::default(),
+            parameter_names: SnapshotMap::new(),
+            inferred: Vec::new(),
+            diverging: Vec::new(),
+            diverges: false,
+            def_types: SnapshotMap::new(),
+            expr_types: SnapshotMap::new(),
+            local_types: SnapshotMap::new(),
+            field_visibilities: SnapshotMap::new(),
+            field_types: SnapshotMap::new(),
+            resolver: Resolver::new(),
             current_path: Path::new(),
-            unnamable_count: 0,
-        }
+            current_file: None,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        };
+
+        engine.builtins = Builtins {
+            integer: engine.intern(TypeInfo::Integer),
+            bool: engine.intern(TypeInfo::Bool),
+            unit: engine.intern(TypeInfo::Unit),
+            error: engine.intern(TypeInfo::Error),
+            never: engine.intern(TypeInfo::Never),
+        };
+
+        engine
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum TypeInfo {
+    /// `type Name = Type;`, kept distinct from the aliased type so diagnostics
+    /// can refer to the alias
+    Alias {
+        full_path: Path,
+        aliased: TypeId,
+    },
     Bool,
-    Function { parameters: Vec<(Identifier, TypeId)>, return_type: TypeId },
+    /// `Box<T>`, a value of type `T` on the heap
+    Box(TypeId),
+    /// The type of something that failed to typecheck, which unifies with
+    /// every type so one error doesn't cause others
+    Error,
+    /// Functions are typed by their parameters' types alone, so functions
+    /// with the same signature share a type
+    Function {
+        parameters: Vec<TypeId>,
+        return_type: TypeId,
+    },
     Infer,
     Integer,
-    Ref(TypeId),
-    Struct { full_path: Path, members: HashMap<Identifier, TypeId> },
+    /// `!`, the type of expressions that never finish
+    Never,
+    Struct {
+        full_path: Path,
+        members: HashMap<Identifier, TypeId>,
+    },
     Unit,
 }
 
 impl TypeInfo {
+    /// The types this type is made of
+    fn children(&self) -> impl Iterator<Item = TypeId> + '_ {
+        let ids: Box<dyn Iterator<Item = TypeId>> = match self {
+            TypeInfo::Alias { aliased, .. } | TypeInfo::Box(aliased) => Box::new(std::iter::once(*aliased)),
+            TypeInfo::Function { parameters, return_type } => {
+                Box::new(parameters.iter().copied().chain(std::iter::once(*return_type)))
+            }
+            TypeInfo::Struct { members, .. } => Box::new(members.values().copied()),
+            TypeInfo::Bool
+            | TypeInfo::Error
+            | TypeInfo::Infer
+            | TypeInfo::Integer
+            | TypeInfo::Never
+            | TypeInfo::Unit => Box::new(std::iter::empty()),
+        };
+
+        ids
+    }
+
+    /// Returns this type with `f` applied to the types it's made of
+    fn map_ids(&self, mut f: impl FnMut(TypeId) -> TypeId) -> Self {
+        match self {
+            TypeInfo::Alias { full_path, aliased } => {
+                TypeInfo::Alias { full_path: full_path.clone(), aliased: f(*aliased) }
+            }
+            TypeInfo::Box(boxed) => TypeInfo::Box(f(*boxed)),
+            TypeInfo::Function { parameters, return_type } => TypeInfo::Function {
+                parameters: parameters.iter().map(|&id| f(id)).collect(),
+                return_type: f(*return_type),
+            },
+            TypeInfo::Struct { full_path, members } => TypeInfo::Struct {
+                full_path: full_path.clone(),
+                members: members.iter().map(|(&name, &id)| (name, f(id))).collect(),
+            },
+            TypeInfo::Bool
+            | TypeInfo::Error
+            | TypeInfo::Infer
+            | TypeInfo::Integer
+            | TypeInfo::Never
+            | TypeInfo::Unit => self.clone(),
+        }
+    }
+
     pub fn debug<'a>(&'a self, engine: &'a TypeEngine) -> TypeInfoDebug<'a> {
         TypeInfoDebug { info: self, engine, indent_level: 0 }
     }
@@ -568,12 +1874,17 @@ impl TypeInfo {
     pub fn name(&self, engine: &TypeEngine) -> String {
         match self {
             TypeInfo::Bool => String::from("Bool"),
+            TypeInfo::Box(boxed) => format!("Box<{}>", engine.declared_typeinfo(*boxed).name(engine)),
             TypeInfo::Integer => String::from("Int"),
             TypeInfo::Unit => String::from("Unit"),
+            TypeInfo::Error => String::from("{error}"),
             TypeInfo::Struct { full_path, .. } => full_path.to_string(),
-            TypeInfo::Ref(r) => engine.typeinfo(*r).name(engine),
+            TypeInfo::Alias { full_path, aliased } => {
+                format!("{} (aka {})", full_path, engine.typeinfo(*aliased).name(engine))
+            }
             TypeInfo::Function { .. } => format!("{:?}", self.debug(engine)),
-            info => unreachable!("{:?}", info),
+            TypeInfo::Infer => String::from("_"),
+            TypeInfo::Never => String::from("!"),
         }
     }
 }
@@ -595,18 +1906,17 @@ impl Debug for TypeInfoDebug<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match &self.info {
             TypeInfo::Bool => write!(f, "Bool"),
+            // Not expanded, since a box is how a struct can contain itself
+            TypeInfo::Box(boxed) => write!(f, "Box<{}>", self.engine.declared_typeinfo(*boxed).name(self.engine)),
+            TypeInfo::Error => write!(f, "{{error}}"),
             TypeInfo::Function { parameters, return_type } => {
                 write!(f, "fn(")?;
 
-                if let Some((ident, id)) = parameters.first() {
-                    write!(f, "{}: {}", ident, self.engine.typeinfo(*id).name(self.engine))?;
-                }
+                let parameters: Vec<_> =
+                    parameters.iter().map(|&id| self.engine.declared_typeinfo(id).name(self.engine)).collect();
+                write!(f, "{}", parameters.join(", "))?;
 
-                for (ident, id) in parameters.iter().skip(1) {
-                    write!(f, ", {}: {}", ident, self.engine.typeinfo(*id).name(self.engine))?;
-                }
-
-                write!(f, ") -> {}", self.engine.typeinfo(*return_type).name(self.engine))
+                write!(f, ") -> {}", self.engine.declared_typeinfo(*return_type).name(self.engine))
             }
             TypeInfo::Integer => write!(f, "Int"),
             TypeInfo::Struct { full_path, members, .. } => {
@@ -624,8 +1934,245 @@ impl Debug for TypeInfoDebug<'_> {
                 write!(f, "{:<width$}}}", "", width = self.indent_level * 4)
             }
             TypeInfo::Infer => write!(f, "_"),
+            TypeInfo::Never => write!(f, "!"),
             TypeInfo::Unit => write!(f, "Unit"),
-            TypeInfo::Ref(id) => write!(f, "{:?}", self.engine.typeinfo(*id).debug(self.engine)),
+            TypeInfo::Alias { full_path, aliased } => {
+                write!(f, "type {} = {:?}", full_path, self.engine.typeinfo(*aliased).debug(self.engine))
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codespan::Files;
+
+    /// Typechecks inputs one at a time, the way the REPL does
+    struct Session {
+        engine: TypeEngine,
+        files: Files<String>,
+    }
+
+    impl Session {
+        fn new() -> Self {
+            Self { engine: TypeEngine::new(), files: Files::new() }
+        }
+
+        /// Typechecks the item `source`, returning the errors found in it
+        fn item(&mut self, source: &str) -> Vec<TypeErrorKind> {
+            let file = self.files.add("item", source.to_string());
+            let mut item = Item::convert(&parser::Parser::new(source).item().unwrap());
+            self.engine.resolver_mut().resolve_item(&mut item, file).unwrap();
+            self.engine.set_file(file);
+            self.engine.typecheck_item(&Context::new(), &item);
+
+            self.errors()
+        }
+
+        /// Typechecks the expression `source`, returning its type
+        fn expression(&mut self, source: &str) -> TypeId {
+            let file = self.files.add("expression", source.to_string());
+            let mut expr = Expression::convert(&parser::Parser::new(source).expression().unwrap());
+            self.engine.resolver_mut().resolve_expression(&mut expr, file).unwrap();
+            let expected = self.engine.fresh_infer();
+
+            self.engine.typecheck_expression_from(&Context::new(), &Path::new(), Some(file), &expr, expected)
+        }
+
+        /// Typechecks the expression `source` and then forgets it, collecting
+        /// the garbage it left behind like the REPL does after each input
+        fn input(&mut self, source: &str) -> TypeInfo {
+            let file = self.files.add("input", source.to_string());
+            let mut expr = Expression::convert(&parser::Parser::new(source).expression().unwrap());
+            self.engine.resolver_mut().resolve_expression(&mut expr, file).unwrap();
+            let expected = self.engine.fresh_infer();
+            let id = self.engine.typecheck_expression_from(&Context::new(), &Path::new(), Some(file), &expr, expected);
+            let info = self.engine.typeinfo(id).clone();
+
+            self.engine.forget_expression(&expr);
+            self.engine.collect_garbage();
+            info
+        }
+
+        fn def_type(&self, name: &str) -> TypeId {
+            self.engine.typeid_from_path(&Path::from_identifier(Identifier::new(name))).unwrap()
+        }
+
+        fn errors(&mut self) -> Vec<TypeErrorKind> {
+            self.engine.take_errors().into_iter().map(|error| error.kind).collect()
+        }
+
+        fn name(&self, id: TypeId) -> String {
+            self.engine.typeinfo(id).name(&self.engine)
+        }
+    }
+
+    #[test]
+    fn unifying_a_type_variable_links_it() {
+        let mut engine = TypeEngine::new();
+        let (a, b) = (engine.fresh_infer(), engine.fresh_infer());
+
+        engine.unify(a, b).unwrap();
+        engine.unify(b, engine.integer()).unwrap();
+        assert!(matches!(engine.typeinfo(a), TypeInfo::Integer));
+
+        let error = engine.unify(a, engine.bool()).unwrap_err();
+        assert!(matches!(error, TypeErrorKind::MismatchedTypes { wanted: TypeInfo::Integer, have: TypeInfo::Bool }));
+    }
+
+    #[test]
+    fn occurs_check_rejects_infinite_types() {
+        let mut engine = TypeEngine::new();
+
+        let var = engine.fresh_infer();
+        let boxed = engine.box_type(var);
+        assert!(matches!(engine.unify(var, boxed), Err(TypeErrorKind::InfiniteType(TypeInfo::Box(_)))));
+
+        let var = engine.fresh_infer();
+        let function = engine.function_type(vec![var], engine.unit());
+        assert!(matches!(engine.unify(function, var), Err(TypeErrorKind::InfiniteType(TypeInfo::Function { .. }))));
+    }
+
+    #[test]
+    fn error_type_unifies_with_every_type() {
+        let mut engine = TypeEngine::new();
+
+        assert!(engine.unify(engine.error_type(), engine.integer()).is_ok());
+        assert!(engine.unify(engine.bool(), engine.error_type()).is_ok());
+    }
+
+    #[test]
+    fn if_branches_have_the_same_type() {
+        let mut session = Session::new();
+
+        let id = session.expression("if true { 1 } else { 2 }");
+        assert_eq!(session.name(id), "Int");
+        assert!(session.errors().is_empty());
+
+        session.expression("if true { 1 } else { false }");
+        let errors = session.errors();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0], TypeErrorKind::MismatchedTypes { .. }));
+    }
+
+    #[test]
+    fn if_branches_are_checked_in_order() {
+        let mut session = Session::new();
+
+        // The first branch decides the type, so the `else` block is the one
+        // that's wrong
+        session.expression("if true { 1 } else if false { 2 } else { false }");
+        let errors = session.errors();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            TypeErrorKind::MismatchedTypes { wanted: TypeInfo::Integer, have: TypeInfo::Bool }
+        ));
+
+        session.expression("if true { 1 } else if false { Unit } else { 2 }");
+        let errors = session.errors();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            TypeErrorKind::MismatchedTypes { wanted: TypeInfo::Integer, have: TypeInfo::Unit }
+        ));
+    }
+
+    #[test]
+    fn never_stands_in_for_every_type() {
+        let mut session = Session::new();
+        assert!(session.item("fn diverge() -> ! { diverge() }").is_empty());
+
+        let id = session.expression("if true { diverge() } else { 1 }");
+        assert_eq!(session.name(id), "Int");
+
+        let id = session.expression("if false { 1 } else { diverge() }");
+        assert_eq!(session.name(id), "Int");
+
+        let id = session.expression("{ let x: Int = diverge(); x == 1 }");
+        assert_eq!(session.name(id), "Bool");
+        assert!(session.errors().is_empty());
+    }
+
+    #[test]
+    fn blocks_diverge_through_their_statements() {
+        let mut session = Session::new();
+        session.item("fn diverge() -> ! { diverge() }");
+
+        let id = session.expression("{ diverge(); }");
+        assert_eq!(session.name(id), "!");
+        assert!(session.engine.take_warnings().is_empty());
+
+        // The chain only diverges if every branch does
+        let id = session.expression("{ if true { diverge() } else { diverge() }; }");
+        assert_eq!(session.name(id), "!");
+        let id = session.expression("{ if true { diverge() } else { 1 }; }");
+        assert_eq!(session.name(id), "Unit");
+
+        session.expression("{ diverge(); 1 }");
+        let warnings = session.engine.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(warnings[0].kind, TypeErrorKind::UnreachableExpression));
+        assert!(session.errors().is_empty());
+    }
+
+    #[test]
+    fn errors_are_not_reported_again_through_the_error_type() {
+        let mut session = Session::new();
+
+        let id = session.expression("{ let x = 1 + true; (x + 1) == false }");
+        let errors = session.errors();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(matches!(errors[0], TypeErrorKind::UnknownBinOp { .. }));
+        assert_eq!(session.name(id), "Bool");
+    }
+
+    #[test]
+    fn every_error_in_an_item_is_reported() {
+        let mut session = Session::new();
+
+        let errors = session.item("fn f() -> Int { let a: Int = true; let b: Int = Unit; a }");
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors.iter().all(|error| matches!(error, TypeErrorKind::MismatchedTypes { .. })));
+    }
+
+    #[test]
+    fn functions_with_the_same_signature_share_a_type() {
+        let mut session = Session::new();
+        session.item("fn f(a: Int) -> Int { a }");
+        session.item("fn g(b: Int) -> Int { b }");
+        session.item("fn h(c: Int) -> Int { c }");
+
+        let (f, g, h) = (session.def_type("f"), session.def_type("g"), session.def_type("h"));
+        assert_eq!(f, g);
+        assert_eq!(g, h);
+        assert_eq!(session.name(f), "fn(Int) -> Int");
+    }
+
+    #[test]
+    fn garbage_collection_renumbers_types_consistently() {
+        let mut session = Session::new();
+        session.item("struct S { x: Int }");
+        session.item("fn f(s: S) -> Int { s.x }");
+        session.engine.collect_garbage();
+        let live = session.engine.types.len();
+
+        for _ in 0..3 {
+            let info = session.input("{ let s = S { x: 1 }; let g = f; g(s) + 1 }");
+            assert!(matches!(info, TypeInfo::Integer));
+            assert_eq!(session.engine.types.len(), live);
+        }
+
+        // Ids held by the engine were renumbered along with the types
+        let (s, f) = (session.def_type("S"), session.def_type("f"));
+        assert_eq!(session.name(f), "fn(S) -> Int");
+        assert!(matches!(session.engine.typeinfo(session.engine.integer()), TypeInfo::Integer));
+        assert!(matches!(session.engine.typeinfo(session.engine.never()), TypeInfo::Never));
+
+        // Interned types are found again under their new ids
+        let function = session.engine.function_type(vec![s], session.engine.integer());
+        assert_eq!(function, f);
+        assert_eq!(session.engine.types.len(), live);
+    }
+}
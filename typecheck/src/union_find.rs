@@ -0,0 +1,145 @@
+use hir::snapshot::{SnapshotVec, VecSnapshot};
+
+/// Disjoint sets of indices, used to track which types have been unified with
+/// each other. Each set is represented by one of its members, its root.
+#[derive(Debug, Clone, Default)]
+pub struct UnionFind {
+    /// Paths aren't compressed while finding roots, so that unions can be
+    /// undone. Union by rank keeps the trees shallow regardless.
+    parents: SnapshotVec<usize>,
+    /// An upper bound on the height of the tree below each root
+    ranks: SnapshotVec<u8>,
+}
+
+/// What a [`UnionFind`] looked like when a snapshot of it was taken
+#[derive(Clone, Copy, Debug)]
+pub struct Snapshot {
+    parents: VecSnapshot,
+    ranks: VecSnapshot,
+}
+
+impl UnionFind {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a new set containing only the returned index
+    pub fn push(&mut self) -> usize {
+        let index = self.parents.len();
+        self.parents.push(index);
+        self.ranks.push(0);
+
+        index
+    }
+
+    /// Returns the root of the set containing `index`
+    pub fn find(&self, mut index: usize) -> usize {
+        while self.parents[index] != index {
+            index = self.parents[index];
+        }
+
+        index
+    }
+
+    /// Merges the sets containing `a` and `b`, returning the root of the
+    /// merged set, which is the root of one of the two
+    pub fn union(&mut self, a: usize, b: usize) -> usize {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return a;
+        }
+
+        let (root, child) = match self.ranks[a] < self.ranks[b] {
+            true => (b, a),
+            false => (a, b),
+        };
+
+        self.attach(child, root);
+        root
+    }
+
+    /// Merges the set containing `child` into the set whose root is `root`,
+    /// keeping `root` as the root of the merged set
+    pub fn attach(&mut self, child: usize, root: usize) {
+        let child = self.find(child);
+        debug_assert_eq!(self.find(root), root);
+
+        self.parents.set(child, root);
+        self.ranks.set(root, self.ranks[root].max(self.ranks[child] + 1));
+    }
+
+    pub fn snapshot(&mut self) -> Snapshot {
+        Snapshot { parents: self.parents.snapshot(), ranks: self.ranks.snapshot() }
+    }
+
+    /// Undoes every set added and merged since `snapshot` was taken
+    pub fn rollback_to(&mut self, snapshot: Snapshot) {
+        self.parents.rollback_to(snapshot.parents);
+        self.ranks.rollback_to(snapshot.ranks);
+    }
+
+    /// Keeps the sets added and merged since `snapshot` was taken
+    pub fn commit(&mut self, snapshot: Snapshot) {
+        self.parents.commit(snapshot.parents);
+        self.ranks.commit(snapshot.ranks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn union_merges_sets() {
+        let mut sets = UnionFind::new();
+        let (a, b, c) = (sets.push(), sets.push(), sets.push());
+
+        let root = sets.union(a, b);
+        assert!(root == a || root == b);
+        assert_eq!(sets.find(a), sets.find(b));
+        assert_ne!(sets.find(a), sets.find(c));
+
+        sets.union(c, b);
+        assert_eq!(sets.find(a), sets.find(c));
+    }
+
+    #[test]
+    fn union_of_the_same_set_keeps_its_root() {
+        let mut sets = UnionFind::new();
+        let (a, b) = (sets.push(), sets.push());
+
+        let root = sets.union(a, b);
+        assert_eq!(sets.union(b, a), root);
+        assert_eq!(sets.union(a, a), root);
+    }
+
+    #[test]
+    fn attach_keeps_the_given_root() {
+        let mut sets = UnionFind::new();
+        let (a, b, c) = (sets.push(), sets.push(), sets.push());
+
+        // `a` is the taller tree, but the root is chosen by the caller
+        sets.union(a, b);
+        let root = sets.find(a);
+        sets.attach(root, c);
+
+        for index in [a, b, c] {
+            assert_eq!(sets.find(index), c);
+        }
+    }
+
+    #[test]
+    fn rolling_back_splits_merged_sets() {
+        let mut sets = UnionFind::new();
+        let (a, b) = (sets.push(), sets.push());
+
+        let snapshot = sets.snapshot();
+        let c = sets.push();
+        sets.union(a, b);
+        sets.union(b, c);
+        sets.rollback_to(snapshot);
+
+        assert_ne!(sets.find(a), sets.find(b));
+        assert_eq!(sets.push(), c);
+    }
+}